@@ -17,7 +17,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     };
 
     // Create agent with comprehensive system instructions
-    let mut agent = Agent::builder()
+    let agent = Agent::builder()
         .name("customer")
         .description(
             "A helpful multilingual customer service assistant. \
@@ -38,11 +38,17 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             response_template: "Tell the user: Our pricing starts at $49/month for basic, $99/month for professional, and custom pricing for enterprise. Ask if they need more details.".to_string(),
             requires_llm: true,  // LLM will understand and respond in any language
             parameters: vec![],
+            must_mention: vec![],
+            output_schema: None,
+            handoff: None,
+            escalate: false,
         },
         priority: 10,
         tools: vec![],
         parameters: std::collections::HashMap::new(),
+        captures: vec![],
         created_at: chrono::Utc::now(),
+        enabled: true,
     };
 
     agent.add_guideline(pricing_guideline).await?;
@@ -56,11 +62,17 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             response_template: "Tell the user: To cancel your subscription, please contact support@example.com or visit your account settings. Be empathetic and ask if there's anything we can improve.".to_string(),
             requires_llm: true,  // LLM handles any language
             parameters: vec![],
+            must_mention: vec![],
+            output_schema: None,
+            handoff: None,
+            escalate: false,
         },
         priority: 15,
         tools: vec![],
         parameters: std::collections::HashMap::new(),
+        captures: vec![],
         created_at: chrono::Utc::now(),
+        enabled: true,
     };
 
     agent.add_guideline(cancel_guideline).await?;
@@ -74,11 +86,17 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             response_template: "Tell the user: Our support team is available Monday-Friday, 9 AM to 6 PM EST. Offer to help with anything else.".to_string(),
             requires_llm: true,  // LLM handles any language
             parameters: vec![],
+            must_mention: vec![],
+            output_schema: None,
+            handoff: None,
+            escalate: false,
         },
         priority: 5,
         tools: vec![],
         parameters: std::collections::HashMap::new(),
+        captures: vec![],
         created_at: chrono::Utc::now(),
+        enabled: true,
     };
 
     agent.add_guideline(hours_guideline).await?;
@@ -11,10 +11,9 @@
 //! Run with: cargo run --example customer_complaint
 
 use talk::{
-    Agent, AgentConfig, Journey, JourneyStep, LogLevel, OpenAIProvider, StepId, Transition,
+    Agent, AgentConfig, Journey, JourneyStep, OpenAIProvider, StepId, Transition,
     TransitionCondition,
 };
-use std::time::Duration;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -35,7 +34,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
 
     // Create customer service agent
-    let mut agent = Agent::builder()
+    let agent = Agent::builder()
         .name("Customer Service Agent")
         .description("An empathetic AI agent that handles customer complaints efficiently.")
         .provider(Box::new(
@@ -43,10 +42,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .with_temperature(0.7),
         ))
         .config(AgentConfig {
-            max_context_messages: 100,
-            default_tool_timeout: Duration::from_secs(30),
-            enable_explainability: true,
-            log_level: LogLevel::Info,
+            enable_llm_journey_prompts: true,
+            ..Default::default()
         })
         .build()?;
 
@@ -76,6 +73,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     next_step: identify_issue_id,
                 }],
                 actions: vec![],
+                captures: vec![],
             },
             // Step 2: Identify complaint type
             JourneyStep {
@@ -88,6 +86,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     next_step: assess_urgency_id,
                 }],
                 actions: vec!["categorize_complaint".to_string()],
+                captures: vec![],
             },
             // Step 3: Fork - Assess urgency
             JourneyStep {
@@ -108,6 +107,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     },
                 ],
                 actions: vec!["evaluate_urgency".to_string()],
+                captures: vec![],
             },
             // Step 4a: Urgent escalation (branch for urgent cases)
             JourneyStep {
@@ -120,6 +120,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     next_step: confirm_satisfaction_id,
                 }],
                 actions: vec!["escalate_to_specialist".to_string(), "create_urgent_ticket".to_string()],
+                captures: vec![],
             },
             // Step 5: Collect detailed information (merge point)
             JourneyStep {
@@ -132,6 +133,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     next_step: propose_solution_id,
                 }],
                 actions: vec!["extract_details".to_string()],
+                captures: vec![],
             },
             // Step 6: Propose solution
             JourneyStep {
@@ -144,6 +146,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     next_step: confirm_satisfaction_id,
                 }],
                 actions: vec!["generate_solution".to_string(), "create_ticket".to_string()],
+                captures: vec![],
             },
             // Step 7: Confirm satisfaction (final step)
             JourneyStep {
@@ -153,14 +156,17 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 expected_response: Some("(yes|no|thanks|thank you|all set|nothing else)".to_string()),
                 transitions: vec![], // Final step
                 actions: vec!["record_satisfaction".to_string(), "close_ticket".to_string()],
+                captures: vec![],
             },
         ],
         initial_step: greet_customer_id,
         current_step: None,
         created_at: chrono::Utc::now(),
+        enabled: true,
     };
 
     // Register the journey with the agent
+    let steps = complaint_journey.steps.clone();
     let journey_id = agent.add_journey(complaint_journey).await?;
     println!("✅ Complaint handling journey registered\n");
 
@@ -176,7 +182,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Simulate user responses - Normal urgency case
     println!("=== Scenario 1: Normal Urgency ===\n");
-    let normal_responses = vec![
+    let normal_responses = [
         "My product arrived damaged",
         "Order #12345. It's a product quality issue",  // Provide order number as requested
         "It's not urgent, but I'd like it resolved",
@@ -192,27 +198,20 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
         println!("👤 Customer: {}", response);
 
-        // Process the journey step
-        let next_step = agent
-            .process_journey_step(&session_id, response)
+        // Process the message - this advances the journey and renders the step prompt
+        let llm_response = agent
+            .process_message(session_id, response.to_string())
             .await?;
 
-        println!("\n📍 Journey Step: {}", next_step.name);
-
-        // Get real LLM response
-        let llm_response = if !response.is_empty() {
-            agent.process_message(session_id.clone(), response.to_string()).await?
-        } else {
-            talk::AgentResponse {
-                message: next_step.prompt.clone(),
-                matched_guideline: None,
-                tools_used: vec![],
-                journey_step: Some(next_step.id),
-                context_updates: std::collections::HashMap::new(),
-                explanation: None,
-            }
+        let Some(next_step) = llm_response
+            .journey_step
+            .and_then(|step_id| steps.iter().find(|s| s.id == step_id))
+        else {
+            println!("💬 Agent: {}", llm_response.message);
+            break;
         };
 
+        println!("\n📍 Journey Step: {}", next_step.name);
         println!("💬 Agent: {}", llm_response.message);
 
         // Get updated journey state
@@ -14,10 +14,9 @@
 //! Run with: cargo run --example onboarding_journey
 
 use talk::{
-    Agent, AgentConfig, Journey, JourneyStep, LogLevel, OpenAIProvider, StepId, Transition,
+    Agent, AgentConfig, Journey, JourneyStep, OpenAIProvider, StepId, Transition,
     TransitionCondition,
 };
-use std::time::Duration;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -40,7 +39,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
 
     // Create agent with OpenAI provider
-    let mut agent = Agent::builder()
+    let agent = Agent::builder()
         .name("Flight Booking Agent")
         .description("An AI travel agent that helps customers book flights through a guided journey.")
         .provider(Box::new(
@@ -48,10 +47,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .with_temperature(0.7),
         ))
         .config(AgentConfig {
-            max_context_messages: 100,
-            default_tool_timeout: Duration::from_secs(30),
-            enable_explainability: true,
-            log_level: LogLevel::Info,
+            enable_llm_journey_prompts: true,
+            ..Default::default()
         })
         .build()?;
 
@@ -80,6 +77,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     next_step: check_destination_fork_id,
                 }],
                 actions: vec!["extract_destination".to_string()],
+                captures: vec![],
             },
             // Step 2: Fork - Check if destination is clear
             JourneyStep {
@@ -100,6 +98,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     },
                 ],
                 actions: vec!["validate_destination".to_string()],
+                captures: vec![],
             },
             // Step 3a: Suggest destinations (branch for unclear destination)
             JourneyStep {
@@ -112,6 +111,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     next_step: ask_dates_id,
                 }],
                 actions: vec!["store_destination".to_string()],
+                captures: vec![],
             },
             // Step 4: Ask travel dates (merge point)
             JourneyStep {
@@ -124,6 +124,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     next_step: search_flights_id,
                 }],
                 actions: vec!["extract_dates".to_string()],
+                captures: vec![],
             },
             // Step 5: Search flights (Tool state)
             JourneyStep {
@@ -136,6 +137,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     next_step: confirm_booking_id,
                 }],
                 actions: vec!["search_flights_tool".to_string()],
+                captures: vec![],
             },
             // Step 6: Confirm booking (final step)
             JourneyStep {
@@ -145,14 +147,17 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 expected_response: Some("(yes|confirm|book|proceed)".to_string()),
                 transitions: vec![], // Final step
                 actions: vec!["confirm_booking_tool".to_string()],
+                captures: vec![],
             },
         ],
         initial_step: ask_destination_id,
         current_step: None,
         created_at: chrono::Utc::now(),
+        enabled: true,
     };
 
     // Register the journey with the agent
+    let steps = flight_booking_journey.steps.clone();
     let journey_id = agent.add_journey(flight_booking_journey).await?;
     println!("✅ Flight booking journey registered\n");
 
@@ -167,7 +172,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("   Is Complete: {}\n", state.is_complete);
 
     // Simulate user responses through the flight booking journey
-    let user_responses = vec![
+    let user_responses = [
         "I'd like to go to Paris",           // Clear destination
         "Leaving June 15th, returning June 22nd",  // Dates
         "",                                   // Auto-transition for search
@@ -181,29 +186,20 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
         println!("👤 사용자: {}", response);
 
-        // Process the journey step to get transition logic
-        let next_step = agent
-            .process_journey_step(&session_id, response)
+        // Process the message - this advances the journey and renders the step prompt
+        let llm_response = agent
+            .process_message(session_id, response.to_string())
             .await?;
 
-        println!("\n📍 Journey 단계: {}", next_step.name);
-
-        // Get real LLM response using the journey prompt as context
-        let llm_response = if !response.is_empty() {
-            // Process message through LLM with journey context
-            agent.process_message(session_id.clone(), response.to_string()).await?
-        } else {
-            // For empty responses (auto-transitions), use the journey prompt
-            talk::AgentResponse {
-                message: next_step.prompt.clone(),
-                matched_guideline: None,
-                tools_used: vec![],
-                journey_step: Some(next_step.id),
-                context_updates: std::collections::HashMap::new(),
-                explanation: None,
-            }
+        let Some(next_step) = llm_response
+            .journey_step
+            .and_then(|step_id| steps.iter().find(|s| s.id == step_id))
+        else {
+            println!("💬 에이전트: {}", llm_response.message);
+            break;
         };
 
+        println!("\n📍 Journey 단계: {}", next_step.name);
         println!("💬 에이전트: {}", llm_response.message);
 
         // Get updated journey state
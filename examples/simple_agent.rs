@@ -19,7 +19,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let provider = OpenAIProvider::new("", "gpt-4");
 
     // Create agent with mock provider
-    let mut agent = Agent::builder()
+    let agent = Agent::builder()
         .name("Customer Support Agent")
         .description("A helpful customer support assistant")
         .provider(Box::new(provider))
@@ -28,6 +28,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             default_tool_timeout: Duration::from_secs(30),
             enable_explainability: true,
             log_level: LogLevel::Info,
+            ..Default::default()
         })
         .build()?;
 
@@ -41,11 +42,17 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             response_template: "Our pricing starts at $49/month for the Basic plan, $99/month for Pro, and $199/month for Enterprise. All plans include a 14-day free trial!".to_string(),
             requires_llm: false,
             parameters: vec![],
+            must_mention: vec![],
+            output_schema: None,
+            handoff: None,
+            escalate: false,
         },
         priority: 10,
         tools: vec![],
         parameters: HashMap::new(),
+        captures: vec![],
         created_at: chrono::Utc::now(),
+        enabled: true,
     };
 
     agent.add_guideline(pricing_guideline).await?;
@@ -59,11 +66,17 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             response_template: "I'm here to help! I can assist you with: pricing information, product features, technical support, and account management. What would you like to know?".to_string(),
             requires_llm: false,
             parameters: vec![],
+            must_mention: vec![],
+            output_schema: None,
+            handoff: None,
+            escalate: false,
         },
         priority: 5,
         tools: vec![],
         parameters: HashMap::new(),
+        captures: vec![],
         created_at: chrono::Utc::now(),
+        enabled: true,
     };
 
     agent.add_guideline(support_guideline).await?;
@@ -78,11 +91,17 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .to_string(),
             requires_llm: false,
             parameters: vec![],
+            must_mention: vec![],
+            output_schema: None,
+            handoff: None,
+            escalate: false,
         },
         priority: 3,
         tools: vec![],
         parameters: HashMap::new(),
+        captures: vec![],
         created_at: chrono::Utc::now(),
+        enabled: true,
     };
 
     let sample_guideline = Guideline {
@@ -92,11 +111,17 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             response_template: "This is a sample guideline response.".to_string(),
             requires_llm: false,
             parameters: vec![],
+            must_mention: vec![],
+            output_schema: None,
+            handoff: None,
+            escalate: false,
         },
         priority: 1,
         tools: vec![],
         parameters: HashMap::new(),
+        captures: vec![],
         created_at: chrono::Utc::now(),
+        enabled: true,
     };
     agent.add_guideline(sample_guideline).await?;
 
@@ -108,7 +133,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("✓ Session created: {}\n", session_id);
 
     // Test different messages
-    let test_messages = vec![
+    let test_messages = [
         "Hello!",
         "What is your pricing?",
         "I need help with my account",
@@ -106,7 +106,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     };
 
     // Create agent with configuration
-    let mut agent = Agent::builder()
+    let agent = Agent::builder()
         .name("Weather Assistant")
         .description("A helpful assistant that provides weather information")
         .provider(Box::new(provider))
@@ -115,6 +115,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             default_tool_timeout: Duration::from_secs(30),
             enable_explainability: true,
             log_level: talk::LogLevel::Info,
+            ..Default::default()
         })
         .build()?;
 
@@ -132,11 +133,17 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             response_template: "Let me check the weather for you.".to_string(),
             requires_llm: true,
             parameters: vec!["city".to_string()],
+            must_mention: vec![],
+            output_schema: None,
+            handoff: None,
+            escalate: false,
         },
         priority: 10,
         tools: vec![tool_id],
         parameters: HashMap::new(),
+        captures: vec![],
         created_at: chrono::Utc::now(),
+        enabled: true,
     };
 
     agent.add_guideline(weather_guideline).await?;
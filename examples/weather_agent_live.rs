@@ -212,7 +212,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("✅ API keys configured\n");
 
     // Create agent with configuration
-    let mut agent = Agent::builder()
+    let agent = Agent::builder()
         .name("Weather Assistant")
         .description("A helpful assistant that provides real-time weather information")
         .provider(provider)
@@ -221,6 +221,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             default_tool_timeout: Duration::from_secs(30),
             enable_explainability: true,
             log_level: talk::LogLevel::Info,
+            ..Default::default()
         })
         .build()?;
 
@@ -238,11 +239,17 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             response_template: "Let me check the current weather for you.".to_string(),
             requires_llm: true,
             parameters: vec!["city".to_string()],
+            must_mention: vec![],
+            output_schema: None,
+            handoff: None,
+            escalate: false,
         },
         priority: 10,
         tools: vec![tool_id],
         parameters: HashMap::new(),
+        captures: vec![],
         created_at: chrono::Utc::now(),
+        enabled: true,
     };
 
     agent.add_guideline(weather_guideline).await?;
@@ -281,7 +288,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     ];
 
     let mut correct_matches = 0;
-    let mut total_cases = test_cases.len();
+    let total_cases = test_cases.len();
 
     for (i, (user_message, should_match, description)) in test_cases.iter().enumerate() {
         if i > 0 {
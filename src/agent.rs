@@ -3,49 +3,83 @@
 // This module implements the main Agent struct that orchestrates guidelines,
 // tools, journeys, and LLM interactions.
 
-use crate::context::{Context, Message};
+use crate::context::{CaptureSource, Context, ContextVariable, Message, VariableCapture};
+use crate::definition::AgentDefinition;
 use crate::error::{AgentError, Result};
-use crate::guideline::{
-    DefaultGuidelineMatcher, Guideline, GuidelineAction, GuidelineCondition, GuidelineMatch,
-    GuidelineMatcher,
-};
+use crate::fallback::{FallbackPolicy, FallbackStrategy};
+use crate::guardrail::Guardrails;
+use crate::guideline::{DefaultGuidelineMatcher, Guideline, GuidelineMatch, GuidelineMatcher};
+use crate::hook::{AgentHook, HookAction, HookContext};
 use crate::journey::{DefaultJourneyManager, Journey, JourneyManager, JourneyState, JourneyStep};
-use crate::provider::LLMProvider;
+use crate::metrics::Metrics;
+use crate::provider::{model_price, LLMProvider, ModelPrice, TokenUsage};
 use crate::session::{Session, SessionStatus};
 use crate::storage::SessionStore;
-use crate::tool::{Tool, ToolRegistry};
-use crate::types::{AgentId, GuidelineId, JourneyId, SessionId, StepId, ToolId};
+use crate::template::{MissingVariablePolicy, TemplateContext};
+use crate::tool::{Tool, ToolDefinition, ToolRegistry, ToolResult};
+use crate::trace::{
+    trace_candidates, DecisionTrace, LlmCallKind, LlmCallTrace, ToolCallTrace, DECISION_TRACE_KEY,
+};
+use crate::types::{AgentId, GuidelineId, JourneyId, MessageId, SessionId, StepId, ToolId};
 use chrono::{DateTime, Utc};
+use futures::channel::mpsc;
+use futures::{future, stream, Stream, StreamExt};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::future::Future;
+use std::path::Path;
+use std::pin::Pin;
 use std::sync::Arc;
-use std::time::Duration;
-use tokio::sync::RwLock;
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, OwnedMutexGuard, RwLock};
 use tracing::{debug, info, trace, warn};
 
 /// Log level for agent operations
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum LogLevel {
     Trace,
     Debug,
+    #[default]
     Info,
     Warn,
     Error,
 }
 
-impl Default for LogLevel {
-    fn default() -> Self {
-        Self::Info
-    }
+/// How matched guidelines are selected for a response
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum GuidelineSelection {
+    /// Apply only the highest-priority match
+    #[default]
+    Best,
+    /// Apply every match at or above both thresholds together
+    ///
+    /// When more than one guideline applies, their actions are combined into
+    /// the LLM system prompt and all of their tools are run.
+    Compose {
+        min_relevance: f32,
+        min_priority: i32,
+    },
 }
 
 /// Agent configuration
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AgentConfig {
+    /// Maximum messages kept in a session's history
+    ///
+    /// Older messages are folded into the context's rolling summary.
     #[serde(default = "default_max_context_messages")]
     pub max_context_messages: usize,
 
+    /// Token budget for the history and summary sent to the LLM
+    ///
+    /// Defaults to the provider's context window minus the tokens reserved for
+    /// the response. With neither, only `max_context_messages` applies.
+    #[serde(default)]
+    pub context_token_budget: Option<usize>,
+
     #[serde(
         default = "default_tool_timeout",
         serialize_with = "serialize_duration",
@@ -56,22 +90,103 @@ pub struct AgentConfig {
     #[serde(default = "default_enable_explainability")]
     pub enable_explainability: bool,
 
+    /// Render journey step prompts through the LLM instead of verbatim
+    #[serde(default)]
+    pub enable_llm_journey_prompts: bool,
+
+    /// Maximum rounds of native LLM tool calls per message (0 disables them)
+    #[serde(default = "default_max_tool_iterations")]
+    pub max_tool_iterations: usize,
+
+    /// How unresolved placeholders in response templates are handled
+    #[serde(default)]
+    pub missing_template_variables: MissingVariablePolicy,
+
+    /// How matched guidelines are selected for a response
+    #[serde(default)]
+    pub guideline_selection: GuidelineSelection,
+
+    /// Hand a session to a human operator when the best guideline match's
+    /// confidence is below this (no match counts as 0.0)
+    #[serde(default)]
+    pub escalation_threshold: Option<f32>,
+
+    /// Reply sent when a session is escalated for low confidence
+    #[serde(default = "default_escalation_message")]
+    pub escalation_message: String,
+
+    /// Tokens a session may use before it switches to template-only responses
+    ///
+    /// Once a session's `token_usage` reaches the budget, the LLM is no longer
+    /// called: guidelines answer with their response templates and journey
+    /// steps with their prompts.
+    #[serde(default)]
+    pub session_token_budget: Option<u32>,
+
+    /// Token prices by model name or model name prefix, used to cost LLM calls
+    #[serde(default)]
+    pub model_prices: HashMap<String, ModelPrice>,
+
+    /// Expire sessions after this long without a message
+    ///
+    /// Every processed message moves the session's `expires_at` to this long
+    /// from now. Expired sessions reject messages with `SessionExpired`.
+    #[serde(
+        default,
+        serialize_with = "serialize_optional_duration",
+        deserialize_with = "deserialize_optional_duration"
+    )]
+    pub session_idle_timeout: Option<Duration>,
+
+    /// How long the session reaper keeps expired sessions before deleting them
+    #[serde(
+        default = "default_session_retention",
+        serialize_with = "serialize_duration",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub session_retention: Duration,
+
+    /// Attach a [`DecisionTrace`] to every response
+    #[serde(default)]
+    pub decision_trace: bool,
+
+    /// Keep each response's decision trace in the metadata of its message
+    ///
+    /// Read it back with [`DecisionTrace::from_message`].
+    #[serde(default)]
+    pub store_decision_trace: bool,
+
     #[serde(default)]
     pub log_level: LogLevel,
 }
 
+fn default_escalation_message() -> String {
+    "Let me connect you with a member of our team.".to_string()
+}
+
 fn default_max_context_messages() -> usize {
     100
 }
 
+/// Tokens reserved for the response when the provider sets no `max_tokens`
+const DEFAULT_RESPONSE_TOKENS: usize = 1024;
+
 fn default_tool_timeout() -> Duration {
     Duration::from_secs(30)
 }
 
+fn default_session_retention() -> Duration {
+    Duration::from_secs(24 * 60 * 60)
+}
+
 fn default_enable_explainability() -> bool {
     true
 }
 
+fn default_max_tool_iterations() -> usize {
+    5
+}
+
 fn serialize_duration<S>(duration: &Duration, serializer: S) -> std::result::Result<S::Ok, S::Error>
 where
     S: serde::Serializer,
@@ -87,17 +202,73 @@ where
     Ok(Duration::from_secs(secs))
 }
 
+fn serialize_optional_duration<S>(
+    duration: &Option<Duration>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    match duration {
+        Some(duration) => serializer.serialize_some(&duration.as_secs()),
+        None => serializer.serialize_none(),
+    }
+}
+
+fn deserialize_optional_duration<'de, D>(
+    deserializer: D,
+) -> std::result::Result<Option<Duration>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let secs = Option::<u64>::deserialize(deserializer)?;
+    Ok(secs.map(Duration::from_secs))
+}
+
 impl Default for AgentConfig {
     fn default() -> Self {
         Self {
             max_context_messages: default_max_context_messages(),
+            context_token_budget: None,
             default_tool_timeout: default_tool_timeout(),
             enable_explainability: default_enable_explainability(),
+            enable_llm_journey_prompts: false,
+            max_tool_iterations: default_max_tool_iterations(),
+            missing_template_variables: MissingVariablePolicy::default(),
+            guideline_selection: GuidelineSelection::default(),
+            escalation_threshold: None,
+            escalation_message: default_escalation_message(),
+            session_token_budget: None,
+            model_prices: HashMap::new(),
+            session_idle_timeout: None,
+            session_retention: default_session_retention(),
+            decision_trace: false,
+            store_decision_trace: false,
             log_level: LogLevel::default(),
         }
     }
 }
 
+/// Options for processing a single message
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MessageOptions {
+    /// Client-supplied key identifying the message across retries
+    #[serde(default)]
+    pub idempotency_key: Option<String>,
+}
+
+impl MessageOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replay the stored response when a message with this key was already processed
+    pub fn with_idempotency_key(mut self, key: impl Into<String>) -> Self {
+        self.idempotency_key = Some(key.into());
+        self
+    }
+}
+
 /// Response from agent processing a message
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AgentResponse {
@@ -107,6 +278,46 @@ pub struct AgentResponse {
     pub journey_step: Option<StepId>,
     pub context_updates: HashMap<String, serde_json::Value>,
     pub explanation: Option<ResponseExplanation>,
+    /// Agent to hand the conversation off to, set by the applied guideline
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub handoff: Option<String>,
+    /// The session is handed to a human operator
+    ///
+    /// While a human is in control, messages are only recorded and `message`
+    /// is empty.
+    #[serde(default)]
+    pub awaiting_human: bool,
+    /// Tokens used by the LLM calls made for this message
+    ///
+    /// Text streamed by `process_message_stream` is not included, since
+    /// providers do not report usage for streams.
+    #[serde(default)]
+    pub usage: TokenUsage,
+    /// Cost of those tokens, priced with `AgentConfig::model_prices`
+    #[serde(default)]
+    pub cost: f64,
+    /// How the response was produced, when `AgentConfig::decision_trace` is set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace: Option<DecisionTrace>,
+}
+
+impl AgentResponse {
+    /// A response with only a message, used when a hook stops processing
+    fn reply(message: String) -> Self {
+        Self {
+            message,
+            matched_guideline: None,
+            tools_used: Vec::new(),
+            journey_step: None,
+            context_updates: HashMap::new(),
+            explanation: None,
+            handoff: None,
+            awaiting_human: false,
+            usage: TokenUsage::default(),
+            cost: 0.0,
+            trace: None,
+        }
+    }
 }
 
 /// Tool execution record
@@ -120,10 +331,170 @@ pub struct ToolExecution {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ResponseExplanation {
     pub guideline_matches: Vec<GuidelineMatch>,
+    /// Guidelines whose actions shaped the response, primary first
+    #[serde(default)]
+    pub applied_guidelines: Vec<GuidelineId>,
     pub reasoning: String,
     pub confidence: f32,
 }
 
+/// Event emitted while an agent processes a message
+///
+/// Returned by [`Agent::process_message_stream`]. The stream always ends with
+/// either [`AgentEvent::Completed`] or an error.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+#[allow(clippy::large_enum_variant)] // one `Completed` per stream
+pub enum AgentEvent {
+    /// A guideline (or the fallback guideline) was selected for the response
+    GuidelineMatched(GuidelineMatch),
+
+    /// The session's active journey moved to a step
+    JourneyAdvanced {
+        step_id: StepId,
+        step_name: String,
+        journey_complete: bool,
+    },
+
+    /// A tool is about to be executed
+    ToolStarted { tool_id: ToolId },
+
+    /// A tool finished executing
+    ToolFinished { tool_id: ToolId, success: bool },
+
+    /// A chunk of the response text
+    TextDelta(String),
+
+    /// The response is complete and has been saved to the session
+    Completed(AgentResponse),
+}
+
+/// Stream of events produced by [`Agent::process_message_stream`]
+pub type AgentEventStream<'a> = Pin<Box<dyn Stream<Item = Result<AgentEvent>> + Send + 'a>>;
+
+type EventSender = mpsc::UnboundedSender<Result<AgentEvent>>;
+
+/// Per-session locks that serialize read-modify-write cycles on a session
+///
+/// Entries are dropped once no task holds or waits for them.
+#[derive(Default)]
+pub(crate) struct SessionLocks {
+    locks: std::sync::Mutex<HashMap<SessionId, Arc<Mutex<()>>>>,
+}
+
+impl SessionLocks {
+    /// Wait until no other task is working on the session
+    pub(crate) async fn lock(&self, session_id: SessionId) -> OwnedMutexGuard<()> {
+        let lock = {
+            let mut locks = self.locks.lock().unwrap_or_else(|e| e.into_inner());
+            locks.retain(|_, lock| Arc::strong_count(lock) > 1);
+            locks.entry(session_id).or_default().clone()
+        };
+        lock.lock_owned().await
+    }
+}
+
+/// Outcome of running a tool through the hook pipeline
+enum ToolRun {
+    Completed(ToolResult, ToolExecution),
+    Failed(AgentError),
+    /// A hook stopped processing with this reply
+    Stopped(String),
+}
+
+/// Match guidelines, dropping disabled ones a custom matcher may still report
+async fn enabled_matches(
+    matcher: &dyn GuidelineMatcher,
+    message: &str,
+    context: &Context,
+) -> Result<Vec<GuidelineMatch>> {
+    let guidelines = matcher.get_guidelines();
+    let mut matches = matcher.match_guidelines(message, context).await?;
+    matches.retain(|m| {
+        guidelines
+            .iter()
+            .find(|g| g.id == m.guideline_id)
+            .is_none_or(|g| g.enabled)
+    });
+    Ok(matches)
+}
+
+/// Reject changes to sessions that were completed or terminated
+fn ensure_not_closed(session: &Session) -> Result<()> {
+    match session.status {
+        SessionStatus::Completed => Err(AgentError::SessionCompleted(session.id)),
+        SessionStatus::Terminated => Err(AgentError::SessionTerminated(session.id)),
+        _ => Ok(()),
+    }
+}
+
+/// Reject messages for sessions that are closed, expired or paused
+fn ensure_accepts_messages(session: &Session) -> Result<()> {
+    ensure_not_closed(session)?;
+    if session.is_expired() {
+        return Err(AgentError::SessionExpired(session.id));
+    }
+    if session.status == SessionStatus::Paused {
+        return Err(AgentError::SessionPaused(session.id));
+    }
+    Ok(())
+}
+
+/// LLM usage and decision trace while processing one message
+struct Turn {
+    /// Tokens the session may still use, if it has a budget
+    budget_left: Option<u32>,
+    usage: TokenUsage,
+    cost: f64,
+    /// Recorded when decision traces are returned or stored
+    trace: Option<DecisionTrace>,
+}
+
+impl Turn {
+    fn new(config: &AgentConfig, session: &Session) -> Self {
+        Self {
+            budget_left: config
+                .session_token_budget
+                .map(|budget| budget.saturating_sub(session.token_usage.total_tokens)),
+            usage: TokenUsage::default(),
+            cost: 0.0,
+            trace: (config.decision_trace || config.store_decision_trace)
+                .then(DecisionTrace::default),
+        }
+    }
+
+    /// The messages to record for an LLM call, if tracing
+    fn prompt(&self, messages: &[Message]) -> Option<Vec<Message>> {
+        self.trace.as_ref().map(|_| messages.to_vec())
+    }
+
+    /// Whether the session's token budget is used up, so only templates may respond
+    fn budget_exhausted(&self) -> bool {
+        self.budget_left
+            .is_some_and(|left| self.usage.total_tokens >= left)
+    }
+}
+
+/// How a message without a selected guideline is answered
+enum Fallback {
+    Respond(Guideline),
+    Journey(JourneyStep),
+}
+
+/// Parse a JSON object from LLM output, tolerating surrounding code fences
+fn parse_json_object(output: &str) -> Option<serde_json::Map<String, serde_json::Value>> {
+    let start = output.find('{')?;
+    let end = output.rfind('}')?;
+    serde_json::from_str(output.get(start..=end)?).ok()
+}
+
+/// Send an event if the caller is streaming
+fn emit(events: Option<&EventSender>, event: AgentEvent) {
+    if let Some(sender) = events {
+        let _ = sender.unbounded_send(Ok(event));
+    }
+}
+
 /// Main Agent struct for creating and managing LLM-based conversational agents.
 ///
 /// An `Agent` orchestrates guidelines, tools, journeys, and LLM interactions to provide
@@ -139,8 +510,8 @@ pub struct ResponseExplanation {
 ///
 /// # #[tokio::main]
 /// # async fn main() -> talk::Result<()> {
-/// let provider = OpenAIProvider::new("api-key".to_string());
-/// let mut agent = Agent::builder()
+/// let provider = OpenAIProvider::new("api-key", "gpt-4");
+/// let agent = Agent::builder()
 ///     .name("Support Bot")
 ///     .description("Customer support agent")
 ///     .provider(Box::new(provider))
@@ -154,11 +525,17 @@ pub struct ResponseExplanation {
 ///         response_template: "How can I help you?".to_string(),
 ///         requires_llm: false,
 ///         parameters: vec![],
+///         must_mention: vec![],
+///         output_schema: None,
+///         handoff: None,
+///         escalate: false,
 ///     },
 ///     priority: 10,
 ///     tools: vec![],
 ///     parameters: Default::default(),
+///     captures: vec![],
 ///     created_at: chrono::Utc::now(),
+///     enabled: true,
 /// };
 /// agent.add_guideline(guideline).await?;
 ///
@@ -178,18 +555,26 @@ pub struct ResponseExplanation {
 /// - **JourneyManager**: Multi-step conversation state machines
 /// - **SessionStore**: Persistent or in-memory session storage
 /// - **LLMProvider**: Pluggable LLM backend (OpenAI, Anthropic)
+/// - **AgentHook**: Middleware run at fixed points while processing a message
+/// - **Guardrails**: Checks on LLM-generated responses, with regeneration
 pub struct Agent {
     id: AgentId,
     name: String,
     description: Option<String>,
     provider: Box<dyn LLMProvider>,
-    guideline_matcher: Arc<RwLock<DefaultGuidelineMatcher>>,
+    guideline_matcher: Arc<RwLock<Box<dyn GuidelineMatcher>>>,
     tool_registry: Arc<ToolRegistry>,
-    journey_manager: Arc<RwLock<DefaultJourneyManager>>,
-    journey_states: Arc<RwLock<HashMap<SessionId, JourneyState>>>,
-    fallback_guideline: Guideline,
+    journey_manager: Arc<RwLock<Box<dyn JourneyManager>>>,
+    /// Held for reading while a message is processed and for writing while
+    /// guidelines, journeys or tools change, so a message never sees half a change
+    rules: RwLock<()>,
+    fallback: FallbackPolicy,
     config: AgentConfig,
     session_store: Arc<dyn SessionStore>,
+    session_locks: SessionLocks,
+    hooks: Vec<Box<dyn AgentHook>>,
+    guardrails: Guardrails,
+    metrics: Arc<Metrics>,
     #[allow(dead_code)]
     created_at: DateTime<Utc>,
     #[allow(dead_code)]
@@ -202,15 +587,132 @@ impl Agent {
         AgentBuilder::new()
     }
 
+    pub fn id(&self) -> AgentId {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    pub fn config(&self) -> &AgentConfig {
+        &self.config
+    }
+
+    /// Metrics recorded by this agent
+    pub fn metrics(&self) -> &Arc<Metrics> {
+        &self.metrics
+    }
+
+    pub(crate) fn session_store(&self) -> &Arc<dyn SessionStore> {
+        &self.session_store
+    }
+
+    /// The guideline this agent would apply to `message`, with its match
+    pub(crate) async fn best_match(
+        &self,
+        message: &str,
+        context: &Context,
+    ) -> Result<Option<(Guideline, GuidelineMatch)>> {
+        let matcher = self.guideline_matcher.read().await;
+        let matches = enabled_matches(matcher.as_ref(), message, context).await?;
+        let Some(best) = matcher.select_best_match(matches).await else {
+            return Ok(None);
+        };
+        Ok(matcher
+            .get_guidelines()
+            .iter()
+            .find(|g| g.id == best.guideline_id)
+            .cloned()
+            .map(|guideline| (guideline, best)))
+    }
+
+    /// Create an agent from a YAML, JSON or TOML definition file
+    ///
+    /// `tools` are registered before the definition's guidelines are added, so
+    /// guidelines can reference them by name. The file must configure a
+    /// provider; to supply one in code, use [`AgentBuilder::from_definition`]
+    /// followed by [`Agent::add_definition`].
+    pub async fn from_config_file(
+        path: impl AsRef<Path>,
+        tools: Vec<Box<dyn Tool>>,
+    ) -> Result<Agent> {
+        let definition = AgentDefinition::from_file(path)?;
+        let tool_names: Vec<&str> = tools.iter().map(|tool| tool.name()).collect();
+        definition.validate(&tool_names)?;
+
+        let agent = AgentBuilder::from_definition(&definition)?.build()?;
+        for tool in tools {
+            agent.add_tool(tool).await?;
+        }
+        agent.add_definition(&definition).await?;
+
+        Ok(agent)
+    }
+
+    /// Add the guidelines and journeys of a definition
+    ///
+    /// Tool references are resolved against the tools already registered with
+    /// the agent. Nothing is added unless the whole definition is valid, and
+    /// messages see either none or all of the definition.
+    pub async fn add_definition(&self, definition: &AgentDefinition) -> Result<()> {
+        let _rules = self.rules.write().await;
+        let tool_ids: HashMap<String, ToolId> = self
+            .tool_registry
+            .list()
+            .await
+            .iter()
+            .map(|tool| (tool.name().to_string(), *tool.id()))
+            .collect();
+        let tool_names: Vec<&str> = tool_ids.keys().map(String::as_str).collect();
+        definition.validate(&tool_names)?;
+
+        let mut manager = self.journey_manager.write().await;
+        for (i, journey) in definition.build_journeys().into_iter().enumerate() {
+            manager
+                .add_journey(journey)
+                .await
+                .map_err(|e| definition.invalid(format!("journeys[{}]", i), e.to_string()))?;
+        }
+        let mut matcher = self.guideline_matcher.write().await;
+        for guideline in definition.build_guidelines(&tool_ids) {
+            matcher.add_guideline(guideline).await?;
+        }
+
+        info!(
+            agent_id = %self.id,
+            guideline_count = definition.guidelines.len(),
+            journey_count = definition.journeys.len(),
+            "Added agent definition"
+        );
+
+        Ok(())
+    }
+
+    /// Find the ID of a journey by name
+    pub async fn find_journey(&self, name: &str) -> Option<JourneyId> {
+        let manager = self.journey_manager.read().await;
+        manager.find_journey(name).map(|journey| journey.id)
+    }
+
     /// Create a new conversation session
     pub async fn create_session(&self) -> Result<SessionId> {
-        let session = Session::new(self.id);
+        let context = Context::with_max_messages(self.config.max_context_messages);
+        let mut session = Session::with_context(self.id, context);
+        if let Some(idle_timeout) = self.config.session_idle_timeout {
+            session.extend_expiration(idle_timeout);
+        }
         let session_id = session.id;
 
         self.session_store
             .create(session)
             .await
-            .map_err(|e| AgentError::Storage(e))?;
+            .map_err(AgentError::Storage)?;
+        self.metrics.session_started();
 
         Ok(session_id)
     }
@@ -220,120 +722,546 @@ impl Agent {
         self.session_store
             .get(session_id)
             .await
-            .map_err(|e| AgentError::Storage(e))
+            .map_err(AgentError::Storage)
     }
 
     /// End a conversation session
+    ///
+    /// Later messages are rejected with `SessionCompleted`.
     pub async fn end_session(&self, session_id: &SessionId) -> Result<()> {
-        let mut session = self
-            .session_store
-            .get(session_id)
+        self.close_session(session_id, SessionStatus::Completed)
+            .await
+    }
+
+    /// Terminate a conversation session
+    ///
+    /// Later messages are rejected with `SessionTerminated`.
+    pub async fn terminate_session(&self, session_id: &SessionId) -> Result<()> {
+        self.close_session(session_id, SessionStatus::Terminated)
             .await
-            .map_err(|e| AgentError::Storage(e))?
-            .ok_or_else(|| AgentError::SessionNotFound(*session_id))?;
+    }
 
-        session.status = SessionStatus::Completed;
+    async fn close_session(&self, session_id: &SessionId, status: SessionStatus) -> Result<()> {
+        let _guard = self.session_locks.lock(*session_id).await;
+        let mut session = self.load_session(session_id).await?;
+
+        let was_closed = matches!(
+            session.status,
+            SessionStatus::Completed | SessionStatus::Terminated
+        );
+        session.status = status;
         session.touch();
 
         self.session_store
             .update(session_id, session)
             .await
-            .map_err(|e| AgentError::Storage(e))
+            .map_err(AgentError::Storage)?;
+        if !was_closed {
+            self.metrics.session_ended();
+        }
+        Ok(())
     }
 
-    /// Add a guideline to the agent
-    pub async fn add_guideline(&mut self, guideline: Guideline) -> Result<GuidelineId> {
-        let mut matcher = self.guideline_matcher.write().await;
-        matcher.add_guideline(guideline).await
+    /// Pause a session
+    ///
+    /// Messages are rejected with `SessionPaused` until the session is resumed.
+    pub async fn pause_session(&self, session_id: &SessionId) -> Result<()> {
+        let _guard = self.session_locks.lock(*session_id).await;
+        let mut session = self.load_session(session_id).await?;
+        ensure_not_closed(&session)?;
+        session.pause();
+
+        self.session_store
+            .update(session_id, session)
+            .await
+            .map_err(AgentError::Storage)
     }
 
-    /// Add a tool to the agent
-    pub async fn add_tool(&self, tool: Box<dyn Tool>) -> Result<ToolId> {
-        info!(
-            agent_id = %self.id,
-            tool_name = tool.name(),
-            "Adding tool to agent"
-        );
+    /// Resume a paused session
+    ///
+    /// Resuming counts as activity, so the idle timeout starts again.
+    pub async fn resume_session(&self, session_id: &SessionId) -> Result<()> {
+        let _guard = self.session_locks.lock(*session_id).await;
+        let mut session = self.load_session(session_id).await?;
+        if session.status != SessionStatus::Paused {
+            return Err(AgentError::InvalidInput(format!(
+                "Session {} is not paused",
+                session_id
+            )));
+        }
+        session.resume();
+        if let Some(idle_timeout) = self.config.session_idle_timeout {
+            session.extend_expiration(idle_timeout);
+        }
 
-        self.tool_registry.register(tool).await
+        self.session_store
+            .update(session_id, session)
+            .await
+            .map_err(AgentError::Storage)
     }
 
-    /// Add a journey to the agent
-    pub async fn add_journey(&mut self, journey: Journey) -> Result<JourneyId> {
-        let mut manager = self.journey_manager.write().await;
-        manager.add_journey(journey).await
+    /// Start a background task that deletes expired sessions every `interval`
+    ///
+    /// Sessions are kept for `AgentConfig::session_retention` after they
+    /// expire, then removed with `SessionStore::cleanup_expired`. The task runs
+    /// until the returned handle is aborted.
+    pub fn spawn_session_reaper(&self, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let store = self.session_store.clone();
+        let retention = self.config.session_retention;
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                match store.cleanup_expired(retention).await {
+                    Ok(0) => {}
+                    Ok(deleted) => info!(deleted, "Deleted expired sessions"),
+                    Err(e) => warn!(error = %e, "Failed to delete expired sessions"),
+                }
+            }
+        })
     }
 
-    /// Start a journey for a session
-    pub async fn start_journey(
+    /// Hand a session to a human operator
+    ///
+    /// Until [`Agent::return_to_agent`] is called, `process_message` only
+    /// records user messages, and the operator replies with
+    /// [`Agent::post_operator_message`].
+    pub async fn escalate_to_human(
         &self,
         session_id: &SessionId,
-        journey_id: &JourneyId,
-    ) -> Result<JourneyState> {
-        let manager = self.journey_manager.read().await;
-        let state = manager.start_journey(session_id, journey_id).await?;
-
-        // Store state in journey_states
-        let mut states = self.journey_states.write().await;
-        states.insert(*session_id, state.clone());
+        reason: impl Into<String>,
+    ) -> Result<()> {
+        let _guard = self.session_locks.lock(*session_id).await;
+        let mut session = self.load_session(session_id).await?;
+        let reason = reason.into();
+        info!(session_id = %session_id, reason = %reason, "Escalating session to a human operator");
+        session.escalate(reason);
 
-        Ok(state)
-    }
-
-    /// Get current journey state for a session
-    pub async fn get_journey_state(&self, session_id: &SessionId) -> Result<Option<JourneyState>> {
-        let states = self.journey_states.read().await;
-        Ok(states.get(session_id).cloned())
+        self.session_store
+            .update(session_id, session)
+            .await
+            .map_err(AgentError::Storage)
     }
 
-    /// Process a journey step with user message
-    pub async fn process_journey_step(
+    /// Post a human operator's message into an escalated session
+    pub async fn post_operator_message(
         &self,
         session_id: &SessionId,
-        message: &str,
-    ) -> Result<JourneyStep> {
-        // Get current state
-        let mut states = self.journey_states.write().await;
-        let state = states
-            .get_mut(session_id)
-            .ok_or_else(|| AgentError::Journey("No active journey for session".to_string()))?;
-
-        let journey_id = state.journey_id;
-        let current_step_id = state.current_step;
+        content: impl Into<String>,
+    ) -> Result<MessageId> {
+        let _guard = self.session_locks.lock(*session_id).await;
+        let mut session = self.load_session(session_id).await?;
+        if !session.is_awaiting_human() {
+            return Err(AgentError::InvalidInput(format!(
+                "Session {} is not awaiting a human operator",
+                session_id
+            )));
+        }
 
-        // Process step through manager
-        let manager = self.journey_manager.read().await;
-        let next_step = manager
-            .process_step(&journey_id, current_step_id, message)
-            .await?;
+        let message = Message::operator(content);
+        let message_id = message.id;
+        session.context.add_message(message);
+        session.touch();
 
-        // Update state
-        state.complete_step(current_step_id);
+        self.session_store
+            .update(session_id, session)
+            .await
+            .map_err(AgentError::Storage)?;
+        Ok(message_id)
+    }
 
-        // Check if we transitioned to a new step or stayed on current (final) step
-        if next_step.id == current_step_id {
-            // No transition - this means we're on the final step
-            state.mark_complete();
-        } else {
-            // Transition to next step
-            state.current_step = next_step.id;
+    /// Hand an escalated session back to the agent
+    pub async fn return_to_agent(&self, session_id: &SessionId) -> Result<()> {
+        let _guard = self.session_locks.lock(*session_id).await;
+        let mut session = self.load_session(session_id).await?;
+        if !session.is_awaiting_human() {
+            return Err(AgentError::InvalidInput(format!(
+                "Session {} is not awaiting a human operator",
+                session_id
+            )));
         }
+        info!(session_id = %session_id, "Returning session to the agent");
+        session.return_to_agent();
 
-        Ok(next_step)
+        self.session_store
+            .update(session_id, session)
+            .await
+            .map_err(AgentError::Storage)
     }
 
-    /// End a journey for a session
-    pub async fn end_journey(&self, session_id: &SessionId) -> Result<()> {
-        let mut states = self.journey_states.write().await;
-        states.remove(session_id);
-        Ok(())
+    /// Add a guideline to the agent
+    pub async fn add_guideline(&self, guideline: Guideline) -> Result<GuidelineId> {
+        let _rules = self.rules.write().await;
+        let mut matcher = self.guideline_matcher.write().await;
+        matcher.add_guideline(guideline).await
     }
 
-    /// Process a user message and generate a response
+    /// Replace the guideline with the same ID
+    pub async fn update_guideline(&self, guideline: Guideline) -> Result<()> {
+        let _rules = self.rules.write().await;
+        let mut matcher = self.guideline_matcher.write().await;
+        if !matcher
+            .get_guidelines()
+            .iter()
+            .any(|g| g.id == guideline.id)
+        {
+            return Err(AgentError::GuidelineNotFound(guideline.id));
+        }
+        info!(agent_id = %self.id, guideline_id = %guideline.id, "Updating guideline");
+        matcher.update_guideline(guideline).await
+    }
+
+    /// Remove a guideline from the agent
+    pub async fn remove_guideline(&self, guideline_id: &GuidelineId) -> Result<()> {
+        let _rules = self.rules.write().await;
+        let mut matcher = self.guideline_matcher.write().await;
+        if !matcher
+            .get_guidelines()
+            .iter()
+            .any(|g| &g.id == guideline_id)
+        {
+            return Err(AgentError::GuidelineNotFound(*guideline_id));
+        }
+        info!(agent_id = %self.id, guideline_id = %guideline_id, "Removing guideline");
+        matcher.remove_guideline(guideline_id).await
+    }
+
+    /// All guidelines of the agent, including disabled ones
+    pub async fn list_guidelines(&self) -> Vec<Guideline> {
+        let matcher = self.guideline_matcher.read().await;
+        matcher.get_guidelines().to_vec()
+    }
+
+    /// Let a disabled guideline match messages again
+    pub async fn enable_guideline(&self, guideline_id: &GuidelineId) -> Result<()> {
+        self.set_guideline_enabled(guideline_id, true).await
+    }
+
+    /// Stop a guideline from matching messages without removing it
+    pub async fn disable_guideline(&self, guideline_id: &GuidelineId) -> Result<()> {
+        self.set_guideline_enabled(guideline_id, false).await
+    }
+
+    async fn set_guideline_enabled(&self, guideline_id: &GuidelineId, enabled: bool) -> Result<()> {
+        let _rules = self.rules.write().await;
+        let mut matcher = self.guideline_matcher.write().await;
+        let mut guideline = matcher
+            .get_guidelines()
+            .iter()
+            .find(|g| &g.id == guideline_id)
+            .cloned()
+            .ok_or(AgentError::GuidelineNotFound(*guideline_id))?;
+        info!(agent_id = %self.id, guideline_id = %guideline_id, enabled = enabled, "Setting guideline state");
+        guideline.enabled = enabled;
+        matcher.update_guideline(guideline).await
+    }
+
+    /// Add a tool to the agent
+    pub async fn add_tool(&self, tool: Box<dyn Tool>) -> Result<ToolId> {
+        info!(
+            agent_id = %self.id,
+            tool_name = tool.name(),
+            "Adding tool to agent"
+        );
+
+        let _rules = self.rules.write().await;
+        self.tool_registry.register(tool).await
+    }
+
+    /// Replace the tool with the same name
+    ///
+    /// Guidelines that used the old tool use the new one, and the tool keeps
+    /// its enabled state. Returns the new tool's ID.
+    pub async fn update_tool(&self, tool: Box<dyn Tool>) -> Result<ToolId> {
+        let _rules = self.rules.write().await;
+        let old = self
+            .tool_registry
+            .get_by_name(tool.name())
+            .await
+            .ok_or_else(|| AgentError::InvalidInput(format!("Unknown tool '{}'", tool.name())))?;
+        let old_id = *old.id();
+        let enabled = self.tool_registry.is_enabled(&old_id).await;
+        info!(agent_id = %self.id, tool_name = tool.name(), "Updating tool");
+
+        self.tool_registry.unregister(&old_id).await?;
+        let new_id = self.tool_registry.register(tool).await?;
+        self.tool_registry.set_enabled(&new_id, enabled).await?;
+
+        let mut matcher = self.guideline_matcher.write().await;
+        let guidelines: Vec<Guideline> = matcher
+            .get_guidelines()
+            .iter()
+            .filter(|g| g.tools.contains(&old_id))
+            .cloned()
+            .collect();
+        for mut guideline in guidelines {
+            for tool_id in guideline.tools.iter_mut().filter(|id| **id == old_id) {
+                *tool_id = new_id;
+            }
+            matcher.update_guideline(guideline).await?;
+        }
+
+        Ok(new_id)
+    }
+
+    /// Remove a tool from the agent
+    ///
+    /// Guidelines that still reference the tool skip it.
+    pub async fn remove_tool(&self, tool_id: &ToolId) -> Result<()> {
+        let _rules = self.rules.write().await;
+        self.tool_registry.unregister(tool_id).await
+    }
+
+    /// All tools of the agent, including disabled ones
+    pub async fn list_tools(&self) -> Vec<Arc<dyn Tool>> {
+        self.tool_registry.list().await
+    }
+
+    /// Let a disabled tool run again
+    pub async fn enable_tool(&self, tool_id: &ToolId) -> Result<()> {
+        let _rules = self.rules.write().await;
+        self.tool_registry.set_enabled(tool_id, true).await
+    }
+
+    /// Stop a tool from running, or being offered to the LLM, without removing it
+    pub async fn disable_tool(&self, tool_id: &ToolId) -> Result<()> {
+        let _rules = self.rules.write().await;
+        self.tool_registry.set_enabled(tool_id, false).await
+    }
+
+    /// Add a journey to the agent
+    pub async fn add_journey(&self, journey: Journey) -> Result<JourneyId> {
+        let _rules = self.rules.write().await;
+        let mut manager = self.journey_manager.write().await;
+        manager.add_journey(journey).await
+    }
+
+    /// Replace the journey with the same ID
+    ///
+    /// Sessions already in the journey continue from their current step, which
+    /// must still exist in the new version.
+    pub async fn update_journey(&self, journey: Journey) -> Result<()> {
+        let _rules = self.rules.write().await;
+        let mut manager = self.journey_manager.write().await;
+        if manager.get_journey(&journey.id).is_none() {
+            return Err(AgentError::Journey(format!(
+                "Journey {:?} not found",
+                journey.id
+            )));
+        }
+        info!(agent_id = %self.id, journey_name = %journey.name, "Updating journey");
+        manager.add_journey(journey).await.map(|_| ())
+    }
+
+    /// Remove a journey from the agent
+    pub async fn remove_journey(&self, journey_id: &JourneyId) -> Result<()> {
+        let _rules = self.rules.write().await;
+        let mut manager = self.journey_manager.write().await;
+        manager.remove_journey(journey_id).await
+    }
+
+    /// All journeys of the agent, including disabled ones
+    pub async fn list_journeys(&self) -> Vec<Journey> {
+        let manager = self.journey_manager.read().await;
+        manager.list_journeys().into_iter().cloned().collect()
+    }
+
+    /// Let a disabled journey be started again
+    pub async fn enable_journey(&self, journey_id: &JourneyId) -> Result<()> {
+        self.set_journey_enabled(journey_id, true).await
+    }
+
+    /// Stop a journey from being started without removing it
+    ///
+    /// Sessions already in the journey continue.
+    pub async fn disable_journey(&self, journey_id: &JourneyId) -> Result<()> {
+        self.set_journey_enabled(journey_id, false).await
+    }
+
+    async fn set_journey_enabled(&self, journey_id: &JourneyId, enabled: bool) -> Result<()> {
+        let _rules = self.rules.write().await;
+        let mut manager = self.journey_manager.write().await;
+        let mut journey = manager
+            .get_journey(journey_id)
+            .cloned()
+            .ok_or_else(|| AgentError::Journey(format!("Journey {:?} not found", journey_id)))?;
+        info!(agent_id = %self.id, journey_name = %journey.name, enabled = enabled, "Setting journey state");
+        journey.enabled = enabled;
+        manager.add_journey(journey).await.map(|_| ())
+    }
+
+    /// Start a journey for a session
+    ///
+    /// The journey state is stored on the session, so it is persisted by the
+    /// agent's `SessionStore`.
+    pub async fn start_journey(
+        &self,
+        session_id: &SessionId,
+        journey_id: &JourneyId,
+    ) -> Result<JourneyState> {
+        let _guard = self.session_locks.lock(*session_id).await;
+        let mut session = self.load_session(session_id).await?;
+
+        let state = {
+            let manager = self.journey_manager.read().await;
+            manager.start_journey(session_id, journey_id).await?
+        };
+
+        session.journey_state = Some(state.clone());
+        session.touch();
+        self.session_store
+            .update(session_id, session)
+            .await
+            .map_err(AgentError::Storage)?;
+
+        Ok(state)
+    }
+
+    /// Get current journey state for a session
+    pub async fn get_journey_state(&self, session_id: &SessionId) -> Result<Option<JourneyState>> {
+        let session = self.load_session(session_id).await?;
+        Ok(session.journey_state)
+    }
+
+    /// Process a journey step with user message
+    pub async fn process_journey_step(
+        &self,
+        session_id: &SessionId,
+        message: &str,
+    ) -> Result<JourneyStep> {
+        let _guard = self.session_locks.lock(*session_id).await;
+        let mut session = self.load_session(session_id).await?;
+        let state = session
+            .journey_state
+            .as_mut()
+            .ok_or_else(|| AgentError::Journey("No active journey for session".to_string()))?;
+
+        let next_step = self.advance_journey(state, message).await?;
+
+        session.touch();
+        self.session_store
+            .update(session_id, session)
+            .await
+            .map_err(AgentError::Storage)?;
+
+        Ok(next_step)
+    }
+
+    /// Advance a journey state with a user message and return the resulting step
+    async fn advance_journey(
+        &self,
+        state: &mut JourneyState,
+        message: &str,
+    ) -> Result<JourneyStep> {
+        let current_step_id = state.current_step;
+
+        // Process step through manager
+        let manager = self.journey_manager.read().await;
+        let next_step = manager
+            .process_step(&state.journey_id, current_step_id, message)
+            .await?;
+
+        // Update state
+        state.complete_step(current_step_id);
+
+        // Check if we transitioned to a new step or stayed on current (final) step
+        if next_step.id == current_step_id {
+            // No transition - this means we're on the final step
+            state.mark_complete();
+        } else {
+            // Transition to next step
+            state.move_to_step(next_step.id);
+        }
+
+        Ok(next_step)
+    }
+
+    /// End a journey for a session
+    pub async fn end_journey(&self, session_id: &SessionId) -> Result<()> {
+        let _guard = self.session_locks.lock(*session_id).await;
+        let mut session = self.load_session(session_id).await?;
+        session.clear_journey();
+
+        self.session_store
+            .update(session_id, session)
+            .await
+            .map_err(AgentError::Storage)
+    }
+
+    /// Load a session from the store, failing if it does not exist
+    async fn load_session(&self, session_id: &SessionId) -> Result<Session> {
+        self.session_store
+            .get(session_id)
+            .await
+            .map_err(AgentError::Storage)?
+            .ok_or_else(|| AgentError::SessionNotFound(*session_id))
+    }
+
+    /// Process a user message and generate a response
+    ///
+    /// If the session has an active journey, the journey is advanced with the
+    /// message and the new step's prompt becomes the response. Matched guidelines
+    /// still run their tools, and their actions are passed to the LLM when the
+    /// step prompt is rendered through it.
+    ///
+    /// Messages for the same session are processed one at a time, in the order
+    /// they arrive; messages for different sessions run concurrently.
     pub async fn process_message(
         &self,
         session_id: SessionId,
         user_message: String,
+    ) -> Result<AgentResponse> {
+        self.process_message_with_options(session_id, user_message, MessageOptions::default())
+            .await
+    }
+
+    /// Process a user message with per-message options
+    ///
+    /// With an idempotency key, the response is stored with the session. A
+    /// message repeated with the same key returns that response again without
+    /// running guidelines, tools or the LLM, so clients can safely retry.
+    pub async fn process_message_with_options(
+        &self,
+        session_id: SessionId,
+        user_message: String,
+        options: MessageOptions,
+    ) -> Result<AgentResponse> {
+        self.respond(session_id, user_message, &options, None).await
+    }
+
+    /// Process a user message and stream the response as it is generated
+    ///
+    /// Behaves like [`Agent::process_message`], but yields [`AgentEvent`]s as
+    /// guidelines are matched, tools run and the LLM produces text. The assistant
+    /// message is written to the session only once the full response has been
+    /// generated, just before [`AgentEvent::Completed`] is yielded.
+    pub fn process_message_stream(
+        &self,
+        session_id: SessionId,
+        user_message: String,
+    ) -> AgentEventStream<'_> {
+        let (sender, receiver) = mpsc::unbounded();
+
+        let run = async move {
+            let options = MessageOptions::default();
+            let result = self
+                .respond(session_id, user_message, &options, Some(&sender))
+                .await;
+            let _ = sender.unbounded_send(result.map(AgentEvent::Completed));
+        };
+
+        // Drive the pipeline alongside the receiver; the channel closes when it finishes
+        let driver = stream::once(run).filter_map(|_| future::ready(None));
+        Box::pin(stream::select(driver, receiver))
+    }
+
+    /// Shared message pipeline, optionally reporting progress through `events`
+    async fn respond(
+        &self,
+        session_id: SessionId,
+        mut user_message: String,
+        options: &MessageOptions,
+        events: Option<&EventSender>,
     ) -> Result<AgentResponse> {
         info!(
             session_id = %session_id,
@@ -341,12 +1269,17 @@ impl Agent {
             "Processing user message"
         );
 
+        // Messages for the same session are processed one at a time
+        let _guard = self.session_locks.lock(session_id).await;
+        // Guideline, journey and tool changes wait until the message is processed
+        let _rules = self.rules.read().await;
+
         // Get session
         let mut session = self
             .session_store
             .get(&session_id)
             .await
-            .map_err(|e| AgentError::Storage(e))?
+            .map_err(AgentError::Storage)?
             .ok_or_else(|| AgentError::SessionNotFound(session_id))?;
 
         debug!(
@@ -354,151 +1287,492 @@ impl Agent {
             message_count = session.context.messages.len(),
             "Session retrieved"
         );
+        let idempotency_key = options.idempotency_key.as_deref();
+        if let Some(response) = idempotency_key.and_then(|key| session.idempotent_response(key)) {
+            info!(
+                session_id = %session_id,
+                idempotency_key = ?idempotency_key,
+                "Replaying response for repeated idempotency key"
+            );
+            return Ok(serde_json::from_value(response.clone())?);
+        }
+        if let Err(e) = ensure_accepts_messages(&session) {
+            warn!(session_id = %session_id, error = %e, "Rejecting message");
+            return Err(e);
+        }
+        if let Some(idle_timeout) = self.config.session_idle_timeout {
+            session.extend_expiration(idle_timeout);
+        }
+
+        let mut turn = Turn::new(&self.config, &session);
+        self.compact_context(&mut session.context, &mut turn).await;
+
+        // A human operator is in control, so only record the message
+        if session.is_awaiting_human() {
+            debug!("Session is awaiting a human operator, recording message only");
+            session.context.add_message(Message::user(user_message));
+            session.token_usage += turn.usage;
+            session.cost += turn.cost;
+            let response = AgentResponse {
+                awaiting_human: true,
+                ..AgentResponse::reply(String::new())
+            };
+            if let Some(key) = idempotency_key {
+                session.record_idempotent_response(key, serde_json::to_value(&response)?);
+            }
+            session.touch();
+            self.session_store
+                .update(&session_id, session)
+                .await
+                .map_err(AgentError::Storage)?;
+            self.metrics.record_message();
+            return Ok(response);
+        }
+
+        let hook_context = HookContext {
+            agent_id: self.id,
+            session_id,
+        };
+        for hook in &self.hooks {
+            if let HookAction::Respond(reply) = hook
+                .before_matching(&hook_context, &mut user_message)
+                .await?
+            {
+                session.context.add_message(Message::user(user_message));
+                emit(events, AgentEvent::TextDelta(reply.clone()));
+                return self
+                    .save_response(
+                        &hook_context,
+                        session,
+                        AgentResponse::reply(reply),
+                        turn,
+                        idempotency_key,
+                    )
+                    .await;
+            }
+        }
 
         // Add user message to context
         let user_msg = Message::user(user_message.clone());
+        let user_message_id = user_msg.id;
         session.context.add_message(user_msg);
 
         // Match guidelines
+        let matching_started = Instant::now();
         let matcher = self.guideline_matcher.read().await;
         trace!("Acquired guideline matcher lock");
-        let matches = matcher
-            .match_guidelines(&user_message, &session.context)
-            .await?;
-
-        // Select best match or use fallback
-        let best_match = if !matches.is_empty() {
-            matcher.select_best_match(matches.clone()).await
-        } else {
-            None
+        let matches = enabled_matches(matcher.as_ref(), &user_message, &session.context).await?;
+
+        // Select the matches to apply
+        let mut selected_matches = match self.config.guideline_selection {
+            _ if matches.is_empty() => Vec::new(),
+            GuidelineSelection::Best => matcher
+                .select_best_match(matches.clone())
+                .await
+                .into_iter()
+                .collect(),
+            GuidelineSelection::Compose {
+                min_relevance,
+                min_priority,
+            } => {
+                matcher
+                    .select_matches(matches.clone(), min_relevance, min_priority)
+                    .await
+            }
         };
+        self.metrics.record_matching(matching_started.elapsed());
+        let proposed_matches = match turn.trace {
+            Some(_) => selected_matches.clone(),
+            None => Vec::new(),
+        };
+        let mut stop = None;
+        for hook in &self.hooks {
+            if let HookAction::Respond(reply) = hook
+                .after_match_selected(&hook_context, &mut selected_matches)
+                .await?
+            {
+                stop = Some(reply);
+                break;
+            }
+        }
+        if let Some(trace) = turn.trace.as_mut() {
+            trace.user_message = user_message.clone();
+            trace.candidates = trace_candidates(
+                &user_message,
+                &matches,
+                &proposed_matches,
+                &selected_matches,
+                matcher.get_guidelines(),
+                self.config.guideline_selection,
+            );
+        }
+        if let Some(reply) = stop {
+            emit(events, AgentEvent::TextDelta(reply.clone()));
+            let response = AgentResponse {
+                matched_guideline: selected_matches.first().cloned(),
+                ..AgentResponse::reply(reply)
+            };
+            return self
+                .save_response(&hook_context, session, response, turn, idempotency_key)
+                .await;
+        }
 
-        let guideline_match = best_match.or_else(|| {
-            // Use fallback guideline
-            Some(GuidelineMatch {
-                guideline_id: self.fallback_guideline.id,
-                relevance_score: 0.5,
-                semantic_score: 0.0,
-                matched_condition: "fallback".to_string(),
-                extracted_parameters: HashMap::new(),
-                explanation: Some("No matching guideline found, using fallback".to_string()),
-            })
-        });
-
-        // Get the guideline to use
-        let guideline_to_use = if let Some(ref gm) = guideline_match {
-            matcher
-                .get_guidelines()
-                .iter()
-                .find(|g| g.id == gm.guideline_id)
-                .cloned()
-                .unwrap_or_else(|| self.fallback_guideline.clone())
-        } else {
-            self.fallback_guideline.clone()
+        // The user is replying to the current journey step, so capture its variables
+        let mut captures = match session.journey_state.as_ref() {
+            Some(state) if !state.is_complete => {
+                let manager = self.journey_manager.read().await;
+                manager
+                    .get_journey(&state.journey_id)
+                    .and_then(|journey| journey.steps.iter().find(|s| s.id == state.current_step))
+                    .map(|step| step.captures.clone())
+                    .unwrap_or_default()
+            }
+            _ => Vec::new(),
         };
 
-        // Execute tools if the guideline specifies any
-        let mut tools_used = Vec::new();
-        let mut tool_context = String::new();
+        // Advance the active journey, if any
+        let mut journey_step = match session.journey_state.as_mut() {
+            Some(state) if !state.is_complete => {
+                Some(self.advance_journey(state, &user_message).await?)
+            }
+            _ => None,
+        };
+        let mut journey_completed = session
+            .journey_state
+            .as_ref()
+            .is_some_and(|state| state.is_complete);
 
-        if !guideline_to_use.tools.is_empty() {
+        if let Some(step) = journey_step.as_ref() {
             debug!(
-                tool_count = guideline_to_use.tools.len(),
-                "Executing tools for guideline"
+                step_id = %step.id,
+                step_name = %step.name,
+                "Journey advanced to step"
             );
+            emit(
+                events,
+                AgentEvent::JourneyAdvanced {
+                    step_id: step.id,
+                    step_name: step.name.clone(),
+                    journey_complete: journey_completed,
+                },
+            );
+        }
 
-            for tool_id in &guideline_to_use.tools {
-                info!(tool_id = %tool_id, "Executing tool");
-
-                // Extract parameters from the matched guideline
-                let parameters = if let Some(ref gm) = guideline_match {
-                    gm.extracted_parameters.clone()
-                } else {
-                    HashMap::new()
+        // Hand the session to a human when the agent is unsure how to respond
+        if let (Some(threshold), None) = (self.config.escalation_threshold, &journey_step) {
+            let confidence = selected_matches
+                .first()
+                .map(|m| Self::calculate_confidence(m.relevance_score, m.semantic_score))
+                .unwrap_or(0.0);
+            if confidence < threshold {
+                info!(
+                    session_id = %session_id,
+                    confidence = confidence,
+                    threshold = threshold,
+                    "Escalating low-confidence message to a human operator"
+                );
+                session.escalate(format!(
+                    "Confidence {:.2} is below the escalation threshold {:.2}",
+                    confidence, threshold
+                ));
+                let reply = self.config.escalation_message.clone();
+                emit(events, AgentEvent::TextDelta(reply.clone()));
+                let response = AgentResponse {
+                    matched_guideline: selected_matches.first().cloned(),
+                    awaiting_human: true,
+                    ..AgentResponse::reply(reply)
                 };
-
-                // Execute tool with retry and timeout
-                let tool_result = self
-                    .tool_registry
-                    .execute_with_retry(
-                        tool_id,
-                        parameters,
-                        self.config.default_tool_timeout,
-                        3,   // max retries
-                        100, // base backoff ms
-                    )
+                return self
+                    .save_response(&hook_context, session, response, turn, idempotency_key)
                     .await;
+            }
+        }
 
-                match tool_result {
-                    Ok(result) => {
-                        let execution_time = std::time::Duration::from_millis(100); // placeholder
-                        tools_used.push(ToolExecution {
-                            tool_id: *tool_id,
-                            duration: execution_time,
-                        });
-
-                        // Incorporate tool result into context for LLM
-                        tool_context.push_str(&format!(
-                            "\n\nTool result: {}",
-                            serde_json::to_string_pretty(&result.output).unwrap_or_default()
-                        ));
-
-                        debug!(
-                            tool_id = %tool_id,
-                            "Tool execution successful"
-                        );
+        // Fall back only when no journey step is driving the response
+        let mut fallback_guideline = None;
+        let selected_matches =
+            if selected_matches.is_empty() && (journey_step.is_none() || journey_completed) {
+                self.metrics.record_fallback();
+                if let Some(trace) = turn.trace.as_mut() {
+                    trace.used_fallback = true;
+                }
+                match self
+                    .fallback(
+                        &mut session,
+                        &user_message,
+                        &matches,
+                        matcher.get_guidelines(),
+                    )
+                    .await
+                {
+                    Fallback::Respond(guideline) => {
+                        fallback_guideline = Some(guideline);
+                        vec![GuidelineMatch {
+                            guideline_id: self.fallback.id,
+                            relevance_score: 0.5,
+                            semantic_score: 0.0,
+                            matched_condition: "fallback".to_string(),
+                            extracted_parameters: HashMap::new(),
+                            explanation: Some(
+                                "No matching guideline found, using fallback".to_string(),
+                            ),
+                            span: None,
+                        }]
                     }
-                    Err(e) => {
-                        warn!(
-                            tool_id = %tool_id,
-                            error = %e,
-                            "Tool execution failed"
+                    Fallback::Journey(step) => {
+                        emit(
+                            events,
+                            AgentEvent::JourneyAdvanced {
+                                step_id: step.id,
+                                step_name: step.name.clone(),
+                                journey_complete: false,
+                            },
                         );
-                        // Continue with other tools even if one fails
-                        tool_context.push_str(&format!("\n\nTool execution failed: {}", e));
+                        journey_step = Some(step);
+                        journey_completed = false;
+                        Vec::new()
                     }
                 }
-            }
+            } else {
+                session.consecutive_fallbacks = 0;
+                for gm in &selected_matches {
+                    self.metrics.record_guideline_hit(gm.guideline_id);
+                }
+                selected_matches
+            };
+        let active_step = journey_step.as_ref().filter(|_| !journey_completed);
+        let guideline_match = selected_matches.first().cloned();
+
+        // Get the guidelines to apply, primary first
+        let applied_guidelines: Vec<Guideline> = selected_matches
+            .iter()
+            .map(|gm| {
+                matcher
+                    .get_guidelines()
+                    .iter()
+                    .find(|g| g.id == gm.guideline_id)
+                    .cloned()
+                    .or_else(|| fallback_guideline.clone())
+                    .unwrap_or_else(|| {
+                        self.fallback
+                            .guideline(&FallbackStrategy::default(), "", &[], &[])
+                    })
+            })
+            .collect();
+        let guideline_to_use = applied_guidelines.first();
+        for gm in &selected_matches {
+            emit(events, AgentEvent::GuidelineMatched(gm.clone()));
         }
 
-        // Generate response based on guideline
-        let response_text = if guideline_to_use.action.requires_llm {
-            // Use LLM to generate response, including tool results in context
-            let mut llm_messages = self.build_llm_messages(&session.context, &guideline_to_use);
+        // Capture context variables declared by the step and guidelines
+        for guideline in &applied_guidelines {
+            captures.extend(guideline.captures.iter().cloned());
+        }
+        let mut context_updates = HashMap::new();
+        for variable in self
+            .capture_variables(&captures, &user_message, user_message_id, &mut turn)
+            .await
+        {
+            context_updates.insert(variable.name.clone(), variable.value.clone());
+            session.context.add_variable(variable);
+        }
 
-            // Add tool results to the last message if any tools were executed
-            if !tool_context.is_empty() {
-                llm_messages.push(Message::system(format!(
-                    "Tool execution results:{}",
-                    tool_context
-                )));
-            }
+        // Execute the union of the applied guidelines' tools
+        let mut tools_used = Vec::new();
+        let mut tool_context = String::new();
+        let mut tool_outputs = HashMap::new();
+        let mut executed_tools = Vec::new();
+
+        // A hook may stop processing with a reply at any point in here
+        let response_text = 'generate: {
+            for (gm, guideline) in selected_matches.iter().zip(&applied_guidelines) {
+                if !guideline.tools.is_empty() {
+                    debug!(
+                        guideline_id = %guideline.id,
+                        tool_count = guideline.tools.len(),
+                        "Executing tools for guideline"
+                    );
+                }
 
-            self.provider.complete(llm_messages).await?
-        } else {
-            // Use template response
-            guideline_to_use.action.response_template.clone()
-        };
+                for tool_id in &guideline.tools {
+                    if executed_tools.contains(tool_id) {
+                        continue;
+                    }
+                    executed_tools.push(*tool_id);
+
+                    // Extract parameters from the matched guideline
+                    let parameters = gm.extracted_parameters.clone();
+
+                    match self
+                        .run_tool(&hook_context, tool_id, parameters, events, &mut turn)
+                        .await?
+                    {
+                        ToolRun::Completed(result, execution) => {
+                            tools_used.push(execution);
+
+                            if let Some(tool) = self.tool_registry.get(tool_id).await {
+                                tool_outputs.insert(tool.name().to_string(), result.output.clone());
+                            }
+
+                            // Incorporate tool result into context for LLM
+                            tool_context.push_str(&format!(
+                                "\n\nTool result: {}",
+                                serde_json::to_string_pretty(&result.output).unwrap_or_default()
+                            ));
+                        }
+                        ToolRun::Failed(e) => {
+                            // Continue with other tools even if one fails
+                            tool_context.push_str(&format!("\n\nTool execution failed: {}", e));
+                        }
+                        ToolRun::Stopped(reply) => {
+                            emit(events, AgentEvent::TextDelta(reply.clone()));
+                            break 'generate reply;
+                        }
+                    }
+                }
+            }
 
-        // Add agent response to context
-        let agent_msg = Message::assistant(response_text.clone());
-        session.context.add_message(agent_msg);
+            // Generate response from the journey step or the guideline
+            // Combining several guidelines always needs the LLM, unless the
+            // session's token budget is used up
+            let template_only = turn.budget_exhausted();
+            if template_only {
+                debug!(session_id = %session_id, "Token budget exhausted, responding from templates");
+            }
+            let requires_llm = !template_only
+                && (applied_guidelines.len() > 1
+                    || applied_guidelines.iter().any(|g| g.action.requires_llm));
+            match (active_step, guideline_to_use) {
+                (Some(step), _)
+                    if (!self.config.enable_llm_journey_prompts || template_only)
+                        && !requires_llm =>
+                {
+                    // Use the journey step prompt verbatim
+                    emit(events, AgentEvent::TextDelta(step.prompt.clone()));
+                    step.prompt.clone()
+                }
+                (None, Some(guideline)) if !requires_llm => {
+                    // Use template response, filling in placeholders
+                    let template_context = TemplateContext {
+                        parameters: guideline_match
+                            .as_ref()
+                            .map(|gm| gm.extracted_parameters.clone())
+                            .unwrap_or_default(),
+                        variables: session
+                            .context
+                            .variables
+                            .iter()
+                            .map(|(name, variable)| (name.clone(), variable.value.clone()))
+                            .collect(),
+                        tool_outputs,
+                        session_metadata: session.metadata.clone(),
+                    };
+                    let text = guideline
+                        .action
+                        .render(&template_context, &self.config.missing_template_variables)?;
+                    emit(events, AgentEvent::TextDelta(text.clone()));
+                    text
+                }
+                (step, _) => {
+                    // Use LLM to generate response, including tool results in context
+                    let mut llm_messages =
+                        self.build_llm_messages(&session.context, &applied_guidelines, step);
+
+                    // Add tool results to the last message if any tools were executed
+                    if !tool_context.is_empty() {
+                        llm_messages.push(Message::system(format!(
+                            "Tool execution results:{}",
+                            tool_context
+                        )));
+                    }
 
-        // Update session
-        session.touch();
-        self.session_store
-            .update(&session_id, session)
-            .await
-            .map_err(|e| AgentError::Storage(e))?;
+                    let tool_definitions = if self.config.max_tool_iterations > 0 {
+                        self.tool_registry.definitions().await
+                    } else {
+                        Vec::new()
+                    };
+
+                    let text = if !tool_definitions.is_empty() {
+                        self.run_tool_loop(
+                            &hook_context,
+                            &mut llm_messages,
+                            tool_definitions,
+                            &mut tools_used,
+                            events,
+                            &mut turn,
+                        )
+                        .await?
+                    } else if let Some(reply) = self
+                        .run_before_llm_hooks(&hook_context, &mut llm_messages)
+                        .await?
+                    {
+                        emit(events, AgentEvent::TextDelta(reply.clone()));
+                        break 'generate reply;
+                    } else if events.is_some() && !self.guardrails.applies_to(&applied_guidelines) {
+                        // Nothing to check, so stream the text as it arrives
+                        let started = Instant::now();
+                        let prompt = turn.prompt(&llm_messages);
+                        let mut chunks = self.provider.stream(llm_messages).await?;
+                        let mut text = String::new();
+                        while let Some(chunk) = chunks.next().await {
+                            let chunk = chunk?;
+                            text.push_str(&chunk);
+                            emit(events, AgentEvent::TextDelta(chunk));
+                        }
+                        self.metrics.record_llm_call(started.elapsed());
+                        if let (Some(trace), Some(messages)) = (turn.trace.as_mut(), prompt) {
+                            trace.llm_calls.push(LlmCallTrace {
+                                kind: LlmCallKind::Response,
+                                messages,
+                                output: text.clone(),
+                                tool_calls: Vec::new(),
+                                metadata: HashMap::new(),
+                                usage: TokenUsage::default(),
+                                duration: started.elapsed(),
+                            });
+                        }
+                        break 'generate text;
+                    } else {
+                        self.complete(LlmCallKind::Response, llm_messages.clone(), &mut turn)
+                            .await?
+                    };
+
+                    let text = self
+                        .enforce_guardrails(
+                            &hook_context,
+                            &llm_messages,
+                            &applied_guidelines,
+                            text,
+                            &mut turn,
+                        )
+                        .await?;
+                    emit(events, AgentEvent::TextDelta(text.clone()));
+                    text
+                }
+            }
+        };
 
         // Build response
         let explanation = if self.config.enable_explainability {
             Some(ResponseExplanation {
                 guideline_matches: matches,
-                reasoning: format!(
-                    "Selected guideline with priority {}",
-                    guideline_to_use.priority
-                ),
+                applied_guidelines: applied_guidelines.iter().map(|g| g.id).collect(),
+                reasoning: match (active_step, applied_guidelines.as_slice()) {
+                    (Some(step), _) => format!("Continued journey at step '{}'", step.name),
+                    (None, [guideline]) => {
+                        format!("Selected guideline with priority {}", guideline.priority)
+                    }
+                    (None, []) => "No guideline selected".to_string(),
+                    (None, guidelines) => format!(
+                        "Composed {} guidelines with priorities {}",
+                        guidelines.len(),
+                        guidelines
+                            .iter()
+                            .map(|g| g.priority.to_string())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ),
+                },
                 confidence: guideline_match
                     .as_ref()
                     .map(|m| Self::calculate_confidence(m.relevance_score, m.semantic_score))
@@ -508,18 +1782,616 @@ impl Agent {
             None
         };
 
-        Ok(AgentResponse {
+        let awaiting_human = applied_guidelines.iter().any(|g| g.action.escalate);
+        if awaiting_human {
+            info!(session_id = %session_id, "Guideline escalated session to a human operator");
+            session.escalate("Guideline requested a human operator");
+        }
+
+        if let Some(trace) = turn.trace.as_mut() {
+            trace.applied_guidelines = applied_guidelines.iter().map(|g| g.id).collect();
+            trace.journey_step = journey_step.as_ref().map(|step| step.id);
+        }
+
+        let response = AgentResponse {
             message: response_text,
             matched_guideline: guideline_match,
             tools_used,
-            journey_step: None,
-            context_updates: HashMap::new(),
+            journey_step: journey_step.map(|step| step.id),
+            context_updates,
             explanation,
+            handoff: applied_guidelines
+                .iter()
+                .find_map(|g| g.action.handoff.clone()),
+            awaiting_human,
+            usage: TokenUsage::default(),
+            cost: 0.0,
+            trace: None,
+        };
+        self.save_response(&hook_context, session, response, turn, idempotency_key)
+            .await
+    }
+
+    /// Apply the session's fallback strategy and count the fallback
+    ///
+    /// A journey that cannot be started is logged and answered like the
+    /// default strategy.
+    async fn fallback(
+        &self,
+        session: &mut Session,
+        message: &str,
+        matches: &[GuidelineMatch],
+        guidelines: &[Guideline],
+    ) -> Fallback {
+        let strategy = self.fallback.strategy_for(session.consecutive_fallbacks);
+        session.consecutive_fallbacks += 1;
+        debug!(
+            consecutive_fallbacks = session.consecutive_fallbacks,
+            strategy = ?strategy,
+            "No guideline selected, falling back"
+        );
+
+        if let FallbackStrategy::StartJourney { journey_id } = strategy {
+            let manager = self.journey_manager.read().await;
+            let started = manager
+                .start_journey(&session.id, journey_id)
+                .await
+                .and_then(|state| {
+                    manager
+                        .get_journey(journey_id)
+                        .and_then(|journey| {
+                            journey.steps.iter().find(|s| s.id == state.current_step)
+                        })
+                        .cloned()
+                        .map(|step| (state, step))
+                        .ok_or_else(|| {
+                            AgentError::Journey(format!("Journey {:?} not found", journey_id))
+                        })
+                });
+            match started {
+                Ok((state, step)) => {
+                    info!(session_id = %session.id, journey_id = %journey_id, "Fallback started journey");
+                    session.journey_state = Some(state);
+                    return Fallback::Journey(step);
+                }
+                Err(e) => {
+                    warn!(journey_id = %journey_id, error = %e, "Fallback journey could not be started")
+                }
+            }
+        }
+
+        Fallback::Respond(
+            self.fallback
+                .guideline(strategy, message, matches, guidelines),
+        )
+    }
+
+    /// Run the `before_save` hooks, then add the response to the session and persist it
+    async fn save_response(
+        &self,
+        hook_context: &HookContext,
+        mut session: Session,
+        mut response: AgentResponse,
+        turn: Turn,
+        idempotency_key: Option<&str>,
+    ) -> Result<AgentResponse> {
+        response.usage = turn.usage;
+        response.cost = turn.cost;
+        session.token_usage += turn.usage;
+        session.cost += turn.cost;
+        let stored_trace = match &turn.trace {
+            Some(trace) if self.config.store_decision_trace => Some(serde_json::to_value(trace)?),
+            _ => None,
+        };
+        if self.config.decision_trace {
+            response.trace = turn.trace;
+        }
+
+        for hook in &self.hooks {
+            if let HookAction::Respond(reply) =
+                hook.before_save(hook_context, &mut response).await?
+            {
+                response.message = reply;
+                break;
+            }
+        }
+
+        // Add agent response to context
+        let mut agent_msg = Message::assistant(response.message.clone());
+        if let Some(trace) = stored_trace {
+            agent_msg = agent_msg.with_metadata(DECISION_TRACE_KEY, trace);
+        }
+        session.context.add_message(agent_msg);
+        if let Some(key) = idempotency_key {
+            session.record_idempotent_response(key, serde_json::to_value(&response)?);
+        }
+
+        // Update session
+        session.touch();
+        self.session_store
+            .update(&hook_context.session_id, session)
+            .await
+            .map_err(AgentError::Storage)?;
+        self.metrics.record_message();
+
+        Ok(response)
+    }
+
+    /// Check an LLM-generated response against the guardrails
+    ///
+    /// On violation the LLM is asked to rewrite the response, up to
+    /// `Guardrails::max_retries` times, before falling back to the
+    /// guardrails' fallback response.
+    async fn enforce_guardrails(
+        &self,
+        hook_context: &HookContext,
+        messages: &[Message],
+        guidelines: &[Guideline],
+        mut text: String,
+        turn: &mut Turn,
+    ) -> Result<String> {
+        let max_retries = self.guardrails.get_max_retries();
+
+        for attempt in 0..=max_retries {
+            let violations = self.guardrails.check(&text, guidelines).await;
+            if violations.is_empty() {
+                return Ok(text);
+            }
+
+            warn!(
+                attempt = attempt,
+                violation_count = violations.len(),
+                rules = ?violations.iter().map(|v| v.rule.as_str()).collect::<Vec<_>>(),
+                "Response violated guardrails"
+            );
+            if attempt == max_retries {
+                break;
+            }
+
+            let mut retry_messages = messages.to_vec();
+            retry_messages.push(Message::assistant(text));
+            retry_messages.push(Message::system(format!(
+                "Your previous response broke these rules:\n{}\nRewrite the response so that it follows all of them.",
+                violations
+                    .iter()
+                    .map(|v| format!("- {}", v.reason))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            )));
+            if let Some(reply) = self
+                .run_before_llm_hooks(hook_context, &mut retry_messages)
+                .await?
+            {
+                return Ok(reply);
+            }
+            text = self
+                .complete(LlmCallKind::GuardrailRetry, retry_messages, turn)
+                .await?;
+        }
+
+        warn!("Guardrail retries exhausted, using fallback response");
+        Ok(self.guardrails.get_fallback_response().to_string())
+    }
+
+    /// Run the `before_llm` hooks, returning the reply if one stops processing
+    async fn run_before_llm_hooks(
+        &self,
+        hook_context: &HookContext,
+        messages: &mut Vec<Message>,
+    ) -> Result<Option<String>> {
+        for hook in &self.hooks {
+            if let HookAction::Respond(reply) = hook.before_llm(hook_context, messages).await? {
+                return Ok(Some(reply));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Token budget for a session's history, if one applies
+    fn context_token_budget(&self) -> Option<usize> {
+        self.config.context_token_budget.or_else(|| {
+            let reserved = self
+                .provider
+                .config()
+                .max_tokens
+                .map(|tokens| tokens as usize)
+                .unwrap_or(DEFAULT_RESPONSE_TOKENS);
+            self.provider
+                .context_window()
+                .map(|window| window.saturating_sub(reserved))
         })
     }
 
-    /// Build LLM messages from context and guideline
-    fn build_llm_messages(&self, context: &Context, guideline: &Guideline) -> Vec<Message> {
+    /// Fold old messages into the context's rolling summary
+    ///
+    /// Runs when this turn's user message and response would take the history
+    /// past `max_context_messages`, or when it is over the token budget. The
+    /// history is then cut to half of both limits, so the summary is only
+    /// regenerated every few turns. If summarizing fails, or the session's
+    /// token budget is used up, the history is kept.
+    async fn compact_context(&self, context: &mut Context, turn: &mut Turn) {
+        let max_messages = self.config.max_context_messages;
+        let max_tokens = self.context_token_budget();
+        let over_limit = context.messages.len() + 2 > max_messages
+            || max_tokens.is_some_and(|max| context.estimated_tokens() > max);
+        if !over_limit || turn.budget_exhausted() {
+            return;
+        }
+
+        let overflow = context.overflow((max_messages / 2).max(1), max_tokens.map(|max| max / 2));
+        if overflow.is_empty() {
+            return;
+        }
+
+        let transcript = overflow
+            .iter()
+            .map(|m| format!("{:?}: {}", m.role, m.content))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let request = match context.summary.as_deref() {
+            Some(summary) => format!(
+                "Summary so far:\n{}\n\nMessages to add:\n{}",
+                summary, transcript
+            ),
+            None => format!("Messages to summarize:\n{}", transcript),
+        };
+        let messages = vec![
+            Message::system(
+                "Summarize this conversation so it can replace the original messages. Keep \
+                 names, numbers, dates, decisions, commitments and open questions. Respond \
+                 with only the summary.",
+            ),
+            Message::user(request),
+        ];
+
+        match self.complete(LlmCallKind::Summary, messages, turn).await {
+            Ok(summary) => {
+                debug!(
+                    folded_count = overflow.len(),
+                    remaining_count = context.messages.len() - overflow.len(),
+                    "Folded old messages into context summary"
+                );
+                context.fold_into_summary(&overflow, summary);
+            }
+            Err(e) => warn!(error = %e, "Context summarization failed, keeping history"),
+        }
+    }
+
+    /// Extract, validate and return the declared context variables
+    ///
+    /// Values that are missing or fail their validator are skipped.
+    async fn capture_variables(
+        &self,
+        captures: &[VariableCapture],
+        message: &str,
+        source_message_id: MessageId,
+        turn: &mut Turn,
+    ) -> Vec<ContextVariable> {
+        let mut values = HashMap::new();
+        let mut llm_fields = Vec::new();
+
+        for capture in captures {
+            match &capture.source {
+                CaptureSource::Regex { pattern } => {
+                    let re = match Regex::new(pattern) {
+                        Ok(re) => re,
+                        Err(e) => {
+                            warn!(variable = %capture.name, error = %e, "Invalid capture regex");
+                            continue;
+                        }
+                    };
+                    let value = re.captures(message).and_then(|captures| {
+                        captures
+                            .name(&capture.name)
+                            .or_else(|| captures.get(1))
+                            .or_else(|| captures.get(0))
+                            .map(|m| capture.coerce(m.as_str()))
+                    });
+                    if let Some(value) = value {
+                        values.insert(capture.name.clone(), value);
+                    }
+                }
+                CaptureSource::Llm { description } => {
+                    llm_fields.push((capture, description));
+                }
+            }
+        }
+
+        if !llm_fields.is_empty() && !turn.budget_exhausted() {
+            let field_list = llm_fields
+                .iter()
+                .map(|(capture, description)| format!("- {}: {}", capture.name, description))
+                .collect::<Vec<_>>()
+                .join("\n");
+            let messages = vec![
+                Message::system(format!(
+                    "Extract the following fields from the user's message. Respond with only a \
+                     JSON object mapping each field name to its value, using null for fields \
+                     that are not present.\n{}",
+                    field_list
+                )),
+                Message::user(message),
+            ];
+
+            match self.complete(LlmCallKind::Capture, messages, turn).await {
+                Ok(output) => match parse_json_object(&output) {
+                    Some(extracted) => {
+                        for (capture, _) in llm_fields {
+                            let value = match extracted.get(&capture.name) {
+                                None | Some(serde_json::Value::Null) => continue,
+                                Some(serde_json::Value::String(raw)) => capture.coerce(raw),
+                                Some(value) => value.clone(),
+                            };
+                            values.insert(capture.name.clone(), value);
+                        }
+                    }
+                    None => warn!("LLM extraction did not return a JSON object"),
+                },
+                Err(e) => warn!(error = %e, "LLM extraction failed"),
+            }
+        }
+
+        captures
+            .iter()
+            .filter_map(|capture| {
+                let value = values.remove(&capture.name)?;
+                let mut variable = ContextVariable::new(&capture.name, value, source_message_id);
+                if let Some(validator) = capture.validator.clone() {
+                    variable = variable.with_validator(validator);
+                }
+
+                match variable.validate() {
+                    Ok(()) => {
+                        debug!(variable = %capture.name, "Captured context variable");
+                        Some(variable)
+                    }
+                    Err(reason) => {
+                        warn!(variable = %capture.name, reason = %reason, "Captured value failed validation");
+                        None
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// Execute a tool with retry and timeout, reporting progress through `events`
+    ///
+    /// The `before_tool` and `after_tool` hooks run around the execution. Tool
+    /// failures are returned as `ToolRun::Failed`; errors from hooks abort.
+    async fn run_tool(
+        &self,
+        hook_context: &HookContext,
+        tool_id: &ToolId,
+        mut parameters: HashMap<String, serde_json::Value>,
+        events: Option<&EventSender>,
+        turn: &mut Turn,
+    ) -> Result<ToolRun> {
+        for hook in &self.hooks {
+            if let HookAction::Respond(reply) = hook
+                .before_tool(hook_context, tool_id, &mut parameters)
+                .await?
+            {
+                return Ok(ToolRun::Stopped(reply));
+            }
+        }
+
+        info!(tool_id = %tool_id, "Executing tool");
+        emit(events, AgentEvent::ToolStarted { tool_id: *tool_id });
+
+        let started_at = Utc::now();
+        let started = Instant::now();
+        let traced_parameters = turn.trace.as_ref().map(|_| parameters.clone());
+        let (mut tool_result, attempts) = self
+            .tool_registry
+            .execute_with_attempts(
+                tool_id,
+                parameters,
+                self.config.default_tool_timeout,
+                3,   // max retries
+                100, // base backoff ms
+            )
+            .await;
+        let duration = started.elapsed();
+
+        let mut stop = None;
+        for hook in &self.hooks {
+            if let HookAction::Respond(reply) = hook
+                .after_tool(hook_context, tool_id, &mut tool_result)
+                .await?
+            {
+                stop = Some(reply);
+                break;
+            }
+        }
+
+        emit(
+            events,
+            AgentEvent::ToolFinished {
+                tool_id: *tool_id,
+                success: tool_result.is_ok(),
+            },
+        );
+        if let (Some(trace), Some(parameters)) = (turn.trace.as_mut(), traced_parameters) {
+            let tool_name = match self.tool_registry.get(tool_id).await {
+                Some(tool) => tool.name().to_string(),
+                None => String::new(),
+            };
+            trace.tool_calls.push(ToolCallTrace {
+                tool_id: *tool_id,
+                tool_name,
+                parameters,
+                output: tool_result
+                    .as_ref()
+                    .ok()
+                    .map(|result| result.output.clone()),
+                error: tool_result.as_ref().err().map(|e| e.to_string()),
+                attempts,
+                started_at,
+                duration,
+            });
+        }
+
+        if let Some(reply) = stop {
+            return Ok(ToolRun::Stopped(reply));
+        }
+
+        match tool_result {
+            Ok(result) => {
+                debug!(tool_id = %tool_id, "Tool execution successful");
+                let execution = ToolExecution {
+                    tool_id: *tool_id,
+                    duration,
+                };
+                Ok(ToolRun::Completed(result, execution))
+            }
+            Err(e) => {
+                warn!(
+                    tool_id = %tool_id,
+                    error = %e,
+                    "Tool execution failed"
+                );
+                Ok(ToolRun::Failed(e))
+            }
+        }
+    }
+
+    /// Complete with the provider, recording the call's latency, usage and trace
+    async fn complete(
+        &self,
+        kind: LlmCallKind,
+        messages: Vec<Message>,
+        turn: &mut Turn,
+    ) -> Result<String> {
+        let prompt = turn.prompt(&messages);
+        let started = Instant::now();
+        let response = self
+            .timed_llm_call(self.provider.complete_with_usage(messages))
+            .await?;
+        self.record_usage(turn, response.usage);
+        if let (Some(trace), Some(messages)) = (turn.trace.as_mut(), prompt) {
+            trace.llm_calls.push(LlmCallTrace {
+                kind,
+                messages,
+                output: response.content.clone(),
+                tool_calls: Vec::new(),
+                metadata: response.metadata,
+                usage: response.usage,
+                duration: started.elapsed(),
+            });
+        }
+        Ok(response.content)
+    }
+
+    /// Add an LLM call's tokens, and their cost, to the turn and the metrics
+    fn record_usage(&self, turn: &mut Turn, usage: TokenUsage) {
+        let cost = model_price(&self.config.model_prices, &self.provider.config().model)
+            .map(|price| usage.cost(price))
+            .unwrap_or_default();
+        turn.usage += usage;
+        turn.cost += cost;
+        self.metrics.record_token_usage(usage, cost);
+    }
+
+    async fn timed_llm_call<T>(&self, call: impl Future<Output = Result<T>>) -> Result<T> {
+        let started = Instant::now();
+        let result = call.await;
+        self.metrics.record_llm_call(started.elapsed());
+        result
+    }
+
+    /// Let the LLM call tools natively, feeding results back until it answers
+    ///
+    /// Runs at most `AgentConfig::max_tool_iterations` tool rounds; if the model
+    /// is still calling tools after that, it is asked for a final answer without
+    /// tools.
+    async fn run_tool_loop(
+        &self,
+        hook_context: &HookContext,
+        messages: &mut Vec<Message>,
+        tools: Vec<ToolDefinition>,
+        tools_used: &mut Vec<ToolExecution>,
+        events: Option<&EventSender>,
+        turn: &mut Turn,
+    ) -> Result<String> {
+        for iteration in 0..self.config.max_tool_iterations {
+            if let Some(reply) = self.run_before_llm_hooks(hook_context, messages).await? {
+                return Ok(reply);
+            }
+
+            let started = Instant::now();
+            let completion = self
+                .timed_llm_call(
+                    self.provider
+                        .complete_with_tools(messages.clone(), tools.clone()),
+                )
+                .await?;
+            self.record_usage(turn, completion.usage);
+            if let Some(trace) = turn.trace.as_mut() {
+                trace.llm_calls.push(LlmCallTrace {
+                    kind: LlmCallKind::Response,
+                    messages: messages.clone(),
+                    output: completion.content.clone(),
+                    tool_calls: completion.tool_calls.clone(),
+                    metadata: HashMap::new(),
+                    usage: completion.usage,
+                    duration: started.elapsed(),
+                });
+            }
+
+            if completion.tool_calls.is_empty() {
+                return Ok(completion.content);
+            }
+
+            debug!(
+                iteration = iteration + 1,
+                tool_call_count = completion.tool_calls.len(),
+                "LLM requested tool calls"
+            );
+            messages.push(Message::assistant_tool_calls(
+                completion.content,
+                &completion.tool_calls,
+            ));
+
+            for call in completion.tool_calls {
+                let content = match self.tool_registry.get_by_name(&call.name).await {
+                    Some(tool) => match self
+                        .run_tool(hook_context, tool.id(), call.arguments, events, turn)
+                        .await?
+                    {
+                        ToolRun::Completed(result, execution) => {
+                            tools_used.push(execution);
+                            serde_json::to_string(&result.output).unwrap_or_default()
+                        }
+                        ToolRun::Failed(e) => format!("Tool execution failed: {}", e),
+                        ToolRun::Stopped(reply) => return Ok(reply),
+                    },
+                    None => {
+                        warn!(tool_name = %call.name, "LLM requested unknown tool");
+                        format!("Unknown tool: {}", call.name)
+                    }
+                };
+                messages.push(Message::tool_result(call.id, content));
+            }
+        }
+
+        warn!(
+            max_iterations = self.config.max_tool_iterations,
+            "Tool call limit reached, requesting final answer"
+        );
+        if let Some(reply) = self.run_before_llm_hooks(hook_context, messages).await? {
+            return Ok(reply);
+        }
+        self.complete(LlmCallKind::Response, messages.clone(), turn)
+            .await
+    }
+
+    /// Build LLM messages from context, applied guidelines and active journey step
+    fn build_llm_messages(
+        &self,
+        context: &Context,
+        guidelines: &[Guideline],
+        journey_step: Option<&JourneyStep>,
+    ) -> Vec<Message> {
         let mut messages = vec![Message::system(format!(
             "You are {}. {}",
             self.name,
@@ -528,11 +2400,54 @@ impl Agent {
                 .unwrap_or("A helpful AI assistant")
         ))];
 
+        // Add the summary of messages that left the history
+        if let Some(summary) = &context.summary {
+            messages.push(Message::system(format!(
+                "Summary of the earlier conversation: {}",
+                summary
+            )));
+        }
+
         // Add guideline context
-        messages.push(Message::system(format!(
-            "Guideline: {}",
-            guideline.action.response_template
-        )));
+        match guidelines {
+            [] => {}
+            [guideline] => messages.push(Message::system(format!(
+                "Guideline: {}",
+                guideline.action.response_template
+            ))),
+            guidelines => messages.push(Message::system(format!(
+                "Follow all of these guidelines:\n{}",
+                guidelines
+                    .iter()
+                    .map(|g| format!("- {}", g.action.response_template))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            ))),
+        }
+
+        // Tell the LLM about the guidelines' output requirements
+        for guideline in guidelines {
+            if !guideline.action.must_mention.is_empty() {
+                messages.push(Message::system(format!(
+                    "Your response must mention: {}",
+                    guideline.action.must_mention.join(", ")
+                )));
+            }
+            if let Some(schema) = &guideline.action.output_schema {
+                messages.push(Message::system(format!(
+                    "Respond with only a JSON value matching this JSON Schema: {}",
+                    schema
+                )));
+            }
+        }
+
+        // Add journey step context
+        if let Some(step) = journey_step {
+            messages.push(Message::system(format!(
+                "Current journey step: {}. Guide the user with this prompt: {}",
+                step.name, step.prompt
+            )));
+        }
 
         // Add conversation history
         messages.extend(context.messages.clone());
@@ -571,6 +2486,13 @@ pub struct AgentBuilder {
     provider: Option<Box<dyn LLMProvider>>,
     config: AgentConfig,
     session_store: Option<Arc<dyn SessionStore>>,
+    guideline_matcher: Option<Box<dyn GuidelineMatcher>>,
+    journey_manager: Option<Box<dyn JourneyManager>>,
+    hooks: Vec<Box<dyn AgentHook>>,
+    guardrails: Guardrails,
+    metrics: Option<Arc<Metrics>>,
+    fallback_strategy: FallbackStrategy,
+    repeated_fallback_strategy: Option<(u32, FallbackStrategy)>,
 }
 
 impl AgentBuilder {
@@ -581,7 +2503,34 @@ impl AgentBuilder {
             provider: None,
             config: AgentConfig::default(),
             session_store: None,
+            guideline_matcher: None,
+            journey_manager: None,
+            hooks: Vec::new(),
+            guardrails: Guardrails::new(),
+            metrics: None,
+            fallback_strategy: FallbackStrategy::default(),
+            repeated_fallback_strategy: None,
+        }
+    }
+
+    /// Start from a definition's name, description, config and provider
+    ///
+    /// The provider's API key is read from the environment. Guidelines and
+    /// journeys are added after building with [`Agent::add_definition`].
+    pub fn from_definition(definition: &AgentDefinition) -> Result<Self> {
+        definition.validate_structure()?;
+
+        let mut builder = Self::new()
+            .name(&definition.name)
+            .config(definition.config.clone());
+        if let Some(description) = &definition.description {
+            builder = builder.description(description);
         }
+        if let Some(provider) = definition.build_provider()? {
+            builder = builder.provider(provider);
+        }
+
+        Ok(builder)
     }
 
     pub fn name(mut self, name: impl Into<String>) -> Self {
@@ -609,6 +2558,54 @@ impl AgentBuilder {
         self
     }
 
+    /// Use a custom guideline matcher instead of `DefaultGuidelineMatcher`
+    ///
+    /// Guidelines added with `Agent::add_guideline` are passed to this matcher.
+    pub fn guideline_matcher(mut self, matcher: Box<dyn GuidelineMatcher>) -> Self {
+        self.guideline_matcher = Some(matcher);
+        self
+    }
+
+    /// Use a custom journey manager instead of `DefaultJourneyManager`
+    ///
+    /// Journeys added with `Agent::add_journey` are passed to this manager.
+    pub fn journey_manager(mut self, manager: Box<dyn JourneyManager>) -> Self {
+        self.journey_manager = Some(manager);
+        self
+    }
+
+    /// Register a hook; hooks run in the order they are registered
+    pub fn hook(mut self, hook: Box<dyn AgentHook>) -> Self {
+        self.hooks.push(hook);
+        self
+    }
+
+    /// Check LLM-generated responses against these guardrails
+    pub fn guardrails(mut self, guardrails: Guardrails) -> Self {
+        self.guardrails = guardrails;
+        self
+    }
+
+    /// Record metrics in this registry, e.g. to share it between agents
+    pub fn metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Respond with this strategy when no guideline matches a message
+    pub fn fallback_strategy(mut self, strategy: FallbackStrategy) -> Self {
+        self.fallback_strategy = strategy;
+        self
+    }
+
+    /// Switch to `strategy` once a session has had `after` fallbacks in a row
+    ///
+    /// A message answered by a guideline or journey resets the count.
+    pub fn fallback_strategy_after(mut self, after: u32, strategy: FallbackStrategy) -> Self {
+        self.repeated_fallback_strategy = Some((after, strategy));
+        self
+    }
+
     pub fn build(self) -> Result<Agent> {
         let name = self
             .name
@@ -622,35 +2619,30 @@ impl AgentBuilder {
             .session_store
             .unwrap_or_else(|| Arc::new(crate::storage::memory::InMemorySessionStore::new()));
 
-        // Create default fallback guideline
-        let fallback_guideline = Guideline {
-            id: GuidelineId::new(),
-            condition: GuidelineCondition::Literal("".to_string()),
-            action: GuidelineAction {
-                response_template:
-                    "I'm not sure how to help with that. Could you please rephrase your question?"
-                        .to_string(),
-                requires_llm: true,
-                parameters: vec![],
-            },
-            priority: -1,
-            tools: vec![],
-            parameters: HashMap::new(),
-            created_at: Utc::now(),
-        };
+        let guideline_matcher = self
+            .guideline_matcher
+            .unwrap_or_else(|| Box::new(DefaultGuidelineMatcher::new()));
+        let journey_manager = self
+            .journey_manager
+            .unwrap_or_else(|| Box::new(DefaultJourneyManager::new()));
+        let metrics = self.metrics.unwrap_or_default();
 
         Ok(Agent {
             id: AgentId::new(),
             name,
             description: self.description,
             provider,
-            guideline_matcher: Arc::new(RwLock::new(DefaultGuidelineMatcher::new())),
-            tool_registry: Arc::new(ToolRegistry::new()),
-            journey_manager: Arc::new(RwLock::new(DefaultJourneyManager::new())),
-            journey_states: Arc::new(RwLock::new(HashMap::new())),
-            fallback_guideline,
+            guideline_matcher: Arc::new(RwLock::new(guideline_matcher)),
+            tool_registry: Arc::new(ToolRegistry::with_metrics(metrics.clone())),
+            journey_manager: Arc::new(RwLock::new(journey_manager)),
+            rules: RwLock::new(()),
+            fallback: FallbackPolicy::new(self.fallback_strategy, self.repeated_fallback_strategy),
             config: self.config,
             session_store,
+            session_locks: SessionLocks::default(),
+            hooks: self.hooks,
+            guardrails: self.guardrails,
+            metrics,
             created_at: Utc::now(),
             updated_at: Utc::now(),
         })
@@ -666,8 +2658,11 @@ impl Default for AgentBuilder {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::context::MessageRole;
+    use crate::guideline::{GuidelineAction, GuidelineCondition};
     use crate::provider::LLMProvider;
     use crate::tool::{ParameterSchema, Tool, ToolResult};
+    use crate::trace::CandidateOutcome;
     use std::collections::HashMap;
 
     #[test]
@@ -675,7 +2670,7 @@ mod tests {
         let config = AgentConfig::default();
         assert_eq!(config.max_context_messages, 100);
         assert_eq!(config.default_tool_timeout, Duration::from_secs(30));
-        assert_eq!(config.enable_explainability, true);
+        assert!(config.enable_explainability);
         assert_eq!(config.log_level, LogLevel::Info);
     }
 
@@ -764,231 +2759,1923 @@ mod tests {
         }
     }
 
-    #[async_trait::async_trait]
-    impl Tool for MockTool {
-        fn id(&self) -> &ToolId {
-            &self.id
-        }
-
-        fn name(&self) -> &str {
-            &self.name
-        }
+    #[async_trait::async_trait]
+    impl Tool for MockTool {
+        fn id(&self) -> &ToolId {
+            &self.id
+        }
+
+        fn name(&self) -> &str {
+            &self.name
+        }
+
+        fn description(&self) -> &str {
+            "A mock tool for testing"
+        }
+
+        fn parameters(&self) -> &HashMap<String, ParameterSchema> {
+            &self.parameters
+        }
+
+        async fn execute(
+            &self,
+            _parameters: HashMap<String, serde_json::Value>,
+        ) -> Result<ToolResult> {
+            Ok(ToolResult {
+                output: serde_json::json!({ "result": self.result }),
+                error: None,
+                metadata: HashMap::new(),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_agent_add_tool() {
+        let provider: Box<dyn LLMProvider> = Box::new(MockProvider::new());
+        let agent = Agent::builder()
+            .name("Test Agent")
+            .provider(provider)
+            .build()
+            .unwrap();
+
+        let tool = MockTool::new("test_tool".to_string(), "test result".to_string());
+        let tool_id = agent.add_tool(Box::new(tool)).await.unwrap();
+
+        // Verify tool was added
+        let tool_registry = &agent.tool_registry;
+        let retrieved_tool = tool_registry.get(&tool_id).await;
+        assert!(retrieved_tool.is_some());
+        assert_eq!(retrieved_tool.unwrap().name(), "test_tool");
+    }
+
+    #[tokio::test]
+    async fn test_agent_tool_execution_in_guideline() {
+        let provider: Box<dyn LLMProvider> = Box::new(MockProvider::new());
+        let agent = Agent::builder()
+            .name("Test Agent")
+            .provider(provider)
+            .build()
+            .unwrap();
+
+        // Add a tool
+        let tool = MockTool::new("weather_tool".to_string(), "sunny, 72F".to_string());
+        let tool_id = agent.add_tool(Box::new(tool)).await.unwrap();
+
+        // Add a guideline that uses the tool
+        let guideline = Guideline {
+            id: GuidelineId::new(),
+            condition: GuidelineCondition::Literal("weather".to_string()),
+            action: GuidelineAction {
+                response_template: "Here's the weather".to_string(),
+                requires_llm: false,
+                parameters: Vec::new(),
+                must_mention: vec![],
+                output_schema: None,
+                handoff: None,
+                escalate: false,
+            },
+            priority: 10,
+            tools: vec![tool_id],
+            parameters: HashMap::new(),
+            captures: vec![],
+            created_at: chrono::Utc::now(),
+            enabled: true,
+        };
+
+        agent.add_guideline(guideline).await.unwrap();
+
+        // Create a session
+        let session_id = agent.create_session().await.unwrap();
+
+        // Process a message that triggers the guideline with the tool
+        let response = agent
+            .process_message(session_id, "What's the weather?".to_string())
+            .await
+            .unwrap();
+
+        // Verify tool was executed
+        assert_eq!(response.tools_used.len(), 1);
+        assert_eq!(response.tools_used[0].tool_id, tool_id);
+    }
+
+    #[tokio::test]
+    async fn test_agent_multiple_tools_in_guideline() {
+        let provider: Box<dyn LLMProvider> = Box::new(MockProvider::new());
+        let agent = Agent::builder()
+            .name("Test Agent")
+            .provider(provider)
+            .build()
+            .unwrap();
+
+        // Add two tools
+        let tool1 = MockTool::new("tool1".to_string(), "result1".to_string());
+        let tool1_id = agent.add_tool(Box::new(tool1)).await.unwrap();
+
+        let tool2 = MockTool::new("tool2".to_string(), "result2".to_string());
+        let tool2_id = agent.add_tool(Box::new(tool2)).await.unwrap();
+
+        // Add a guideline that uses both tools
+        let guideline = Guideline {
+            id: GuidelineId::new(),
+            condition: GuidelineCondition::Literal("multi".to_string()),
+            action: GuidelineAction {
+                response_template: "Using multiple tools".to_string(),
+                requires_llm: false,
+                parameters: Vec::new(),
+                must_mention: vec![],
+                output_schema: None,
+                handoff: None,
+                escalate: false,
+            },
+            priority: 10,
+            tools: vec![tool1_id, tool2_id],
+            parameters: HashMap::new(),
+            captures: vec![],
+            created_at: chrono::Utc::now(),
+            enabled: true,
+        };
+
+        agent.add_guideline(guideline).await.unwrap();
+
+        // Create a session
+        let session_id = agent.create_session().await.unwrap();
+
+        // Process a message
+        let response = agent
+            .process_message(session_id, "multi tool test".to_string())
+            .await
+            .unwrap();
+
+        // Verify both tools were executed
+        assert_eq!(response.tools_used.len(), 2);
+        assert!(response.tools_used.iter().any(|t| t.tool_id == tool1_id));
+        assert!(response.tools_used.iter().any(|t| t.tool_id == tool2_id));
+    }
+
+    #[tokio::test]
+    async fn test_agent_tool_with_llm_response() {
+        let provider: Box<dyn LLMProvider> = Box::new(MockProvider::new());
+        let agent = Agent::builder()
+            .name("Test Agent")
+            .provider(provider)
+            .build()
+            .unwrap();
+
+        // Add a tool
+        let tool = MockTool::new("data_tool".to_string(), "data result".to_string());
+        let tool_id = agent.add_tool(Box::new(tool)).await.unwrap();
+
+        // Add a guideline that uses the tool AND requires LLM
+        let guideline = Guideline {
+            id: GuidelineId::new(),
+            condition: GuidelineCondition::Literal("analyze".to_string()),
+            action: GuidelineAction {
+                response_template: "Analyzing data".to_string(),
+                requires_llm: true, // This should use LLM to generate response
+                parameters: Vec::new(),
+                must_mention: vec![],
+                output_schema: None,
+                handoff: None,
+                escalate: false,
+            },
+            priority: 10,
+            tools: vec![tool_id],
+            parameters: HashMap::new(),
+            captures: vec![],
+            created_at: chrono::Utc::now(),
+            enabled: true,
+        };
+
+        agent.add_guideline(guideline).await.unwrap();
+
+        // Create a session
+        let session_id = agent.create_session().await.unwrap();
+
+        // Process a message
+        let response = agent
+            .process_message(session_id, "analyze this data".to_string())
+            .await
+            .unwrap();
+
+        // Verify tool was executed
+        assert_eq!(response.tools_used.len(), 1);
+        assert_eq!(response.tools_used[0].tool_id, tool_id);
+
+        // Response should come from LLM (our mock returns "Mock LLM response")
+        assert_eq!(response.message, "Mock LLM response");
+    }
+
+    #[tokio::test]
+    async fn test_agent_guideline_without_tools() {
+        let provider: Box<dyn LLMProvider> = Box::new(MockProvider::new());
+        let agent = Agent::builder()
+            .name("Test Agent")
+            .provider(provider)
+            .build()
+            .unwrap();
+
+        // Add a guideline without any tools
+        let guideline = Guideline {
+            id: GuidelineId::new(),
+            condition: GuidelineCondition::Literal("hello".to_string()),
+            action: GuidelineAction {
+                response_template: "Hello there!".to_string(),
+                requires_llm: false,
+                parameters: Vec::new(),
+                must_mention: vec![],
+                output_schema: None,
+                handoff: None,
+                escalate: false,
+            },
+            priority: 10,
+            tools: Vec::new(), // No tools
+            parameters: HashMap::new(),
+            captures: vec![],
+            created_at: chrono::Utc::now(),
+            enabled: true,
+        };
+
+        agent.add_guideline(guideline).await.unwrap();
+
+        // Create a session
+        let session_id = agent.create_session().await.unwrap();
+
+        // Process a message
+        let response = agent
+            .process_message(session_id, "hello".to_string())
+            .await
+            .unwrap();
+
+        // Verify no tools were executed
+        assert_eq!(response.tools_used.len(), 0);
+        assert_eq!(response.message, "Hello there!");
+    }
+
+    fn create_two_step_journey() -> Journey {
+        let step1_id = StepId::new();
+        let step2_id = StepId::new();
+
+        Journey {
+            id: JourneyId::new(),
+            name: "Onboarding".to_string(),
+            description: "Test journey".to_string(),
+            steps: vec![
+                JourneyStep {
+                    id: step1_id,
+                    name: "Welcome".to_string(),
+                    prompt: "What's your name?".to_string(),
+                    expected_response: None,
+                    transitions: vec![crate::journey::Transition {
+                        condition: crate::journey::TransitionCondition::Always,
+                        next_step: step2_id,
+                    }],
+                    actions: vec![],
+                    captures: vec![],
+                },
+                JourneyStep {
+                    id: step2_id,
+                    name: "Done".to_string(),
+                    prompt: "Thanks!".to_string(),
+                    expected_response: None,
+                    transitions: vec![],
+                    actions: vec![],
+                    captures: vec![],
+                },
+            ],
+            initial_step: step1_id,
+            current_step: None,
+            created_at: Utc::now(),
+            enabled: true,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_process_message_renders_journey_step_with_llm() {
+        let provider: Box<dyn LLMProvider> = Box::new(MockProvider::new());
+        let agent = Agent::builder()
+            .name("Test Agent")
+            .provider(provider)
+            .config(AgentConfig {
+                enable_llm_journey_prompts: true,
+                ..Default::default()
+            })
+            .build()
+            .unwrap();
+
+        let journey = create_two_step_journey();
+        let journey_id = journey.id;
+        let step2_id = journey.steps[1].id;
+        agent.add_journey(journey).await.unwrap();
+
+        let session_id = agent.create_session().await.unwrap();
+        agent.start_journey(&session_id, &journey_id).await.unwrap();
+
+        let response = agent
+            .process_message(session_id, "Alice".to_string())
+            .await
+            .unwrap();
+
+        assert_eq!(response.journey_step, Some(step2_id));
+        assert_eq!(response.message, "Mock LLM response");
+    }
+
+    #[tokio::test]
+    async fn test_process_message_after_journey_completes_uses_guidelines() {
+        let provider: Box<dyn LLMProvider> = Box::new(MockProvider::new());
+        let agent = Agent::builder()
+            .name("Test Agent")
+            .provider(provider)
+            .build()
+            .unwrap();
+
+        agent
+            .add_guideline(Guideline::new(
+                GuidelineCondition::Literal("bye".to_string()),
+                GuidelineAction::template("Goodbye!"),
+                10,
+            ))
+            .await
+            .unwrap();
+
+        let journey = create_two_step_journey();
+        let journey_id = journey.id;
+        let step2_id = journey.steps[1].id;
+        agent.add_journey(journey).await.unwrap();
+
+        let session_id = agent.create_session().await.unwrap();
+        agent.start_journey(&session_id, &journey_id).await.unwrap();
+
+        let first = agent
+            .process_message(session_id, "Alice".to_string())
+            .await
+            .unwrap();
+        assert_eq!(first.message, "Thanks!");
+
+        // The final step has no transitions, so this message completes the journey
+        let second = agent
+            .process_message(session_id, "bye".to_string())
+            .await
+            .unwrap();
+        assert_eq!(second.message, "Goodbye!");
+        assert_eq!(second.journey_step, Some(step2_id));
+
+        let state = agent.get_journey_state(&session_id).await.unwrap().unwrap();
+        assert!(state.is_complete);
+    }
+
+    #[tokio::test]
+    async fn test_fallback_clarifies_then_escalates_after_repeated_fallbacks() {
+        let agent = Agent::builder()
+            .name("Test Agent")
+            .provider(Box::new(MockProvider::new()))
+            .fallback_strategy(FallbackStrategy::clarify("Did you mean:", 2))
+            .fallback_strategy_after(2, FallbackStrategy::escalate("Let me get a person."))
+            .build()
+            .unwrap();
+        agent
+            .add_guideline(Guideline::new(
+                GuidelineCondition::Literal("refund policy".to_string()),
+                GuidelineAction::template("Refunds take 5 days."),
+                10,
+            ))
+            .await
+            .unwrap();
+        let session_id = agent.create_session().await.unwrap();
+        let send = |message: &str| agent.process_message(session_id, message.to_string());
+
+        let response = send("Can I get my refunds?").await.unwrap();
+        assert_eq!(response.message, "Did you mean:\n- refund policy");
+
+        // A matched guideline resets the count
+        send("What is your refund policy?").await.unwrap();
+        send("hmm").await.unwrap();
+        let response = send("hmm").await.unwrap();
+        assert_eq!(response.message, "Did you mean:");
+        let session = agent.get_session(&session_id).await.unwrap().unwrap();
+        assert_eq!(session.consecutive_fallbacks, 2);
+
+        let response = send("hmm").await.unwrap();
+        assert_eq!(response.message, "Let me get a person.");
+        assert!(response.awaiting_human);
+        assert_eq!(agent.metrics().snapshot().fallbacks_used, 4);
+    }
+
+    #[tokio::test]
+    async fn test_fallback_starts_journey() {
+        let journey = create_two_step_journey();
+        let journey_id = journey.id;
+        let (step1_id, step2_id) = (journey.steps[0].id, journey.steps[1].id);
+        let agent = Agent::builder()
+            .name("Test Agent")
+            .provider(Box::new(MockProvider::new()))
+            .fallback_strategy(FallbackStrategy::start_journey(journey_id))
+            .build()
+            .unwrap();
+        agent.add_journey(journey).await.unwrap();
+
+        let session_id = agent.create_session().await.unwrap();
+        let response = agent
+            .process_message(session_id, "hello".to_string())
+            .await
+            .unwrap();
+        assert_eq!(response.message, "What's your name?");
+        assert_eq!(response.journey_step, Some(step1_id));
+
+        let response = agent
+            .process_message(session_id, "Alice".to_string())
+            .await
+            .unwrap();
+        assert_eq!(response.journey_step, Some(step2_id));
+        let session = agent.get_session(&session_id).await.unwrap().unwrap();
+        assert_eq!(session.consecutive_fallbacks, 0);
+    }
+
+    #[tokio::test]
+    async fn test_process_message_stream_emits_events_in_order() {
+        use futures::StreamExt;
+
+        let provider: Box<dyn LLMProvider> = Box::new(MockProvider::new());
+        let agent = Agent::builder()
+            .name("Test Agent")
+            .provider(provider)
+            .config(AgentConfig {
+                // Guideline tools only, so the LLM response is streamed token by token
+                max_tool_iterations: 0,
+                ..Default::default()
+            })
+            .build()
+            .unwrap();
+
+        let tool = MockTool::new("weather_tool".to_string(), "sunny, 72F".to_string());
+        let tool_id = agent.add_tool(Box::new(tool)).await.unwrap();
+
+        let mut guideline = Guideline::new(
+            GuidelineCondition::Literal("weather".to_string()),
+            GuidelineAction::llm_with_template("Report the weather"),
+            10,
+        );
+        guideline.tools.push(tool_id);
+        let guideline_id = guideline.id;
+        agent.add_guideline(guideline).await.unwrap();
+
+        let session_id = agent.create_session().await.unwrap();
+
+        let events: Vec<AgentEvent> = agent
+            .process_message_stream(session_id, "What's the weather?".to_string())
+            .map(|event| event.unwrap())
+            .collect()
+            .await;
+
+        assert!(matches!(
+            &events[0],
+            AgentEvent::GuidelineMatched(gm) if gm.guideline_id == guideline_id
+        ));
+        assert!(matches!(&events[1], AgentEvent::ToolStarted { tool_id: id } if *id == tool_id));
+        assert!(matches!(
+            &events[2],
+            AgentEvent::ToolFinished { success: true, .. }
+        ));
+        assert!(matches!(&events[3], AgentEvent::TextDelta(text) if text == "Mock"));
+        assert!(matches!(&events[4], AgentEvent::TextDelta(text) if text == " response"));
+
+        let AgentEvent::Completed(response) = &events[5] else {
+            panic!("expected Completed event, got {:?}", events[5]);
+        };
+        assert_eq!(response.message, "Mock response");
+        assert_eq!(events.len(), 6);
+
+        let session = agent.get_session(&session_id).await.unwrap().unwrap();
+        let last = session.context.messages.last().unwrap();
+        assert_eq!(last.content, "Mock response");
+    }
+
+    // Mock provider that calls `weather_tool` until it sees a tool result
+    struct ToolCallingProvider {
+        config: crate::provider::ProviderConfig,
+        always_call: bool,
+        requests: Arc<std::sync::Mutex<Vec<Vec<Message>>>>,
+    }
+
+    impl ToolCallingProvider {
+        fn new(always_call: bool) -> Self {
+            Self {
+                config: crate::provider::ProviderConfig::new("mock-model"),
+                always_call,
+                requests: Arc::new(std::sync::Mutex::new(Vec::new())),
+            }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl LLMProvider for ToolCallingProvider {
+        async fn complete(
+            &self,
+            _messages: Vec<Message>,
+        ) -> std::result::Result<String, AgentError> {
+            Ok("Final answer".to_string())
+        }
+
+        async fn complete_with_tools(
+            &self,
+            messages: Vec<Message>,
+            tools: Vec<ToolDefinition>,
+        ) -> std::result::Result<crate::provider::ToolCompletion, AgentError> {
+            assert_eq!(tools[0].name, "weather_tool");
+            let answered = messages
+                .last()
+                .is_some_and(|m| m.role == crate::context::MessageRole::Tool);
+            let call_number = self.requests.lock().unwrap().len();
+            self.requests.lock().unwrap().push(messages);
+
+            if answered && !self.always_call {
+                return Ok(crate::provider::ToolCompletion {
+                    content: "It is sunny".to_string(),
+                    tool_calls: Vec::new(),
+                    usage: crate::provider::TokenUsage::new(60, 5),
+                });
+            }
+
+            Ok(crate::provider::ToolCompletion {
+                content: String::new(),
+                tool_calls: vec![crate::tool::ToolCall {
+                    id: format!("call_{}", call_number),
+                    name: "weather_tool".to_string(),
+                    arguments: HashMap::from([("query".to_string(), serde_json::json!("Paris"))]),
+                }],
+                usage: crate::provider::TokenUsage::new(50, 10),
+            })
+        }
+
+        async fn stream(
+            &self,
+            _messages: Vec<Message>,
+        ) -> std::result::Result<
+            std::pin::Pin<Box<dyn futures::Stream<Item = crate::provider::StreamChunk> + Send>>,
+            AgentError,
+        > {
+            unimplemented!("streaming is not used in tool calling tests")
+        }
+
+        fn name(&self) -> &str {
+            "tool-calling-mock"
+        }
+
+        fn config(&self) -> &crate::provider::ProviderConfig {
+            &self.config
+        }
+    }
+
+    async fn create_tool_calling_agent(
+        provider: ToolCallingProvider,
+        config: AgentConfig,
+    ) -> Agent {
+        let agent = Agent::builder()
+            .name("Test Agent")
+            .provider(Box::new(provider))
+            .config(config)
+            .build()
+            .unwrap();
+
+        let tool = MockTool::new("weather_tool".to_string(), "sunny".to_string());
+        agent.add_tool(Box::new(tool)).await.unwrap();
+        agent
+            .add_guideline(Guideline::new(
+                GuidelineCondition::Literal("weather".to_string()),
+                GuidelineAction::llm_with_template("Answer weather questions"),
+                10,
+            ))
+            .await
+            .unwrap();
+        agent
+    }
+
+    #[tokio::test]
+    async fn test_llm_native_tool_calls_are_executed_and_fed_back() {
+        let provider = ToolCallingProvider::new(false);
+        let requests = provider.requests.clone();
+        let agent = create_tool_calling_agent(provider, AgentConfig::default()).await;
+
+        let session_id = agent.create_session().await.unwrap();
+        let response = agent
+            .process_message(session_id, "What's the weather in Paris?".to_string())
+            .await
+            .unwrap();
+
+        assert_eq!(response.message, "It is sunny");
+        assert_eq!(response.tools_used.len(), 1);
+        assert!(response.trace.is_none());
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        let second = &requests[1];
+        let call_message = &second[second.len() - 2];
+        assert_eq!(call_message.tool_calls()[0].id, "call_0");
+        let result_message = &second[second.len() - 1];
+        assert_eq!(result_message.role, crate::context::MessageRole::Tool);
+        assert_eq!(result_message.tool_call_id(), Some("call_0"));
+        assert!(result_message.content.contains("sunny"));
+    }
+
+    #[tokio::test]
+    async fn test_decision_trace_records_candidates_tools_and_llm_calls() {
+        let config = AgentConfig {
+            decision_trace: true,
+            store_decision_trace: true,
+            ..Default::default()
+        };
+        let agent = create_tool_calling_agent(ToolCallingProvider::new(false), config).await;
+        let weather_id = agent.list_guidelines().await[0].id;
+        agent
+            .add_guideline(Guideline::new(
+                GuidelineCondition::Regex(r"(?i)paris".to_string()),
+                GuidelineAction::template("Paris is lovely"),
+                1,
+            ))
+            .await
+            .unwrap();
+
+        let session_id = agent.create_session().await.unwrap();
+        let message = "What's the weather in Paris?";
+        let response = agent
+            .process_message(session_id, message.to_string())
+            .await
+            .unwrap();
+        let trace = response.trace.clone().unwrap();
+
+        assert_eq!(trace.user_message, message);
+        assert_eq!(trace.applied_guidelines, vec![weather_id]);
+        assert!(!trace.used_fallback);
+        let outcomes: Vec<_> = trace
+            .candidates
+            .iter()
+            .map(|c| (c.matched_text.clone(), c.outcome.clone()))
+            .collect();
+        assert!(outcomes.contains(&(Some("weather".to_string()), CandidateOutcome::Selected)));
+        assert!(outcomes.contains(&(
+            Some("Paris".to_string()),
+            CandidateOutcome::LowerPriority { winner: weather_id }
+        )));
+
+        assert_eq!(trace.tool_calls.len(), 1);
+        let tool_call = &trace.tool_calls[0];
+        assert_eq!(tool_call.tool_name, "weather_tool");
+        assert_eq!(tool_call.parameters["query"], serde_json::json!("Paris"));
+        assert_eq!(tool_call.attempts, 1);
+        assert!(tool_call.output.is_some() && tool_call.error.is_none());
+
+        assert_eq!(trace.llm_calls.len(), 2);
+        assert_eq!(trace.llm_calls[0].tool_calls[0].name, "weather_tool");
+        assert_eq!(trace.llm_calls[1].output, "It is sunny");
+        assert_eq!(
+            trace.llm_calls[1].messages.last().unwrap().role,
+            crate::context::MessageRole::Tool
+        );
+
+        let session = agent.get_session(&session_id).await.unwrap().unwrap();
+        let stored = DecisionTrace::from_message(session.context.messages.last().unwrap());
+        assert_eq!(stored, Some(trace));
+    }
+
+    #[tokio::test]
+    async fn test_llm_tool_loop_stops_at_max_iterations() {
+        let provider = ToolCallingProvider::new(true);
+        let requests = provider.requests.clone();
+        let config = AgentConfig {
+            max_tool_iterations: 2,
+            ..Default::default()
+        };
+        let agent = create_tool_calling_agent(provider, config).await;
+
+        let session_id = agent.create_session().await.unwrap();
+        let response = agent
+            .process_message(session_id, "What's the weather?".to_string())
+            .await
+            .unwrap();
+
+        assert_eq!(response.message, "Final answer");
+        assert_eq!(response.tools_used.len(), 2);
+        assert_eq!(requests.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_token_usage_is_reported_and_priced() {
+        let config = AgentConfig {
+            model_prices: HashMap::from([("mock".to_string(), ModelPrice::new(1.0, 2.0))]),
+            ..Default::default()
+        };
+        let agent = create_tool_calling_agent(ToolCallingProvider::new(false), config).await;
+
+        let session_id = agent.create_session().await.unwrap();
+        let response = agent
+            .process_message(session_id, "What's the weather in Paris?".to_string())
+            .await
+            .unwrap();
+
+        // Both rounds of the tool loop are counted
+        assert_eq!(response.usage, TokenUsage::new(110, 15));
+        assert!((response.cost - 0.00014).abs() < 1e-12);
+
+        agent
+            .process_message(session_id, "And tomorrow's weather?".to_string())
+            .await
+            .unwrap();
+        let session = agent.get_session(&session_id).await.unwrap().unwrap();
+        assert_eq!(session.token_usage, TokenUsage::new(220, 30));
+        assert!((session.cost - 0.00028).abs() < 1e-12);
+        assert_eq!(agent.metrics().snapshot().token_usage.total_tokens, 250);
+    }
+
+    #[tokio::test]
+    async fn test_exhausted_token_budget_switches_to_templates() {
+        let provider = ToolCallingProvider::new(false);
+        let requests = provider.requests.clone();
+        let config = AgentConfig {
+            session_token_budget: Some(100),
+            ..Default::default()
+        };
+        let agent = create_tool_calling_agent(provider, config).await;
+
+        let session_id = agent.create_session().await.unwrap();
+        let response = agent
+            .process_message(session_id, "What's the weather in Paris?".to_string())
+            .await
+            .unwrap();
+        assert_eq!(response.message, "It is sunny");
+        assert_eq!(response.usage.total_tokens, 125);
+
+        // The budget is used up, so the fallback template answers without the LLM
+        let response = agent
+            .process_message(session_id, "Hello".to_string())
+            .await
+            .unwrap();
+        assert_eq!(
+            response.message,
+            "I'm not sure how to help with that. Could you please rephrase your question?"
+        );
+        assert!(response.usage.is_empty());
+        assert_eq!(requests.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_template_uses_tool_output() {
+        let provider: Box<dyn LLMProvider> = Box::new(MockProvider::new());
+        let agent = Agent::builder()
+            .name("Test Agent")
+            .provider(provider)
+            .build()
+            .unwrap();
+
+        let tool = MockTool::new("weather_tool".to_string(), "sunny, 72F".to_string());
+        let tool_id = agent.add_tool(Box::new(tool)).await.unwrap();
+
+        let mut guideline = Guideline::new(
+            GuidelineCondition::Literal("weather".to_string()),
+            GuidelineAction::template("It's {tool.weather_tool.result} today"),
+            10,
+        );
+        guideline.tools.push(tool_id);
+        agent.add_guideline(guideline).await.unwrap();
+
+        let session_id = agent.create_session().await.unwrap();
+        let response = agent
+            .process_message(session_id, "What's the weather?".to_string())
+            .await
+            .unwrap();
+
+        assert_eq!(response.message, "It's sunny, 72F today");
+    }
+
+    // Mock provider that always returns the same text and records requests
+    struct StaticProvider {
+        config: crate::provider::ProviderConfig,
+        response: String,
+        requests: Arc<std::sync::Mutex<Vec<Vec<Message>>>>,
+    }
+
+    impl StaticProvider {
+        fn new(response: impl Into<String>) -> Self {
+            Self {
+                config: crate::provider::ProviderConfig::new("mock-model"),
+                response: response.into(),
+                requests: Arc::new(std::sync::Mutex::new(Vec::new())),
+            }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl LLMProvider for StaticProvider {
+        async fn complete(
+            &self,
+            messages: Vec<Message>,
+        ) -> std::result::Result<String, AgentError> {
+            self.requests.lock().unwrap().push(messages);
+            Ok(self.response.clone())
+        }
+
+        async fn stream(
+            &self,
+            _messages: Vec<Message>,
+        ) -> std::result::Result<
+            std::pin::Pin<Box<dyn futures::Stream<Item = crate::provider::StreamChunk> + Send>>,
+            AgentError,
+        > {
+            let chunks = vec![Ok(self.response.clone())];
+            Ok(Box::pin(futures::stream::iter(chunks)))
+        }
+
+        fn name(&self) -> &str {
+            "static"
+        }
+
+        fn config(&self) -> &crate::provider::ProviderConfig {
+            &self.config
+        }
+    }
+
+    #[tokio::test]
+    async fn test_regex_captures_are_validated_and_stored() {
+        use crate::context::Validator;
+
+        let provider: Box<dyn LLMProvider> = Box::new(MockProvider::new());
+        let agent = Agent::builder()
+            .name("Test Agent")
+            .provider(provider)
+            .build()
+            .unwrap();
+
+        let guideline = Guideline::new(
+            GuidelineCondition::Literal("order".to_string()),
+            GuidelineAction::template("Looking up order {context.order_id}"),
+            10,
+        )
+        .with_captures(vec![
+            VariableCapture::regex("order_id", r"#(\d+)").with_validator(Validator::Integer {
+                min: Some(1),
+                max: None,
+            }),
+            VariableCapture::regex("email", r"from (\S+)").with_validator(Validator::Email),
+        ]);
+        agent.add_guideline(guideline).await.unwrap();
+
+        let session_id = agent.create_session().await.unwrap();
+        let response = agent
+            .process_message(session_id, "Where is order #42 from me?".to_string())
+            .await
+            .unwrap();
+
+        assert_eq!(response.message, "Looking up order 42");
+        assert_eq!(response.context_updates.len(), 1);
+        assert_eq!(response.context_updates["order_id"], serde_json::json!(42));
+
+        let session = agent.get_session(&session_id).await.unwrap().unwrap();
+        let variable = session.context.get_variable("order_id").unwrap();
+        assert_eq!(variable.source_message_id, session.context.messages[0].id);
+        assert!(session.context.get_variable("email").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_journey_step_llm_capture() {
+        let agent = Agent::builder()
+            .name("Test Agent")
+            .provider(Box::new(StaticProvider::new(
+                "```json\n{\"name\": \"Alice\", \"company\": null}\n```",
+            )))
+            .build()
+            .unwrap();
+
+        let mut journey = create_two_step_journey();
+        journey.steps[0].captures = vec![
+            VariableCapture::llm("name", "The user's first name"),
+            VariableCapture::llm("company", "The user's employer"),
+        ];
+        let journey_id = journey.id;
+        agent.add_journey(journey).await.unwrap();
+
+        let session_id = agent.create_session().await.unwrap();
+        agent.start_journey(&session_id, &journey_id).await.unwrap();
+
+        let response = agent
+            .process_message(session_id, "Hi, I'm Alice".to_string())
+            .await
+            .unwrap();
+
+        assert_eq!(response.message, "Thanks!");
+        assert_eq!(response.context_updates.len(), 1);
+        assert_eq!(response.context_updates["name"], "Alice");
+    }
+
+    #[tokio::test]
+    async fn test_compose_mode_combines_matched_guidelines() {
+        let provider = StaticProvider::new("I'm sorry to hear that. Refunds take 5 days.");
+        let requests = provider.requests.clone();
+        let agent = Agent::builder()
+            .name("Test Agent")
+            .provider(Box::new(provider))
+            .config(AgentConfig {
+                guideline_selection: GuidelineSelection::Compose {
+                    min_relevance: 0.5,
+                    min_priority: 5,
+                },
+                max_tool_iterations: 0,
+                ..Default::default()
+            })
+            .build()
+            .unwrap();
+
+        let policy_tool = MockTool::new("refund_policy".to_string(), "5 days".to_string());
+        let policy_tool_id = agent.add_tool(Box::new(policy_tool)).await.unwrap();
+        let mood_tool = MockTool::new("log_mood".to_string(), "logged".to_string());
+        let mood_tool_id = agent.add_tool(Box::new(mood_tool)).await.unwrap();
+
+        let mut refund = Guideline::new(
+            GuidelineCondition::Literal("refund".to_string()),
+            GuidelineAction::template("Mention the refund policy"),
+            10,
+        );
+        refund.tools = vec![policy_tool_id];
+        let mut empathy = Guideline::new(
+            GuidelineCondition::Literal("angry".to_string()),
+            GuidelineAction::template("Be empathetic"),
+            20,
+        );
+        empathy.tools = vec![mood_tool_id, policy_tool_id];
+        let low_priority = Guideline::new(
+            GuidelineCondition::Literal("refund".to_string()),
+            GuidelineAction::template("Upsell premium"),
+            1,
+        );
+        let (refund_id, empathy_id) = (refund.id, empathy.id);
+        agent.add_guideline(refund).await.unwrap();
+        agent.add_guideline(empathy).await.unwrap();
+        agent.add_guideline(low_priority).await.unwrap();
+
+        let session_id = agent.create_session().await.unwrap();
+        let response = agent
+            .process_message(session_id, "I'm angry and want a refund".to_string())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            response.message,
+            "I'm sorry to hear that. Refunds take 5 days."
+        );
+        assert_eq!(response.matched_guideline.unwrap().guideline_id, empathy_id);
+
+        // Each tool runs once even when several guidelines list it
+        let tool_ids: Vec<_> = response.tools_used.iter().map(|t| t.tool_id).collect();
+        assert_eq!(tool_ids, vec![mood_tool_id, policy_tool_id]);
+
+        let explanation = response.explanation.unwrap();
+        assert_eq!(explanation.applied_guidelines, vec![empathy_id, refund_id]);
+        assert_eq!(explanation.guideline_matches.len(), 3);
+
+        let requests = requests.lock().unwrap();
+        let prompt = &requests[0][1].content;
+        assert_eq!(
+            prompt,
+            "Follow all of these guidelines:\n- Be empathetic\n- Mention the refund policy"
+        );
+    }
+
+    /// Matcher that routes on a classified intent instead of message text
+    struct IntentMatcher {
+        guidelines: Vec<Guideline>,
+    }
+
+    impl IntentMatcher {
+        fn classify(message: &str) -> Option<&'static str> {
+            message.contains("money back").then_some("refund")
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl GuidelineMatcher for IntentMatcher {
+        async fn match_guidelines(
+            &self,
+            message: &str,
+            _context: &Context,
+        ) -> Result<Vec<GuidelineMatch>> {
+            let Some(intent) = Self::classify(message) else {
+                return Ok(vec![]);
+            };
+
+            Ok(self
+                .guidelines
+                .iter()
+                .filter(|g| matches!(&g.condition, GuidelineCondition::Literal(label) if label == intent))
+                .map(|g| GuidelineMatch {
+                    guideline_id: g.id,
+                    relevance_score: 0.9,
+                    semantic_score: 0.9,
+                    matched_condition: intent.to_string(),
+                    extracted_parameters: HashMap::new(),
+                    explanation: Some(format!("Classified intent '{}'", intent)),
+                    span: None,
+                })
+                .collect())
+        }
+
+        async fn select_best_match(&self, matches: Vec<GuidelineMatch>) -> Option<GuidelineMatch> {
+            matches.into_iter().next()
+        }
+
+        async fn add_guideline(&mut self, guideline: Guideline) -> Result<GuidelineId> {
+            let id = guideline.id;
+            self.guidelines.push(guideline);
+            Ok(id)
+        }
+
+        async fn remove_guideline(&mut self, id: &GuidelineId) -> Result<()> {
+            self.guidelines.retain(|g| g.id != *id);
+            Ok(())
+        }
+
+        fn get_guidelines(&self) -> &[Guideline] {
+            &self.guidelines
+        }
+    }
+
+    #[tokio::test]
+    async fn test_builder_accepts_custom_guideline_matcher() {
+        let agent = Agent::builder()
+            .name("Test Agent")
+            .provider(Box::new(MockProvider::new()))
+            .guideline_matcher(Box::new(IntentMatcher { guidelines: vec![] }))
+            .build()
+            .unwrap();
+
+        let refund = Guideline::new(
+            GuidelineCondition::Literal("refund".to_string()),
+            GuidelineAction::template("Refunds take 5 days."),
+            10,
+        );
+        let refund_id = refund.id;
+        agent.add_guideline(refund).await.unwrap();
+
+        let session_id = agent.create_session().await.unwrap();
+        let response = agent
+            .process_message(session_id, "I want my money back".to_string())
+            .await
+            .unwrap();
+
+        assert_eq!(response.message, "Refunds take 5 days.");
+        let matched = response.matched_guideline.unwrap();
+        assert_eq!(matched.guideline_id, refund_id);
+        assert_eq!(matched.matched_condition, "refund");
+    }
+
+    /// Hook that records each point it runs at and rewrites data along the way
+    struct RecordingHook {
+        calls: Arc<std::sync::Mutex<Vec<&'static str>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl AgentHook for RecordingHook {
+        async fn before_matching(
+            &self,
+            _context: &HookContext,
+            message: &mut String,
+        ) -> Result<HookAction> {
+            self.calls.lock().unwrap().push("before_matching");
+            *message = message.replace("cost", "price");
+            Ok(HookAction::Continue)
+        }
+
+        async fn after_match_selected(
+            &self,
+            _context: &HookContext,
+            matches: &mut Vec<GuidelineMatch>,
+        ) -> Result<HookAction> {
+            self.calls.lock().unwrap().push("after_match_selected");
+            assert_eq!(matches.len(), 1);
+            Ok(HookAction::Continue)
+        }
+
+        async fn before_tool(
+            &self,
+            _context: &HookContext,
+            _tool_id: &ToolId,
+            parameters: &mut HashMap<String, serde_json::Value>,
+        ) -> Result<HookAction> {
+            self.calls.lock().unwrap().push("before_tool");
+            parameters.insert("query".to_string(), serde_json::json!("pro"));
+            Ok(HookAction::Continue)
+        }
+
+        async fn after_tool(
+            &self,
+            _context: &HookContext,
+            _tool_id: &ToolId,
+            result: &mut Result<ToolResult>,
+        ) -> Result<HookAction> {
+            self.calls.lock().unwrap().push("after_tool");
+            if let Ok(result) = result {
+                result.output = serde_json::json!({"result": "$49"});
+            }
+            Ok(HookAction::Continue)
+        }
+
+        async fn before_llm(
+            &self,
+            _context: &HookContext,
+            messages: &mut Vec<Message>,
+        ) -> Result<HookAction> {
+            self.calls.lock().unwrap().push("before_llm");
+            messages.push(Message::system("Answer in one sentence"));
+            Ok(HookAction::Continue)
+        }
+
+        async fn before_save(
+            &self,
+            _context: &HookContext,
+            response: &mut AgentResponse,
+        ) -> Result<HookAction> {
+            self.calls.lock().unwrap().push("before_save");
+            response.message.push_str(" (reviewed)");
+            Ok(HookAction::Continue)
+        }
+    }
+
+    /// Hook that answers before any tool runs
+    struct BlockToolsHook;
+
+    #[async_trait::async_trait]
+    impl AgentHook for BlockToolsHook {
+        async fn before_tool(
+            &self,
+            _context: &HookContext,
+            _tool_id: &ToolId,
+            _parameters: &mut HashMap<String, serde_json::Value>,
+        ) -> Result<HookAction> {
+            Ok(HookAction::Respond(
+                "Tools are disabled right now.".to_string(),
+            ))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hooks_run_in_order_and_can_modify_data() {
+        let provider = StaticProvider::new("Pro costs $49.");
+        let requests = provider.requests.clone();
+        let calls = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let agent = Agent::builder()
+            .name("Test Agent")
+            .provider(Box::new(provider))
+            .config(AgentConfig {
+                max_tool_iterations: 0,
+                ..Default::default()
+            })
+            .hook(Box::new(RecordingHook {
+                calls: calls.clone(),
+            }))
+            .build()
+            .unwrap();
+
+        let tool = MockTool::new("price_lookup".to_string(), "unknown".to_string());
+        let tool_id = agent.add_tool(Box::new(tool)).await.unwrap();
+        let mut guideline = Guideline::new(
+            GuidelineCondition::Literal("price".to_string()),
+            GuidelineAction::llm_with_template("Quote the price"),
+            10,
+        );
+        guideline.tools = vec![tool_id];
+        agent.add_guideline(guideline).await.unwrap();
+
+        let session_id = agent.create_session().await.unwrap();
+        let response = agent
+            .process_message(session_id, "What is the cost of pro?".to_string())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            *calls.lock().unwrap(),
+            vec![
+                "before_matching",
+                "after_match_selected",
+                "before_tool",
+                "after_tool",
+                "before_llm",
+                "before_save"
+            ]
+        );
+        assert_eq!(response.message, "Pro costs $49. (reviewed)");
+
+        let prompt = requests.lock().unwrap()[0].clone();
+        assert!(prompt.iter().any(|m| m.content.contains("$49")));
+        assert_eq!(prompt.last().unwrap().content, "Answer in one sentence");
+
+        // The rewritten input and the final response are what gets saved
+        let session = agent.get_session(&session_id).await.unwrap().unwrap();
+        let saved: Vec<_> = session
+            .context
+            .messages
+            .iter()
+            .map(|m| m.content.as_str())
+            .collect();
+        assert_eq!(
+            saved,
+            vec!["What is the price of pro?", "Pro costs $49. (reviewed)"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_hook_can_stop_processing_with_reply() {
+        let provider = StaticProvider::new("unused");
+        let requests = provider.requests.clone();
+        let agent = Agent::builder()
+            .name("Test Agent")
+            .provider(Box::new(provider))
+            .hook(Box::new(BlockToolsHook))
+            .build()
+            .unwrap();
+
+        let tool = MockTool::new("search".to_string(), "found".to_string());
+        let tool_id = agent.add_tool(Box::new(tool)).await.unwrap();
+        let mut guideline = Guideline::new(
+            GuidelineCondition::Literal("search".to_string()),
+            GuidelineAction::llm_with_template("Summarize the results"),
+            10,
+        );
+        guideline.tools = vec![tool_id];
+        agent.add_guideline(guideline).await.unwrap();
+
+        let session_id = agent.create_session().await.unwrap();
+        let response = agent
+            .process_message(session_id, "search for rust".to_string())
+            .await
+            .unwrap();
+
+        assert_eq!(response.message, "Tools are disabled right now.");
+        assert!(response.tools_used.is_empty());
+        assert!(requests.lock().unwrap().is_empty());
+
+        let session = agent.get_session(&session_id).await.unwrap().unwrap();
+        assert_eq!(session.context.messages.len(), 2);
+    }
+
+    /// Provider that returns queued responses in order, recording each request
+    struct QueuedProvider {
+        config: crate::provider::ProviderConfig,
+        responses: std::sync::Mutex<std::collections::VecDeque<String>>,
+        requests: Arc<std::sync::Mutex<Vec<Vec<Message>>>>,
+    }
+
+    impl QueuedProvider {
+        fn new(responses: &[&str]) -> Self {
+            Self {
+                config: crate::provider::ProviderConfig::new("mock-model"),
+                responses: std::sync::Mutex::new(responses.iter().map(|r| r.to_string()).collect()),
+                requests: Arc::new(std::sync::Mutex::new(Vec::new())),
+            }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl LLMProvider for QueuedProvider {
+        async fn complete(
+            &self,
+            messages: Vec<Message>,
+        ) -> std::result::Result<String, AgentError> {
+            self.requests.lock().unwrap().push(messages);
+            self.responses
+                .lock()
+                .unwrap()
+                .pop_front()
+                .ok_or_else(|| AgentError::ProviderError("No queued response".to_string()))
+        }
+
+        async fn stream(
+            &self,
+            messages: Vec<Message>,
+        ) -> std::result::Result<
+            std::pin::Pin<Box<dyn futures::Stream<Item = crate::provider::StreamChunk> + Send>>,
+            AgentError,
+        > {
+            let response = self.complete(messages).await;
+            Ok(Box::pin(futures::stream::iter(vec![response])))
+        }
+
+        fn name(&self) -> &str {
+            "queued"
+        }
+
+        fn config(&self) -> &crate::provider::ProviderConfig {
+            &self.config
+        }
+    }
+
+    async fn create_guarded_agent(provider: QueuedProvider, guardrails: Guardrails) -> Agent {
+        let agent = Agent::builder()
+            .name("Test Agent")
+            .provider(Box::new(provider))
+            .guardrails(guardrails)
+            .build()
+            .unwrap();
+        agent
+            .add_guideline(Guideline::new(
+                GuidelineCondition::Literal("refund".to_string()),
+                GuidelineAction::llm_with_template("Explain the refund policy")
+                    .with_must_mention(vec!["5 days".to_string()]),
+                10,
+            ))
+            .await
+            .unwrap();
+        agent
+    }
+
+    #[tokio::test]
+    async fn test_guardrail_violation_is_regenerated() {
+        let provider = QueuedProvider::new(&[
+            "Refunds are guaranteed!",
+            "Refunds are processed within 5 days.",
+        ]);
+        let requests = provider.requests.clone();
+        let agent =
+            create_guarded_agent(provider, Guardrails::new().forbid_phrase("guaranteed")).await;
+
+        let session_id = agent.create_session().await.unwrap();
+        let response = agent
+            .process_message(session_id, "I want a refund".to_string())
+            .await
+            .unwrap();
+
+        assert_eq!(response.message, "Refunds are processed within 5 days.");
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        let retry_prompt = requests[1].last().unwrap();
+        assert_eq!(retry_prompt.role, crate::context::MessageRole::System);
+        assert!(retry_prompt
+            .content
+            .contains("forbidden phrase 'guaranteed'"));
+        assert!(retry_prompt.content.contains("must mention '5 days'"));
+    }
+
+    #[tokio::test]
+    async fn test_guardrail_falls_back_after_max_retries() {
+        let provider = QueuedProvider::new(&["Soon.", "Very soon.", "Unused"]);
+        let requests = provider.requests.clone();
+        let guardrails = Guardrails::new()
+            .max_retries(1)
+            .fallback_response("Please contact support about refunds.");
+        let agent = create_guarded_agent(provider, guardrails).await;
+
+        let session_id = agent.create_session().await.unwrap();
+        let response = agent
+            .process_message(session_id, "I want a refund".to_string())
+            .await
+            .unwrap();
+
+        assert_eq!(response.message, "Please contact support about refunds.");
+        assert_eq!(requests.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_old_messages_are_folded_into_summary() {
+        let provider = QueuedProvider::new(&["The user asked about order 123.", "Hello again!"]);
+        let requests = provider.requests.clone();
+        let agent = Agent::builder()
+            .name("Test Agent")
+            .provider(Box::new(provider))
+            .config(AgentConfig {
+                max_context_messages: 4,
+                max_tool_iterations: 0,
+                ..Default::default()
+            })
+            .build()
+            .unwrap();
+        agent
+            .add_guideline(Guideline::new(
+                GuidelineCondition::Literal("order".to_string()),
+                GuidelineAction::template("Checking your order."),
+                10,
+            ))
+            .await
+            .unwrap();
+
+        let session_id = agent.create_session().await.unwrap();
+        for message in ["Where is order 123?", "Any news on my order?"] {
+            agent
+                .process_message(session_id, message.to_string())
+                .await
+                .unwrap();
+        }
+        assert!(requests.lock().unwrap().is_empty());
+
+        // The third turn would exceed the limit, so the two oldest messages are summarized
+        let response = agent
+            .process_message(session_id, "Hi".to_string())
+            .await
+            .unwrap();
+        assert_eq!(response.message, "Hello again!");
+
+        let requests = requests.lock().unwrap().clone();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[0][1].content.contains("User: Where is order 123?"));
+        assert!(requests[1]
+            .iter()
+            .any(|m| m.content
+                == "Summary of the earlier conversation: The user asked about order 123."));
+
+        let session = agent.get_session(&session_id).await.unwrap().unwrap();
+        assert_eq!(
+            session.context.summary.as_deref(),
+            Some("The user asked about order 123.")
+        );
+        let contents: Vec<_> = session
+            .context
+            .messages
+            .iter()
+            .map(|m| m.content.as_str())
+            .collect();
+        assert_eq!(
+            contents,
+            vec![
+                "Any news on my order?",
+                "Checking your order.",
+                "Hi",
+                "Hello again!"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_escalating_guideline_hands_session_to_operator() {
+        let provider = StaticProvider::new("Happy to help.");
+        let requests = provider.requests.clone();
+        let agent = Agent::builder()
+            .name("Test Agent")
+            .provider(Box::new(provider))
+            .build()
+            .unwrap();
+        agent
+            .add_guideline(Guideline::new(
+                GuidelineCondition::Literal("human".to_string()),
+                GuidelineAction::template("Connecting you with a person.").with_escalation(),
+                10,
+            ))
+            .await
+            .unwrap();
 
-        fn description(&self) -> &str {
-            "A mock tool for testing"
-        }
+        let session_id = agent.create_session().await.unwrap();
+        let response = agent
+            .process_message(session_id, "I want a human".to_string())
+            .await
+            .unwrap();
+        assert_eq!(response.message, "Connecting you with a person.");
+        assert!(response.awaiting_human);
 
-        fn parameters(&self) -> &HashMap<String, ParameterSchema> {
-            &self.parameters
-        }
+        // While the operator is in control, messages are only recorded
+        let response = agent
+            .process_message(session_id, "Hello?".to_string())
+            .await
+            .unwrap();
+        assert!(response.awaiting_human);
+        assert!(response.message.is_empty());
+        agent
+            .post_operator_message(&session_id, "Hi, this is Sam.")
+            .await
+            .unwrap();
+        assert!(requests.lock().unwrap().is_empty());
 
-        async fn execute(
-            &self,
-            _parameters: HashMap<String, serde_json::Value>,
-        ) -> Result<ToolResult> {
-            Ok(ToolResult {
-                output: serde_json::json!({ "result": self.result }),
-                error: None,
-                metadata: HashMap::new(),
-            })
-        }
+        agent.return_to_agent(&session_id).await.unwrap();
+        let response = agent
+            .process_message(session_id, "Thanks".to_string())
+            .await
+            .unwrap();
+        assert_eq!(response.message, "Happy to help.");
+        assert!(!response.awaiting_human);
+
+        let session = agent.get_session(&session_id).await.unwrap().unwrap();
+        assert!(session.is_active());
+        let roles: Vec<_> = session.context.messages.iter().map(|m| m.role).collect();
+        assert_eq!(
+            roles,
+            vec![
+                MessageRole::User,
+                MessageRole::Assistant,
+                MessageRole::User,
+                MessageRole::Operator,
+                MessageRole::User,
+                MessageRole::Assistant,
+            ]
+        );
     }
 
     #[tokio::test]
-    async fn test_agent_add_tool() {
-        let provider: Box<dyn LLMProvider> = Box::new(MockProvider::new());
+    async fn test_low_confidence_escalates_to_operator() {
+        let provider = StaticProvider::new("Unused");
+        let requests = provider.requests.clone();
         let agent = Agent::builder()
             .name("Test Agent")
-            .provider(provider)
+            .provider(Box::new(provider))
+            .config(AgentConfig {
+                escalation_threshold: Some(0.6),
+                ..Default::default()
+            })
             .build()
             .unwrap();
 
-        let tool = MockTool::new("test_tool".to_string(), "test result".to_string());
-        let tool_id = agent.add_tool(Box::new(tool)).await.unwrap();
+        let session_id = agent.create_session().await.unwrap();
+        let response = agent
+            .process_message(session_id, "Something unexpected".to_string())
+            .await
+            .unwrap();
+        assert!(response.awaiting_human);
+        assert_eq!(response.message, AgentConfig::default().escalation_message);
+        assert!(requests.lock().unwrap().is_empty());
 
-        // Verify tool was added
-        let tool_registry = &agent.tool_registry;
-        let retrieved_tool = tool_registry.get(&tool_id).await;
-        assert!(retrieved_tool.is_some());
-        assert_eq!(retrieved_tool.unwrap().name(), "test_tool");
+        let session = agent.get_session(&session_id).await.unwrap().unwrap();
+        assert_eq!(session.status, SessionStatus::AwaitingHuman);
+        assert!(session.metadata.contains_key("escalation_reason"));
     }
 
     #[tokio::test]
-    async fn test_agent_tool_execution_in_guideline() {
-        let provider: Box<dyn LLMProvider> = Box::new(MockProvider::new());
-        let mut agent = Agent::builder()
+    async fn test_operator_apis_require_escalated_session() {
+        let agent = Agent::builder()
             .name("Test Agent")
-            .provider(provider)
+            .provider(Box::new(MockProvider::new()))
             .build()
             .unwrap();
+        let session_id = agent.create_session().await.unwrap();
 
-        // Add a tool
-        let tool = MockTool::new("weather_tool".to_string(), "sunny, 72F".to_string());
-        let tool_id = agent.add_tool(Box::new(tool)).await.unwrap();
+        assert!(matches!(
+            agent.post_operator_message(&session_id, "Hi").await,
+            Err(AgentError::InvalidInput(_))
+        ));
+        assert!(matches!(
+            agent.return_to_agent(&session_id).await,
+            Err(AgentError::InvalidInput(_))
+        ));
+
+        agent
+            .escalate_to_human(&session_id, "Requested by support lead")
+            .await
+            .unwrap();
+        agent
+            .post_operator_message(&session_id, "Hi")
+            .await
+            .unwrap();
+    }
 
-        // Add a guideline that uses the tool
-        let guideline = Guideline {
-            id: GuidelineId::new(),
-            condition: GuidelineCondition::Literal("weather".to_string()),
-            action: GuidelineAction {
-                response_template: "Here's the weather".to_string(),
-                requires_llm: false,
-                parameters: Vec::new(),
-            },
-            priority: 10,
-            tools: vec![tool_id],
-            parameters: HashMap::new(),
-            created_at: chrono::Utc::now(),
+    #[tokio::test]
+    async fn test_runtime_guideline_management_on_shared_agent() {
+        let agent = Arc::new(
+            Agent::builder()
+                .name("Test Agent")
+                .provider(Box::new(MockProvider::new()))
+                .build()
+                .unwrap(),
+        );
+        let session_id = agent.create_session().await.unwrap();
+
+        let mut guideline = Guideline::new(
+            GuidelineCondition::Literal("refund".to_string()),
+            GuidelineAction::template("Refunds take 5 days."),
+            10,
+        );
+        let guideline_id = tokio::spawn({
+            let agent = agent.clone();
+            let guideline = guideline.clone();
+            async move { agent.add_guideline(guideline).await.unwrap() }
+        })
+        .await
+        .unwrap();
+        assert_eq!(agent.list_guidelines().await.len(), 1);
+
+        let reply = |agent: Arc<Agent>| async move {
+            agent
+                .process_message(session_id, "I want a refund".to_string())
+                .await
+                .unwrap()
+                .message
         };
+        assert_eq!(reply(agent.clone()).await, "Refunds take 5 days.");
 
-        agent.add_guideline(guideline).await.unwrap();
+        guideline.action = GuidelineAction::template("Refunds take 3 days.");
+        agent.update_guideline(guideline).await.unwrap();
+        assert_eq!(reply(agent.clone()).await, "Refunds take 3 days.");
 
-        // Create a session
+        agent.disable_guideline(&guideline_id).await.unwrap();
+        assert_eq!(reply(agent.clone()).await, "Mock LLM response");
+        assert!(!agent.list_guidelines().await[0].enabled);
+
+        agent.enable_guideline(&guideline_id).await.unwrap();
+        assert_eq!(reply(agent.clone()).await, "Refunds take 3 days.");
+
+        agent.remove_guideline(&guideline_id).await.unwrap();
+        assert!(agent.list_guidelines().await.is_empty());
+        assert!(matches!(
+            agent.remove_guideline(&guideline_id).await,
+            Err(AgentError::GuidelineNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_runtime_tool_management() {
+        let agent = Agent::builder()
+            .name("Test Agent")
+            .provider(Box::new(MockProvider::new()))
+            .build()
+            .unwrap();
+        let old_id = agent
+            .add_tool(Box::new(MockTool::new(
+                "weather_tool".to_string(),
+                "sunny".to_string(),
+            )))
+            .await
+            .unwrap();
+        let mut guideline = Guideline::new(
+            GuidelineCondition::Literal("weather".to_string()),
+            GuidelineAction::template("It is {tool.weather_tool.result}."),
+            10,
+        );
+        guideline.tools = vec![old_id];
+        agent.add_guideline(guideline).await.unwrap();
         let session_id = agent.create_session().await.unwrap();
 
-        // Process a message that triggers the guideline with the tool
+        let new_id = agent
+            .update_tool(Box::new(MockTool::new(
+                "weather_tool".to_string(),
+                "rainy".to_string(),
+            )))
+            .await
+            .unwrap();
+        assert_ne!(new_id, old_id);
+        assert_eq!(agent.list_tools().await.len(), 1);
+        assert_eq!(agent.list_guidelines().await[0].tools, vec![new_id]);
+
         let response = agent
-            .process_message(session_id, "What's the weather?".to_string())
+            .process_message(session_id, "How is the weather?".to_string())
             .await
             .unwrap();
+        assert_eq!(response.message, "It is rainy.");
 
-        // Verify tool was executed
-        assert_eq!(response.tools_used.len(), 1);
-        assert_eq!(response.tools_used[0].tool_id, tool_id);
+        agent.disable_tool(&new_id).await.unwrap();
+        let response = agent
+            .process_message(session_id, "How is the weather?".to_string())
+            .await
+            .unwrap();
+        assert!(response.tools_used.is_empty());
+
+        agent.remove_tool(&new_id).await.unwrap();
+        assert!(agent.list_tools().await.is_empty());
+        assert!(matches!(
+            agent.enable_tool(&new_id).await,
+            Err(AgentError::ToolNotFound(_))
+        ));
     }
 
     #[tokio::test]
-    async fn test_agent_multiple_tools_in_guideline() {
-        let provider: Box<dyn LLMProvider> = Box::new(MockProvider::new());
-        let mut agent = Agent::builder()
+    async fn test_runtime_journey_management() {
+        let agent = Agent::builder()
             .name("Test Agent")
-            .provider(provider)
+            .provider(Box::new(MockProvider::new()))
             .build()
             .unwrap();
+        let mut journey = create_two_step_journey();
+        let journey_id = agent.add_journey(journey.clone()).await.unwrap();
+        let session_id = agent.create_session().await.unwrap();
+        agent.start_journey(&session_id, &journey_id).await.unwrap();
 
-        // Add two tools
-        let tool1 = MockTool::new("tool1".to_string(), "result1".to_string());
-        let tool1_id = agent.add_tool(Box::new(tool1)).await.unwrap();
+        journey.name = "Welcome".to_string();
+        agent.update_journey(journey).await.unwrap();
+        assert_eq!(agent.list_journeys().await[0].name, "Welcome");
 
-        let tool2 = MockTool::new("tool2".to_string(), "result2".to_string());
-        let tool2_id = agent.add_tool(Box::new(tool2)).await.unwrap();
+        // Sessions already in a disabled journey continue; new ones cannot start it
+        agent.disable_journey(&journey_id).await.unwrap();
+        let response = agent
+            .process_message(session_id, "Alice".to_string())
+            .await
+            .unwrap();
+        assert!(response.journey_step.is_some());
+        let other_session = agent.create_session().await.unwrap();
+        assert!(matches!(
+            agent.start_journey(&other_session, &journey_id).await,
+            Err(AgentError::Journey(_))
+        ));
+
+        agent.enable_journey(&journey_id).await.unwrap();
+        agent
+            .start_journey(&other_session, &journey_id)
+            .await
+            .unwrap();
 
-        // Add a guideline that uses both tools
-        let guideline = Guideline {
-            id: GuidelineId::new(),
-            condition: GuidelineCondition::Literal("multi".to_string()),
-            action: GuidelineAction {
-                response_template: "Using multiple tools".to_string(),
-                requires_llm: false,
-                parameters: Vec::new(),
-            },
-            priority: 10,
-            tools: vec![tool1_id, tool2_id],
-            parameters: HashMap::new(),
-            created_at: chrono::Utc::now(),
-        };
+        agent.remove_journey(&journey_id).await.unwrap();
+        assert!(agent.list_journeys().await.is_empty());
+        assert!(matches!(
+            agent.update_journey(create_two_step_journey()).await,
+            Err(AgentError::Journey(_))
+        ));
+    }
 
-        agent.add_guideline(guideline).await.unwrap();
+    #[tokio::test]
+    async fn test_metrics_are_recorded() {
+        let metrics = Arc::new(Metrics::new());
+        let agent = Agent::builder()
+            .name("Test Agent")
+            .provider(Box::new(MockProvider::new()))
+            .metrics(metrics.clone())
+            .build()
+            .unwrap();
+        assert!(Arc::ptr_eq(agent.metrics(), &metrics));
 
-        // Create a session
-        let session_id = agent.create_session().await.unwrap();
+        let tool_id = agent
+            .add_tool(Box::new(MockTool::new(
+                "weather_tool".to_string(),
+                "sunny".to_string(),
+            )))
+            .await
+            .unwrap();
+        let mut guideline = Guideline::new(
+            GuidelineCondition::Literal("weather".to_string()),
+            GuidelineAction::template("It is {tool.weather_tool.result}."),
+            10,
+        );
+        guideline.tools = vec![tool_id];
+        let guideline_id = agent.add_guideline(guideline).await.unwrap();
 
-        // Process a message
+        let session_id = agent.create_session().await.unwrap();
         let response = agent
-            .process_message(session_id, "multi tool test".to_string())
+            .process_message(session_id, "How is the weather?".to_string())
             .await
             .unwrap();
-
-        // Verify both tools were executed
-        assert_eq!(response.tools_used.len(), 2);
-        assert!(response.tools_used.iter().any(|t| t.tool_id == tool1_id));
-        assert!(response.tools_used.iter().any(|t| t.tool_id == tool2_id));
+        assert!(response.tools_used[0].duration < Duration::from_millis(100));
+        agent
+            .process_message(session_id, "Hello".to_string())
+            .await
+            .unwrap();
+        agent.end_session(&session_id).await.unwrap();
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.messages_processed, 2);
+        assert_eq!(snapshot.fallbacks_used, 1);
+        assert_eq!(snapshot.guideline_hits[&guideline_id], 1);
+        assert_eq!(snapshot.tools["weather_tool"].successes, 1);
+        assert_eq!(snapshot.matching_latency.count, 2);
+        assert_eq!(snapshot.llm_latency.count, 1);
+        assert_eq!(snapshot.active_sessions, 0);
+        assert!(metrics
+            .render_prometheus()
+            .contains("talk_tool_successes_total{tool=\"weather_tool\"} 1\n"));
     }
 
     #[tokio::test]
-    async fn test_agent_tool_with_llm_response() {
-        let provider: Box<dyn LLMProvider> = Box::new(MockProvider::new());
-        let mut agent = Agent::builder()
+    async fn test_closed_and_paused_sessions_reject_messages() {
+        let agent = Agent::builder()
             .name("Test Agent")
-            .provider(provider)
+            .provider(Box::new(MockProvider::new()))
             .build()
             .unwrap();
+        let hello = || "Hello".to_string();
 
-        // Add a tool
-        let tool = MockTool::new("data_tool".to_string(), "data result".to_string());
-        let tool_id = agent.add_tool(Box::new(tool)).await.unwrap();
+        let session_id = agent.create_session().await.unwrap();
+        agent.pause_session(&session_id).await.unwrap();
+        assert!(matches!(
+            agent.process_message(session_id, hello()).await,
+            Err(AgentError::SessionPaused(id)) if id == session_id
+        ));
+        agent.resume_session(&session_id).await.unwrap();
+        assert!(agent.process_message(session_id, hello()).await.is_ok());
+
+        agent.end_session(&session_id).await.unwrap();
+        assert!(matches!(
+            agent.process_message(session_id, hello()).await,
+            Err(AgentError::SessionCompleted(_))
+        ));
+        assert!(matches!(
+            agent.pause_session(&session_id).await,
+            Err(AgentError::SessionCompleted(_))
+        ));
 
-        // Add a guideline that uses the tool AND requires LLM
-        let guideline = Guideline {
-            id: GuidelineId::new(),
-            condition: GuidelineCondition::Literal("analyze".to_string()),
-            action: GuidelineAction {
-                response_template: "Analyzing data".to_string(),
-                requires_llm: true, // This should use LLM to generate response
-                parameters: Vec::new(),
-            },
-            priority: 10,
-            tools: vec![tool_id],
-            parameters: HashMap::new(),
-            created_at: chrono::Utc::now(),
-        };
+        let session_id = agent.create_session().await.unwrap();
+        agent.terminate_session(&session_id).await.unwrap();
+        assert!(matches!(
+            agent.process_message(session_id, hello()).await,
+            Err(AgentError::SessionTerminated(_))
+        ));
+        assert_eq!(agent.metrics().snapshot().active_sessions, 0);
+    }
 
-        agent.add_guideline(guideline).await.unwrap();
+    #[tokio::test]
+    async fn test_idle_timeout_slides_and_expires_sessions() {
+        let agent = Agent::builder()
+            .name("Test Agent")
+            .provider(Box::new(MockProvider::new()))
+            .config(AgentConfig {
+                session_idle_timeout: Some(Duration::from_secs(600)),
+                ..Default::default()
+            })
+            .build()
+            .unwrap();
 
-        // Create a session
         let session_id = agent.create_session().await.unwrap();
+        let created = agent.get_session(&session_id).await.unwrap().unwrap();
+        let first_expiry = created.expires_at.unwrap();
 
-        // Process a message
-        let response = agent
-            .process_message(session_id, "analyze this data".to_string())
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        agent
+            .process_message(session_id, "Hello".to_string())
             .await
             .unwrap();
+        let mut session = agent.get_session(&session_id).await.unwrap().unwrap();
+        assert!(session.expires_at.unwrap() > first_expiry);
 
-        // Verify tool was executed
-        assert_eq!(response.tools_used.len(), 1);
-        assert_eq!(response.tools_used[0].tool_id, tool_id);
-
-        // Response should come from LLM (our mock returns "Mock LLM response")
-        assert_eq!(response.message, "Mock LLM response");
+        // Nothing happened for longer than the idle timeout
+        session.expires_at = Some(Utc::now() - chrono::Duration::seconds(1));
+        agent
+            .session_store
+            .update(&session_id, session)
+            .await
+            .unwrap();
+        assert!(matches!(
+            agent.process_message(session_id, "Hello".to_string()).await,
+            Err(AgentError::SessionExpired(_))
+        ));
     }
 
     #[tokio::test]
-    async fn test_agent_guideline_without_tools() {
-        let provider: Box<dyn LLMProvider> = Box::new(MockProvider::new());
-        let mut agent = Agent::builder()
+    async fn test_session_reaper_deletes_expired_sessions() {
+        let agent = Agent::builder()
             .name("Test Agent")
-            .provider(provider)
+            .provider(Box::new(MockProvider::new()))
+            .config(AgentConfig {
+                session_retention: Duration::ZERO,
+                ..Default::default()
+            })
             .build()
             .unwrap();
 
-        // Add a guideline without any tools
-        let guideline = Guideline {
-            id: GuidelineId::new(),
-            condition: GuidelineCondition::Literal("hello".to_string()),
-            action: GuidelineAction {
-                response_template: "Hello there!".to_string(),
-                requires_llm: false,
-                parameters: Vec::new(),
-            },
-            priority: 10,
-            tools: Vec::new(), // No tools
-            parameters: HashMap::new(),
-            created_at: chrono::Utc::now(),
-        };
+        let expired = Session::new(agent.id).with_expiration(Utc::now());
+        let expired_id = agent.session_store.create(expired).await.unwrap();
+        let active_id = agent.create_session().await.unwrap();
 
-        agent.add_guideline(guideline).await.unwrap();
+        let reaper = agent.spawn_session_reaper(Duration::from_millis(10));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        reaper.abort();
 
-        // Create a session
-        let session_id = agent.create_session().await.unwrap();
+        assert!(agent.get_session(&expired_id).await.unwrap().is_none());
+        assert!(agent.get_session(&active_id).await.unwrap().is_some());
+    }
 
-        // Process a message
-        let response = agent
-            .process_message(session_id, "hello".to_string())
+    #[tokio::test]
+    async fn test_repeated_idempotency_key_replays_response() {
+        let provider = StaticProvider::new("Hi there");
+        let requests = provider.requests.clone();
+        let agent = Agent::builder()
+            .name("Test Agent")
+            .provider(Box::new(provider))
+            .build()
+            .unwrap();
+        let tool_id = agent
+            .add_tool(Box::new(MockTool::new(
+                "weather_tool".to_string(),
+                "sunny".to_string(),
+            )))
             .await
             .unwrap();
+        let mut guideline = Guideline::new(
+            GuidelineCondition::Literal("weather".to_string()),
+            GuidelineAction::template("It is {tool.weather_tool.result}."),
+            10,
+        );
+        guideline.tools = vec![tool_id];
+        agent.add_guideline(guideline).await.unwrap();
 
-        // Verify no tools were executed
-        assert_eq!(response.tools_used.len(), 0);
-        assert_eq!(response.message, "Hello there!");
+        let session_id = agent.create_session().await.unwrap();
+        let send = |message: &str, key: &str| {
+            agent.process_message_with_options(
+                session_id,
+                message.to_string(),
+                MessageOptions::new().with_idempotency_key(key),
+            )
+        };
+
+        let first = send("How is the weather?", "retry-1").await.unwrap();
+        let retried = send("How is the weather?", "retry-1").await.unwrap();
+        assert_eq!(retried.message, "It is sunny.");
+        assert_eq!(
+            serde_json::to_value(&retried).unwrap(),
+            serde_json::to_value(&first).unwrap()
+        );
+
+        send("Hello", "retry-2").await.unwrap();
+        send("Hello", "retry-2").await.unwrap();
+
+        // Retries did not run the tool or the LLM again
+        let snapshot = agent.metrics().snapshot();
+        assert_eq!(snapshot.tools["weather_tool"].successes, 1);
+        assert_eq!(snapshot.messages_processed, 2);
+        assert_eq!(requests.lock().unwrap().len(), 1);
+
+        // Stored responses survive the session's serialized form
+        let session = agent.get_session(&session_id).await.unwrap().unwrap();
+        assert_eq!(session.context.messages.len(), 4);
+        let restored: Session =
+            serde_json::from_str(&serde_json::to_string(&session).unwrap()).unwrap();
+        assert_eq!(
+            restored.idempotent_response("retry-1"),
+            Some(&serde_json::to_value(&first).unwrap())
+        );
     }
 }
@@ -0,0 +1,33 @@
+//! Serve an agent definition file over HTTP
+//!
+//! ```bash
+//! TALK_ADDR=0.0.0.0:8080 talk-server agent.yaml
+//! ```
+//!
+//! The address defaults to `127.0.0.1:8080` and can be set with `TALK_ADDR`.
+//! Log verbosity is read from `RUST_LOG`. Definitions that reference tools
+//! cannot be served, since tools are registered in code.
+
+use std::sync::Arc;
+use talk::Agent;
+use tracing_subscriber::EnvFilter;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
+        )
+        .init();
+
+    let Some(path) = std::env::args().nth(1) else {
+        eprintln!("Usage: talk-server <agent definition file>");
+        std::process::exit(2);
+    };
+    let address = std::env::var("TALK_ADDR").unwrap_or_else(|_| "127.0.0.1:8080".to_string());
+
+    let agent = Agent::from_config_file(&path, Vec::new()).await?;
+    let listener = tokio::net::TcpListener::bind(&address).await?;
+    talk::server::serve(listener, Arc::new(agent)).await?;
+    Ok(())
+}
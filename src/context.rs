@@ -3,6 +3,7 @@
 //! This module provides data structures for managing conversation context,
 //! messages, and context variables extracted from user inputs.
 
+use crate::tool::ToolCall;
 use crate::types::MessageId;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
@@ -20,6 +21,10 @@ pub enum MessageRole {
     Assistant,
     /// Tool execution result
     Tool,
+    /// Message from a human operator who has taken over the conversation
+    ///
+    /// Sent to LLM providers as an assistant message.
+    Operator,
 }
 
 /// A single message in the conversation
@@ -83,6 +88,71 @@ impl Message {
         }
     }
 
+    /// Create a new human operator message
+    pub fn operator(content: impl Into<String>) -> Self {
+        Self {
+            id: MessageId::new(),
+            role: MessageRole::Operator,
+            content: content.into(),
+            metadata: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    /// Create an assistant message that requests tool calls
+    pub fn assistant_tool_calls(content: impl Into<String>, tool_calls: &[ToolCall]) -> Self {
+        Self::assistant(content).with_metadata(
+            "tool_calls",
+            serde_json::to_value(tool_calls).unwrap_or_default(),
+        )
+    }
+
+    /// Create a tool message answering the tool call with the given ID
+    pub fn tool_result(tool_call_id: impl Into<String>, content: impl Into<String>) -> Self {
+        Self::tool(content).with_metadata("tool_call_id", tool_call_id.into().into())
+    }
+
+    /// Tool calls requested by this message, if any
+    pub fn tool_calls(&self) -> Vec<ToolCall> {
+        self.metadata
+            .as_ref()
+            .and_then(|metadata| metadata.get("tool_calls"))
+            .and_then(|value| serde_json::from_value(value.clone()).ok())
+            .unwrap_or_default()
+    }
+
+    /// ID of the tool call this message answers, if any
+    pub fn tool_call_id(&self) -> Option<&str> {
+        self.metadata
+            .as_ref()
+            .and_then(|metadata| metadata.get("tool_call_id"))
+            .and_then(|value| value.as_str())
+    }
+
+    /// Pin the message so context windowing never drops it
+    pub fn pinned(self) -> Self {
+        self.with_metadata("pinned", true.into())
+    }
+
+    /// Whether the message is pinned
+    pub fn is_pinned(&self) -> bool {
+        self.metadata
+            .as_ref()
+            .and_then(|metadata| metadata.get("pinned"))
+            .and_then(|value| value.as_bool())
+            .unwrap_or(false)
+    }
+
+    /// Whether context windowing must keep this message (system and pinned messages)
+    pub fn is_preserved(&self) -> bool {
+        self.role == MessageRole::System || self.is_pinned()
+    }
+
+    /// Rough token count of the message, at about four characters per token
+    pub fn estimated_tokens(&self) -> usize {
+        estimate_tokens(&self.content) + MESSAGE_TOKEN_OVERHEAD
+    }
+
     /// Add metadata to the message
     pub fn with_metadata(mut self, key: impl Into<String>, value: serde_json::Value) -> Self {
         self.metadata
@@ -92,6 +162,14 @@ impl Message {
     }
 }
 
+/// Tokens added per message for role and formatting
+const MESSAGE_TOKEN_OVERHEAD: usize = 4;
+
+/// Rough token count of a text, at about four characters per token
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
 /// Validator for context variables
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
@@ -316,6 +394,71 @@ impl ContextVariable {
     }
 }
 
+/// Where the value of a captured context variable comes from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CaptureSource {
+    /// Regex over the user message; uses the capture group named after the
+    /// variable, else the first capture group, else the whole match
+    Regex { pattern: String },
+    /// Structured extraction by the LLM, guided by a description of the value
+    Llm { description: String },
+}
+
+/// Declaration of a context variable to capture from user messages
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VariableCapture {
+    /// Name of the variable to store
+    pub name: String,
+    /// How the value is extracted
+    pub source: CaptureSource,
+    /// Optional validator the value must pass before it is stored
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub validator: Option<Validator>,
+}
+
+impl VariableCapture {
+    /// Capture a variable with a regex
+    pub fn regex(name: impl Into<String>, pattern: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            source: CaptureSource::Regex {
+                pattern: pattern.into(),
+            },
+            validator: None,
+        }
+    }
+
+    /// Capture a variable from free text with the LLM
+    pub fn llm(name: impl Into<String>, description: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            source: CaptureSource::Llm {
+                description: description.into(),
+            },
+            validator: None,
+        }
+    }
+
+    /// Add a validator to the capture
+    pub fn with_validator(mut self, validator: Validator) -> Self {
+        self.validator = Some(validator);
+        self
+    }
+
+    /// Convert raw extracted text to the JSON type expected by the validator
+    pub fn coerce(&self, raw: &str) -> serde_json::Value {
+        let raw = raw.trim();
+        let coerced = match self.validator {
+            Some(Validator::Integer { .. }) => raw.parse::<i64>().ok().map(Into::into),
+            Some(Validator::Float { .. }) => raw.parse::<f64>().ok().map(Into::into),
+            Some(Validator::Boolean) => raw.to_lowercase().parse::<bool>().ok().map(Into::into),
+            _ => None,
+        };
+        coerced.unwrap_or_else(|| raw.into())
+    }
+}
+
 /// Context for a conversation session
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Context {
@@ -326,6 +469,9 @@ pub struct Context {
     /// Maximum number of messages to keep in context
     #[serde(default = "default_max_messages")]
     pub max_messages: usize,
+    /// Rolling summary of messages that were dropped from the history
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
 }
 
 fn default_max_messages() -> usize {
@@ -339,6 +485,7 @@ impl Context {
             messages: Vec::new(),
             variables: HashMap::new(),
             max_messages: default_max_messages(),
+            summary: None,
         }
     }
 
@@ -348,18 +495,75 @@ impl Context {
             messages: Vec::new(),
             variables: HashMap::new(),
             max_messages,
+            summary: None,
         }
     }
 
     /// Add a message to the context
+    ///
+    /// Once `max_messages` is exceeded the oldest messages are dropped, except
+    /// system and pinned messages. The agent folds old messages into
+    /// [`Context::summary`] before this limit is reached.
     pub fn add_message(&mut self, message: Message) {
         self.messages.push(message);
 
         // Trim old messages if we exceed the limit
         if self.messages.len() > self.max_messages {
-            let excess = self.messages.len() - self.max_messages;
-            self.messages.drain(0..excess);
+            let mut excess = self.messages.len() - self.max_messages;
+            self.messages.retain(|m| {
+                if excess > 0 && !m.is_preserved() {
+                    excess -= 1;
+                    false
+                } else {
+                    true
+                }
+            });
+        }
+    }
+
+    /// Rough token count of the history and summary
+    pub fn estimated_tokens(&self) -> usize {
+        let summary = self.summary.as_deref().map(estimate_tokens).unwrap_or(0);
+        summary
+            + self
+                .messages
+                .iter()
+                .map(Message::estimated_tokens)
+                .sum::<usize>()
+    }
+
+    /// Oldest messages that must be dropped to fit within the limits
+    ///
+    /// System and pinned messages are never returned, and neither is the most
+    /// recent message. Messages are returned oldest first.
+    pub fn overflow(&self, max_messages: usize, max_tokens: Option<usize>) -> Vec<Message> {
+        let mut count = self.messages.len();
+        let mut tokens = self.estimated_tokens();
+        let fits = |count: usize, tokens: usize| {
+            count <= max_messages && max_tokens.is_none_or(|max| tokens <= max)
+        };
+
+        let mut overflow = Vec::new();
+        let candidates = self.messages.len().saturating_sub(1);
+        for message in self.messages[..candidates]
+            .iter()
+            .filter(|m| !m.is_preserved())
+        {
+            if fits(count, tokens) {
+                break;
+            }
+            count -= 1;
+            tokens -= message.estimated_tokens();
+            overflow.push(message.clone());
         }
+        overflow
+    }
+
+    /// Replace the given messages with a summary of the conversation so far
+    pub fn fold_into_summary(&mut self, messages: &[Message], summary: impl Into<String>) {
+        self.messages
+            .retain(|m| !messages.iter().any(|folded| folded.id == m.id));
+        self.summary = Some(summary.into());
     }
 
     /// Add a context variable
@@ -382,10 +586,11 @@ impl Context {
         self.messages.last()
     }
 
-    /// Clear all messages and variables
+    /// Clear all messages, variables and the summary
     pub fn clear(&mut self) {
         self.messages.clear();
         self.variables.clear();
+        self.summary = None;
     }
 }
 
@@ -418,6 +623,24 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_message_tool_calls_roundtrip() {
+        let call = ToolCall {
+            id: "call_1".to_string(),
+            name: "weather".to_string(),
+            arguments: HashMap::from([("city".to_string(), serde_json::json!("Paris"))]),
+        };
+
+        let request = Message::assistant_tool_calls("", std::slice::from_ref(&call));
+        assert_eq!(request.role, MessageRole::Assistant);
+        assert_eq!(request.tool_calls(), vec![call]);
+
+        let result = Message::tool_result("call_1", "sunny");
+        assert_eq!(result.role, MessageRole::Tool);
+        assert_eq!(result.tool_call_id(), Some("call_1"));
+        assert!(result.tool_calls().is_empty());
+    }
+
     #[test]
     fn test_message_serialization() {
         let msg = Message::system("System prompt");
@@ -498,6 +721,29 @@ mod tests {
         assert!(invalid_var.validate().is_err());
     }
 
+    #[test]
+    fn test_variable_capture_coerce() {
+        let age = VariableCapture::regex("age", r"(\d+)").with_validator(Validator::Integer {
+            min: None,
+            max: None,
+        });
+        assert_eq!(age.coerce("42"), serde_json::json!(42));
+        assert_eq!(age.coerce("forty"), serde_json::json!("forty"));
+
+        let name = VariableCapture::llm("name", "The user's name");
+        assert_eq!(name.coerce(" Alice "), serde_json::json!("Alice"));
+    }
+
+    #[test]
+    fn test_variable_capture_serialization() {
+        let capture = VariableCapture::regex("email", r"\S+@\S+").with_validator(Validator::Email);
+        let json = serde_json::to_value(&capture).unwrap();
+        assert_eq!(json["source"]["type"], "regex");
+
+        let deserialized: VariableCapture = serde_json::from_value(json).unwrap();
+        assert_eq!(deserialized, capture);
+    }
+
     #[test]
     fn test_context_creation() {
         let ctx = Context::new();
@@ -530,6 +776,47 @@ mod tests {
         assert_eq!(ctx.messages[2].content, "Message 4");
     }
 
+    #[test]
+    fn test_context_max_messages_keeps_system_and_pinned() {
+        let mut ctx = Context::with_max_messages(3);
+        ctx.add_message(Message::system("Be concise"));
+        ctx.add_message(Message::user("My account is 42").pinned());
+
+        for i in 0..3 {
+            ctx.add_message(Message::user(format!("Message {}", i)));
+        }
+
+        let contents: Vec<_> = ctx.messages.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(
+            contents,
+            vec!["Be concise", "My account is 42", "Message 2"]
+        );
+    }
+
+    #[test]
+    fn test_context_overflow_and_summary() {
+        let mut ctx = Context::new();
+        ctx.add_message(Message::system("Be concise"));
+        for i in 0..4 {
+            ctx.add_message(Message::user(format!("Message {}", i)));
+        }
+        ctx.messages[2] = ctx.messages[2].clone().pinned();
+
+        // Message 1 is pinned, so Message 0 and Message 2 are dropped
+        let overflow = ctx.overflow(3, None);
+        let contents: Vec<_> = overflow.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, vec!["Message 0", "Message 2"]);
+
+        ctx.fold_into_summary(&overflow, "User sent messages 0 and 2");
+        assert_eq!(ctx.messages.len(), 3);
+        assert_eq!(ctx.summary.as_deref(), Some("User sent messages 0 and 2"));
+
+        // A token budget drops messages until the rest fits
+        let budget = ctx.estimated_tokens() - 1;
+        let overflow = ctx.overflow(10, Some(budget));
+        assert!(overflow.is_empty(), "the newest message is never dropped");
+    }
+
     #[test]
     fn test_context_add_variable() {
         let mut ctx = Context::new();
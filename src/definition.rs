@@ -0,0 +1,882 @@
+//! Declarative agent definitions
+//!
+//! An [`AgentDefinition`] describes a whole agent - name, configuration,
+//! provider, guidelines and journeys - in one YAML, JSON or TOML file, so its
+//! behaviour can be changed without recompiling:
+//!
+//! ```yaml
+//! name: Support Bot
+//! description: Answers billing questions
+//! provider:
+//!   type: openai
+//!   model: gpt-4o
+//! config:
+//!   max_context_messages: 50
+//! guidelines:
+//!   - contains: refund
+//!     response: Refunds take 5 business days.
+//!     priority: 10
+//!   - matches: "order #(\\d+)"
+//!     response: Look up the order and summarize its status.
+//!     use_llm: true
+//!     tools: [lookup_order]
+//! journeys:
+//!   - name: Onboarding
+//!     steps:
+//!       - key: ask_name
+//!         prompt: What's your name?
+//!         transitions:
+//!           - to: done
+//!       - key: done
+//!         prompt: Thanks, you're all set!
+//! ```
+//!
+//! Tools are referenced by name and resolved against the agent's
+//! [`ToolRegistry`](crate::ToolRegistry); journey steps are referenced by their
+//! `key`. Every problem found is reported at once in
+//! [`AgentError::InvalidDefinition`]: syntax errors by line and column, other
+//! problems by their path in the document, e.g. `guidelines[1].tools[0]`.
+//!
+//! Load a definition with [`Agent::from_config_file`](crate::Agent::from_config_file),
+//! or combine [`AgentBuilder::from_definition`](crate::AgentBuilder::from_definition)
+//! and [`Agent::add_definition`](crate::Agent::add_definition) to supply the
+//! provider or session store in code.
+
+use crate::agent::AgentConfig;
+use crate::context::{CaptureSource, VariableCapture};
+use crate::error::{AgentError, Result};
+use crate::guideline::{Guideline, GuidelineAction, GuidelineCondition};
+use crate::journey::{Journey, JourneyStep, Transition, TransitionCondition};
+use crate::provider::{AnthropicProvider, LLMProvider, OpenAIProvider};
+use crate::types::{JourneyId, StepId, ToolId};
+use chrono::Utc;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// An agent described in a definition file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AgentDefinition {
+    pub name: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    #[serde(default)]
+    pub config: AgentConfig,
+
+    /// LLM provider; may be omitted when the provider is supplied in code
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<ProviderDefinition>,
+
+    #[serde(default)]
+    pub guidelines: Vec<GuidelineDefinition>,
+
+    #[serde(default)]
+    pub journeys: Vec<JourneyDefinition>,
+
+    /// File the definition was loaded from, used to locate issues
+    #[serde(skip)]
+    source: Option<PathBuf>,
+}
+
+/// LLM provider settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProviderDefinition {
+    #[serde(rename = "type")]
+    pub kind: ProviderKind,
+
+    pub model: String,
+
+    /// Environment variable holding the API key
+    ///
+    /// Defaults to `OPENAI_API_KEY` or `ANTHROPIC_API_KEY`. Keys are never
+    /// read from the definition itself.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key_env: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+}
+
+/// Supported LLM providers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderKind {
+    OpenAI,
+    Anthropic,
+}
+
+impl ProviderKind {
+    fn default_api_key_env(self) -> &'static str {
+        match self {
+            ProviderKind::OpenAI => "OPENAI_API_KEY",
+            ProviderKind::Anthropic => "ANTHROPIC_API_KEY",
+        }
+    }
+}
+
+/// A guideline; exactly one of `contains` and `matches` must be set
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GuidelineDefinition {
+    /// Case-insensitive text the message must contain
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contains: Option<String>,
+
+    /// Regex the message must match
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub matches: Option<String>,
+
+    /// Response template, or LLM instructions when `use_llm` is set
+    pub response: String,
+
+    #[serde(default)]
+    pub use_llm: bool,
+
+    #[serde(default)]
+    pub priority: i32,
+
+    /// Names of the regex capture groups extracted as parameters, in order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub parameters: Vec<String>,
+
+    /// Names of registered tools to run
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<String>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub captures: Vec<VariableCapture>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub must_mention: Vec<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<serde_json::Value>,
+
+    /// Name of the agent a router hands the conversation off to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub handoff: Option<String>,
+
+    /// Hand the session to a human operator after responding
+    #[serde(default)]
+    pub escalate: bool,
+}
+
+/// A journey whose steps refer to each other by key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JourneyDefinition {
+    pub name: String,
+
+    #[serde(default)]
+    pub description: String,
+
+    /// Key of the first step; defaults to the first step listed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start: Option<String>,
+
+    pub steps: Vec<StepDefinition>,
+}
+
+/// A journey step
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StepDefinition {
+    /// Key used by transitions and `start`
+    pub key: String,
+
+    /// Display name; defaults to the key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    pub prompt: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected_response: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub transitions: Vec<TransitionDefinition>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub actions: Vec<String>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub captures: Vec<VariableCapture>,
+}
+
+/// A transition to another step
+///
+/// Without `matches` or `variable` the transition is always taken.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TransitionDefinition {
+    /// Key of the next step
+    pub to: String,
+
+    /// Regex the user's reply must match
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub matches: Option<String>,
+
+    /// Context variable that must equal `equals`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub variable: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub equals: Option<String>,
+}
+
+/// Format of a definition file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DefinitionFormat {
+    Yaml,
+    Json,
+    Toml,
+}
+
+impl DefinitionFormat {
+    /// Detect the format from a `.yaml`, `.yml`, `.json` or `.toml` extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "yaml" | "yml" => Some(DefinitionFormat::Yaml),
+            "json" => Some(DefinitionFormat::Json),
+            "toml" => Some(DefinitionFormat::Toml),
+            _ => None,
+        }
+    }
+}
+
+/// A problem found in an agent definition
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DefinitionIssue {
+    /// File the definition was loaded from
+    pub file: Option<PathBuf>,
+    /// 1-based line and column, for syntax errors
+    pub position: Option<(usize, usize)>,
+    /// Path of the offending value, e.g. `guidelines[1].tools[0]`
+    pub path: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for DefinitionIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut location = Vec::new();
+        if let Some(file) = &self.file {
+            location.push(file.display().to_string());
+        }
+        if let Some((line, column)) = self.position {
+            location.push(format!("{}:{}", line, column));
+        }
+        if let Some(path) = &self.path {
+            location.push(path.clone());
+        }
+
+        if location.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{}: {}", location.join(":"), self.message)
+        }
+    }
+}
+
+/// Collects issues located in one definition
+struct Issues<'a> {
+    file: Option<&'a Path>,
+    issues: Vec<DefinitionIssue>,
+}
+
+impl<'a> Issues<'a> {
+    fn new(file: Option<&'a Path>) -> Self {
+        Self {
+            file,
+            issues: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, path: impl Into<String>, message: impl Into<String>) {
+        self.issues.push(DefinitionIssue {
+            file: self.file.map(Path::to_path_buf),
+            position: None,
+            path: Some(path.into()),
+            message: message.into(),
+        });
+    }
+
+    fn check_regex(&mut self, path: impl Into<String>, pattern: &str) {
+        if let Err(e) = Regex::new(pattern) {
+            self.push(path, format!("Invalid regex '{}': {}", pattern, e));
+        }
+    }
+
+    fn check_captures(&mut self, path: &str, captures: &[VariableCapture]) {
+        for (i, capture) in captures.iter().enumerate() {
+            if let CaptureSource::Regex { pattern } = &capture.source {
+                self.check_regex(format!("{}[{}].source.pattern", path, i), pattern);
+            }
+        }
+    }
+
+    fn into_result(self) -> Result<()> {
+        if self.issues.is_empty() {
+            Ok(())
+        } else {
+            Err(AgentError::InvalidDefinition(self.issues))
+        }
+    }
+}
+
+impl AgentDefinition {
+    /// Parse a definition from a string
+    pub fn parse(content: &str, format: DefinitionFormat) -> Result<Self> {
+        Self::parse_from(content, format, None)
+    }
+
+    /// Load a definition, detecting the format from the file extension
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let format = DefinitionFormat::from_path(path).ok_or_else(|| {
+            AgentError::Configuration(format!(
+                "Unsupported definition file '{}': expected a .yaml, .yml, .json or .toml extension",
+                path.display()
+            ))
+        })?;
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            AgentError::Configuration(format!(
+                "Failed to read definition file '{}': {}",
+                path.display(),
+                e
+            ))
+        })?;
+
+        Self::parse_from(&content, format, Some(path))
+    }
+
+    fn parse_from(content: &str, format: DefinitionFormat, file: Option<&Path>) -> Result<Self> {
+        let parsed = match format {
+            DefinitionFormat::Yaml => serde_yaml::from_str::<Self>(content).map_err(|e| {
+                let position = e.location().map(|l| (l.line(), l.column()));
+                (position, e.to_string())
+            }),
+            DefinitionFormat::Json => serde_json::from_str::<Self>(content).map_err(|e| {
+                let position = (e.line() > 0).then(|| (e.line(), e.column()));
+                (position, e.to_string())
+            }),
+            DefinitionFormat::Toml => toml::from_str::<Self>(content).map_err(|e| {
+                let position = e.span().map(|span| line_column(content, span.start));
+                (position, e.message().to_string())
+            }),
+        };
+
+        match parsed {
+            Ok(mut definition) => {
+                definition.source = file.map(Path::to_path_buf);
+                Ok(definition)
+            }
+            Err((position, message)) => Err(AgentError::InvalidDefinition(vec![DefinitionIssue {
+                file: file.map(Path::to_path_buf),
+                position,
+                path: None,
+                message: strip_position(&message).to_string(),
+            }])),
+        }
+    }
+
+    /// File the definition was loaded from
+    pub fn source(&self) -> Option<&Path> {
+        self.source.as_deref()
+    }
+
+    /// Names of the tools referenced by guidelines
+    pub fn tool_names(&self) -> impl Iterator<Item = &str> {
+        self.guidelines
+            .iter()
+            .flat_map(|g| g.tools.iter().map(String::as_str))
+    }
+
+    /// Check the whole definition, resolving tool references against `tool_names`
+    ///
+    /// Returns [`AgentError::InvalidDefinition`] listing every issue found.
+    pub fn validate(&self, tool_names: &[&str]) -> Result<()> {
+        let known: HashSet<&str> = tool_names.iter().copied().collect();
+        let mut issues = self.structure_issues();
+        for (i, guideline) in self.guidelines.iter().enumerate() {
+            for (j, tool) in guideline.tools.iter().enumerate() {
+                if !known.contains(tool.as_str()) {
+                    issues.push(
+                        format!("guidelines[{}].tools[{}]", i, j),
+                        format!("Unknown tool '{}'", tool),
+                    );
+                }
+            }
+        }
+        issues.into_result()
+    }
+
+    /// Check everything except tool references
+    pub(crate) fn validate_structure(&self) -> Result<()> {
+        self.structure_issues().into_result()
+    }
+
+    fn structure_issues(&self) -> Issues<'_> {
+        let mut issues = Issues::new(self.source());
+
+        if self.name.trim().is_empty() {
+            issues.push("name", "Agent name must not be empty");
+        }
+
+        if let Some(provider) = &self.provider {
+            if provider.model.trim().is_empty() {
+                issues.push("provider.model", "Model must not be empty");
+            }
+            if let Some(temperature) = provider.temperature {
+                if !(0.0..=2.0).contains(&temperature) {
+                    issues.push(
+                        "provider.temperature",
+                        format!(
+                            "Temperature must be between 0.0 and 2.0, got {}",
+                            temperature
+                        ),
+                    );
+                }
+            }
+        }
+
+        for (i, guideline) in self.guidelines.iter().enumerate() {
+            let path = format!("guidelines[{}]", i);
+            match (&guideline.contains, &guideline.matches) {
+                (Some(_), Some(_)) => {
+                    issues.push(path.clone(), "Set only one of 'contains' and 'matches'")
+                }
+                (None, None) => issues.push(path.clone(), "Set one of 'contains' and 'matches'"),
+                (Some(text), None) if text.trim().is_empty() => {
+                    issues.push(format!("{}.contains", path), "Text must not be empty")
+                }
+                (None, Some(pattern)) => issues.check_regex(format!("{}.matches", path), pattern),
+                _ => {}
+            }
+            if guideline.response.trim().is_empty() {
+                issues.push(format!("{}.response", path), "Response must not be empty");
+            }
+            if !guideline.parameters.is_empty() && guideline.matches.is_none() {
+                issues.push(
+                    format!("{}.parameters", path),
+                    "Parameters are extracted from 'matches' capture groups",
+                );
+            }
+            if let Some(schema) = &guideline.output_schema {
+                if let Err(e) = jsonschema::validator_for(schema) {
+                    issues.push(
+                        format!("{}.output_schema", path),
+                        format!("Invalid JSON Schema: {}", e),
+                    );
+                }
+            }
+            issues.check_captures(&format!("{}.captures", path), &guideline.captures);
+        }
+
+        let mut journey_names = HashSet::new();
+        for (i, journey) in self.journeys.iter().enumerate() {
+            let path = format!("journeys[{}]", i);
+            if journey.name.trim().is_empty() {
+                issues.push(format!("{}.name", path), "Journey name must not be empty");
+            } else if !journey_names.insert(journey.name.as_str()) {
+                issues.push(
+                    format!("{}.name", path),
+                    format!("Duplicate journey name '{}'", journey.name),
+                );
+            }
+            if journey.steps.is_empty() {
+                issues.push(
+                    format!("{}.steps", path),
+                    "Journey must have at least one step",
+                );
+            }
+
+            let mut keys = HashSet::new();
+            for (j, step) in journey.steps.iter().enumerate() {
+                if !keys.insert(step.key.as_str()) {
+                    issues.push(
+                        format!("{}.steps[{}].key", path, j),
+                        format!("Duplicate step key '{}'", step.key),
+                    );
+                }
+            }
+
+            if let Some(start) = &journey.start {
+                if !keys.contains(start.as_str()) {
+                    issues.push(
+                        format!("{}.start", path),
+                        format!("Unknown step '{}'", start),
+                    );
+                }
+            }
+
+            for (j, step) in journey.steps.iter().enumerate() {
+                let step_path = format!("{}.steps[{}]", path, j);
+                if let Some(pattern) = &step.expected_response {
+                    issues.check_regex(format!("{}.expected_response", step_path), pattern);
+                }
+                issues.check_captures(&format!("{}.captures", step_path), &step.captures);
+
+                for (k, transition) in step.transitions.iter().enumerate() {
+                    let transition_path = format!("{}.transitions[{}]", step_path, k);
+                    if !keys.contains(transition.to.as_str()) {
+                        issues.push(
+                            format!("{}.to", transition_path),
+                            format!("Unknown step '{}'", transition.to),
+                        );
+                    }
+                    if let Some(pattern) = &transition.matches {
+                        issues.check_regex(format!("{}.matches", transition_path), pattern);
+                        if transition.variable.is_some() {
+                            issues.push(
+                                transition_path.clone(),
+                                "Set only one of 'matches' and 'variable'",
+                            );
+                        }
+                    }
+                    if transition.variable.is_some() != transition.equals.is_some() {
+                        issues.push(
+                            transition_path,
+                            "'variable' and 'equals' must be set together",
+                        );
+                    }
+                }
+            }
+        }
+
+        issues
+    }
+
+    /// Create the configured provider, reading its API key from the environment
+    pub(crate) fn build_provider(&self) -> Result<Option<Box<dyn LLMProvider>>> {
+        let Some(provider) = &self.provider else {
+            return Ok(None);
+        };
+
+        let key_env = provider
+            .api_key_env
+            .as_deref()
+            .unwrap_or(provider.kind.default_api_key_env());
+        let api_key = std::env::var(key_env).map_err(|_| {
+            AgentError::Configuration(format!("{} environment variable not set", key_env))
+        })?;
+
+        let provider: Box<dyn LLMProvider> = match provider.kind {
+            ProviderKind::OpenAI => {
+                let mut openai = OpenAIProvider::new(api_key, &provider.model);
+                if let Some(temperature) = provider.temperature {
+                    openai = openai.with_temperature(temperature);
+                }
+                if let Some(max_tokens) = provider.max_tokens {
+                    openai = openai.with_max_tokens(max_tokens);
+                }
+                Box::new(openai)
+            }
+            ProviderKind::Anthropic => {
+                let mut anthropic = AnthropicProvider::new(api_key, &provider.model);
+                if let Some(temperature) = provider.temperature {
+                    anthropic = anthropic.with_temperature(temperature);
+                }
+                if let Some(max_tokens) = provider.max_tokens {
+                    anthropic = anthropic.with_max_tokens(max_tokens);
+                }
+                Box::new(anthropic)
+            }
+        };
+
+        Ok(Some(provider))
+    }
+
+    /// Build the guidelines, resolving tool names with `tool_ids`
+    pub(crate) fn build_guidelines(&self, tool_ids: &HashMap<String, ToolId>) -> Vec<Guideline> {
+        self.guidelines
+            .iter()
+            .map(|definition| {
+                let condition = match (&definition.contains, &definition.matches) {
+                    (_, Some(pattern)) => GuidelineCondition::Regex(pattern.clone()),
+                    (Some(text), None) => GuidelineCondition::Literal(text.clone()),
+                    (None, None) => GuidelineCondition::Literal(String::new()),
+                };
+
+                let mut action = if definition.use_llm {
+                    GuidelineAction::llm_with_template(&definition.response)
+                } else {
+                    GuidelineAction::template(&definition.response)
+                }
+                .with_parameters(definition.parameters.clone())
+                .with_must_mention(definition.must_mention.clone());
+                action.output_schema = definition.output_schema.clone();
+                action.handoff = definition.handoff.clone();
+                action.escalate = definition.escalate;
+
+                let mut guideline = Guideline::new(condition, action, definition.priority)
+                    .with_captures(definition.captures.clone());
+                guideline.tools = definition
+                    .tools
+                    .iter()
+                    .filter_map(|name| tool_ids.get(name).copied())
+                    .collect();
+                guideline
+            })
+            .collect()
+    }
+
+    /// Build the journeys, assigning step IDs to the step keys
+    pub(crate) fn build_journeys(&self) -> Vec<Journey> {
+        self.journeys
+            .iter()
+            .map(|definition| {
+                let step_ids: HashMap<&str, StepId> = definition
+                    .steps
+                    .iter()
+                    .map(|step| (step.key.as_str(), StepId::new()))
+                    .collect();
+
+                let steps: Vec<JourneyStep> = definition
+                    .steps
+                    .iter()
+                    .map(|step| JourneyStep {
+                        id: step_ids[step.key.as_str()],
+                        name: step.name.clone().unwrap_or_else(|| step.key.clone()),
+                        prompt: step.prompt.clone(),
+                        expected_response: step.expected_response.clone(),
+                        transitions: step
+                            .transitions
+                            .iter()
+                            .filter_map(|transition| {
+                                Some(Transition {
+                                    condition: transition_condition(transition),
+                                    next_step: *step_ids.get(transition.to.as_str())?,
+                                })
+                            })
+                            .collect(),
+                        actions: step.actions.clone(),
+                        captures: step.captures.clone(),
+                    })
+                    .collect();
+
+                let initial_step = definition
+                    .start
+                    .as_deref()
+                    .and_then(|key| step_ids.get(key).copied())
+                    .or_else(|| steps.first().map(|step| step.id))
+                    .unwrap_or_default();
+
+                Journey {
+                    id: JourneyId::new(),
+                    name: definition.name.clone(),
+                    description: definition.description.clone(),
+                    steps,
+                    initial_step,
+                    current_step: None,
+                    created_at: Utc::now(),
+                    enabled: true,
+                }
+            })
+            .collect()
+    }
+
+    /// Error for an issue found while applying the definition
+    pub(crate) fn invalid(
+        &self,
+        path: impl Into<String>,
+        message: impl Into<String>,
+    ) -> AgentError {
+        let mut issues = Issues::new(self.source());
+        issues.push(path, message);
+        AgentError::InvalidDefinition(issues.issues)
+    }
+}
+
+fn transition_condition(transition: &TransitionDefinition) -> TransitionCondition {
+    match (
+        &transition.matches,
+        &transition.variable,
+        &transition.equals,
+    ) {
+        (Some(pattern), _, _) => TransitionCondition::Match(pattern.clone()),
+        (None, Some(key), Some(value)) => TransitionCondition::ContextVariable {
+            key: key.clone(),
+            value: value.clone(),
+        },
+        _ => TransitionCondition::Always,
+    }
+}
+
+/// 1-based line and column of a byte offset
+fn line_column(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset.min(content.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.chars().rev().take_while(|&c| c != '\n').count() + 1;
+    (line, column)
+}
+
+/// Drop the " at line X column Y" suffix that parsers append to messages
+fn strip_position(message: &str) -> &str {
+    message
+        .rfind(" at line ")
+        .map_or(message, |index| &message[..index])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const YAML: &str = r#"
+name: Support Bot
+description: Answers billing questions
+config:
+  max_context_messages: 20
+guidelines:
+  - contains: refund
+    response: Refunds take 5 business days.
+    priority: 10
+  - matches: "order #(\\d+)"
+    response: Summarize the status of order {order_id}.
+    use_llm: true
+    parameters: [order_id]
+    tools: [lookup_order]
+journeys:
+  - name: Onboarding
+    start: ask_plan
+    steps:
+      - key: ask_plan
+        prompt: Which plan do you want?
+        transitions:
+          - to: pro
+            matches: "(?i)pro"
+          - to: done
+      - key: pro
+        prompt: Pro it is.
+        transitions:
+          - to: done
+      - key: done
+        prompt: All set!
+"#;
+
+    #[test]
+    fn test_parse_yaml_json_and_toml() {
+        let yaml = AgentDefinition::parse(YAML, DefinitionFormat::Yaml).unwrap();
+        assert_eq!(yaml.name, "Support Bot");
+        assert_eq!(yaml.config.max_context_messages, 20);
+        assert_eq!(yaml.guidelines.len(), 2);
+        assert_eq!(yaml.journeys[0].steps.len(), 3);
+        yaml.validate(&["lookup_order"]).unwrap();
+
+        let json = serde_json::to_string(&yaml).unwrap();
+        let from_json = AgentDefinition::parse(&json, DefinitionFormat::Json).unwrap();
+        assert_eq!(from_json.guidelines[1].tools, vec!["lookup_order"]);
+
+        let toml = r#"
+name = "Support Bot"
+
+[[guidelines]]
+contains = "refund"
+response = "Refunds take 5 business days."
+priority = 10
+"#;
+        let from_toml = AgentDefinition::parse(toml, DefinitionFormat::Toml).unwrap();
+        assert_eq!(from_toml.guidelines[0].priority, 10);
+        assert_eq!(from_toml.config, AgentConfig::default());
+    }
+
+    #[test]
+    fn test_build_resolves_tools_and_step_keys() {
+        let definition = AgentDefinition::parse(YAML, DefinitionFormat::Yaml).unwrap();
+        let tool_id = ToolId::new();
+        let tool_ids = HashMap::from([("lookup_order".to_string(), tool_id)]);
+
+        let guidelines = definition.build_guidelines(&tool_ids);
+        assert!(matches!(
+            guidelines[0].condition,
+            GuidelineCondition::Literal(_)
+        ));
+        assert!(guidelines[1].action.requires_llm);
+        assert_eq!(guidelines[1].tools, vec![tool_id]);
+
+        let journey = &definition.build_journeys()[0];
+        let ask_plan = &journey.steps[0];
+        assert_eq!(journey.initial_step, ask_plan.id);
+        assert_eq!(ask_plan.name, "ask_plan");
+        assert_eq!(ask_plan.transitions[0].next_step, journey.steps[1].id);
+        assert!(matches!(
+            ask_plan.transitions[1].condition,
+            TransitionCondition::Always
+        ));
+    }
+
+    #[test]
+    fn test_every_issue_is_reported_with_its_location() {
+        let yaml = r#"
+name: Broken
+guidelines:
+  - contains: refund
+    matches: refund
+    response: Both conditions
+  - matches: "("
+    response: Bad regex
+    tools: [missing_tool]
+journeys:
+  - name: Flow
+    start: nowhere
+    steps:
+      - key: a
+        prompt: A
+        transitions:
+          - to: b
+      - key: a
+        prompt: Duplicate
+"#;
+        let definition = AgentDefinition::parse(yaml, DefinitionFormat::Yaml).unwrap();
+        let Err(AgentError::InvalidDefinition(issues)) = definition.validate(&[]) else {
+            panic!("expected an invalid definition");
+        };
+
+        let paths: Vec<_> = issues.iter().filter_map(|i| i.path.as_deref()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "guidelines[0]",
+                "guidelines[1].matches",
+                "journeys[0].steps[1].key",
+                "journeys[0].start",
+                "journeys[0].steps[0].transitions[0].to",
+                "guidelines[1].tools[0]",
+            ]
+        );
+        assert_eq!(
+            issues.last().unwrap().to_string(),
+            "guidelines[1].tools[0]: Unknown tool 'missing_tool'"
+        );
+    }
+
+    #[test]
+    fn test_syntax_errors_report_line_and_column() {
+        let yaml = "name: Bot\nguidelines:\n  - contian: refund\n    response: Hi\n";
+        let Err(AgentError::InvalidDefinition(issues)) =
+            AgentDefinition::parse(yaml, DefinitionFormat::Yaml)
+        else {
+            panic!("expected a syntax error");
+        };
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].position.map(|(line, _)| line), Some(3));
+        assert!(issues[0].message.contains("contian"));
+
+        let toml = "name = \"Bot\"\n\n[[guidelines]]\nresponse = 5\n";
+        let Err(AgentError::InvalidDefinition(issues)) =
+            AgentDefinition::parse(toml, DefinitionFormat::Toml)
+        else {
+            panic!("expected a syntax error");
+        };
+        assert_eq!(issues[0].position.map(|(line, _)| line), Some(4));
+    }
+}
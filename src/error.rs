@@ -2,6 +2,7 @@
 //!
 //! This module provides comprehensive error types using thiserror for all Talk operations.
 
+use crate::definition::DefinitionIssue;
 use crate::types::{GuidelineId, JourneyId, SessionId, StepId, ToolId};
 use thiserror::Error;
 
@@ -25,6 +26,22 @@ pub enum AgentError {
     #[error("Session already exists: {0}")]
     SessionAlreadyExists(SessionId),
 
+    /// Session passed its expiry time and no longer accepts messages
+    #[error("Session expired: {0}")]
+    SessionExpired(SessionId),
+
+    /// Session is paused and must be resumed before it accepts messages
+    #[error("Session is paused: {0}")]
+    SessionPaused(SessionId),
+
+    /// Session was completed and no longer accepts messages
+    #[error("Session is completed: {0}")]
+    SessionCompleted(SessionId),
+
+    /// Session was terminated and no longer accepts messages
+    #[error("Session is terminated: {0}")]
+    SessionTerminated(SessionId),
+
     /// Guideline matching failed
     #[error("Guideline matching failed: {0}")]
     GuidelineMatch(String),
@@ -57,6 +74,13 @@ pub enum AgentError {
     #[error("Configuration error: {0}")]
     Configuration(String),
 
+    /// Agent definition file failed to parse or validate
+    #[error(
+        "Invalid agent definition:{}",
+        .0.iter().map(|issue| format!("\n  {}", issue)).collect::<String>()
+    )]
+    InvalidDefinition(Vec<DefinitionIssue>),
+
     /// Invalid input
     #[error("Invalid input: {0}")]
     InvalidInput(String),
@@ -65,6 +89,10 @@ pub enum AgentError {
     #[error("Tool not found: {0}")]
     ToolNotFound(ToolId),
 
+    /// Tool is registered but disabled
+    #[error("Tool is disabled: {0}")]
+    ToolDisabled(String),
+
     /// Tool already registered with same name
     #[error("Tool already registered: {0}")]
     ToolAlreadyRegistered(String),
@@ -84,6 +112,10 @@ pub enum AgentError {
         timeout: std::time::Duration,
     },
 
+    /// Response template rendering failed
+    #[error("Template rendering failed: {0}")]
+    Template(String),
+
     /// Internal error (should not happen in normal operation)
     #[error("Internal error: {0}")]
     Internal(String),
@@ -117,6 +149,10 @@ pub enum StorageError {
     #[error("Resource already exists: {0}")]
     AlreadyExists(String),
 
+    /// The stored version differs from the version being updated
+    #[error("Version conflict: expected version {expected}, found {actual}")]
+    Conflict { expected: u64, actual: u64 },
+
     /// Storage backend not available
     #[error("Storage backend not available: {0}")]
     BackendUnavailable(String),
@@ -276,6 +312,9 @@ mod tests {
         let display = format!("{}", err);
         assert!(display.contains("Session not found"));
         assert!(display.contains(&session_id.to_string()));
+
+        let display = AgentError::SessionExpired(session_id).to_string();
+        assert_eq!(display, format!("Session expired: {}", session_id));
     }
 
     #[test]
@@ -0,0 +1,296 @@
+//! Fallback strategies
+//!
+//! When no guideline is selected for a message and no journey is driving the
+//! conversation, an [`Agent`](crate::Agent) answers with its
+//! [`FallbackStrategy`]. Sessions count their consecutive fallbacks, so an
+//! agent can switch to a different strategy, e.g. escalation, once the user
+//! has not been understood several times in a row.
+
+use crate::guideline::{Guideline, GuidelineAction, GuidelineCondition, GuidelineMatch};
+use crate::types::{GuidelineId, JourneyId};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const DEFAULT_INSTRUCTION: &str =
+    "I'm not sure how to help with that. Could you please rephrase your question?";
+
+/// How an agent responds when no guideline matches
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "strategy", rename_all = "snake_case")]
+pub enum FallbackStrategy {
+    /// Reply with this response template
+    Template { template: String },
+    /// Let the LLM answer freely, guided by this instruction
+    Llm { instruction: String },
+    /// Ask the user to clarify, suggesting up to `max_suggestions` of the
+    /// guidelines that came closest to matching
+    Clarify {
+        question: String,
+        max_suggestions: usize,
+    },
+    /// Reply with this message and hand the session to a human operator
+    Escalate { message: String },
+    /// Start this journey and reply with its first step
+    StartJourney { journey_id: JourneyId },
+}
+
+impl FallbackStrategy {
+    pub fn template(template: impl Into<String>) -> Self {
+        Self::Template {
+            template: template.into(),
+        }
+    }
+
+    pub fn llm(instruction: impl Into<String>) -> Self {
+        Self::Llm {
+            instruction: instruction.into(),
+        }
+    }
+
+    pub fn clarify(question: impl Into<String>, max_suggestions: usize) -> Self {
+        Self::Clarify {
+            question: question.into(),
+            max_suggestions,
+        }
+    }
+
+    pub fn escalate(message: impl Into<String>) -> Self {
+        Self::Escalate {
+            message: message.into(),
+        }
+    }
+
+    pub fn start_journey(journey_id: JourneyId) -> Self {
+        Self::StartJourney { journey_id }
+    }
+}
+
+impl Default for FallbackStrategy {
+    /// Ask the LLM to answer, prompting the user to rephrase
+    fn default() -> Self {
+        Self::llm(DEFAULT_INSTRUCTION)
+    }
+}
+
+/// The fallback strategies of an agent
+#[derive(Debug, Clone)]
+pub(crate) struct FallbackPolicy {
+    /// ID of the guideline built for fallback responses
+    pub(crate) id: GuidelineId,
+    strategy: FallbackStrategy,
+    /// Strategy used once a session has had this many fallbacks in a row
+    repeated: Option<(u32, FallbackStrategy)>,
+}
+
+impl FallbackPolicy {
+    pub(crate) fn new(
+        strategy: FallbackStrategy,
+        repeated: Option<(u32, FallbackStrategy)>,
+    ) -> Self {
+        Self {
+            id: GuidelineId::new(),
+            strategy,
+            repeated,
+        }
+    }
+
+    /// The strategy for a session that has had `consecutive` fallbacks in a row
+    pub(crate) fn strategy_for(&self, consecutive: u32) -> &FallbackStrategy {
+        match &self.repeated {
+            Some((after, strategy)) if consecutive >= *after => strategy,
+            _ => &self.strategy,
+        }
+    }
+
+    /// The guideline that answers with a strategy
+    ///
+    /// `StartJourney` has no guideline of its own and answers like the
+    /// default strategy, for when its journey cannot be started.
+    pub(crate) fn guideline(
+        &self,
+        strategy: &FallbackStrategy,
+        message: &str,
+        matches: &[GuidelineMatch],
+        guidelines: &[Guideline],
+    ) -> Guideline {
+        let (template, requires_llm, escalate) = match strategy {
+            FallbackStrategy::Template { template } => (template.clone(), false, false),
+            FallbackStrategy::Llm { instruction } => (instruction.clone(), true, false),
+            FallbackStrategy::Clarify {
+                question,
+                max_suggestions,
+            } => {
+                let mut template = question.clone();
+                for suggestion in near_misses(message, matches, guidelines, *max_suggestions) {
+                    // Suggestions are shown verbatim, so escape template braces
+                    template.push_str(&format!(
+                        "\n- {}",
+                        suggestion.replace('{', "{{").replace('}', "}}")
+                    ));
+                }
+                (template, false, false)
+            }
+            FallbackStrategy::Escalate { message } => (message.clone(), false, true),
+            FallbackStrategy::StartJourney { .. } => (DEFAULT_INSTRUCTION.to_string(), true, false),
+        };
+
+        Guideline {
+            id: self.id,
+            condition: GuidelineCondition::Literal("".to_string()),
+            action: GuidelineAction {
+                response_template: template,
+                requires_llm,
+                parameters: vec![],
+                must_mention: vec![],
+                output_schema: None,
+                handoff: None,
+                escalate,
+            },
+            priority: -1,
+            tools: vec![],
+            parameters: HashMap::new(),
+            captures: vec![],
+            created_at: Utc::now(),
+            enabled: true,
+        }
+    }
+}
+
+/// Words of a guideline condition, shown to users when suggesting it
+fn condition_words(condition: &GuidelineCondition) -> Vec<String> {
+    let text = match condition {
+        GuidelineCondition::Literal(text) | GuidelineCondition::Regex(text) => text,
+    };
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() >= 3)
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Whether two words are equal or share a stem of at least 4 characters
+fn words_match(a: &str, b: &str) -> bool {
+    a == b || a.chars().zip(b.chars()).take_while(|(x, y)| x == y).count() >= 4
+}
+
+/// Labels of the guidelines that came closest to matching `message`, best first
+///
+/// Guidelines that matched but were not selected come first, then those whose
+/// condition shares the most words with the message.
+pub(crate) fn near_misses(
+    message: &str,
+    matches: &[GuidelineMatch],
+    guidelines: &[Guideline],
+    limit: usize,
+) -> Vec<String> {
+    let message_words: Vec<String> = message
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+
+    let mut scored: Vec<(f32, i32, String)> = guidelines
+        .iter()
+        .filter(|g| g.enabled)
+        .filter_map(|guideline| {
+            let words = condition_words(&guideline.condition);
+            if words.is_empty() {
+                return None;
+            }
+            let score = match matches.iter().find(|m| m.guideline_id == guideline.id) {
+                // Matched guidelines rank above every partial overlap
+                Some(m) => 1.0 + m.relevance_score,
+                None => {
+                    let shared = words
+                        .iter()
+                        .filter(|word| message_words.iter().any(|m| words_match(word, m)))
+                        .count();
+                    shared as f32 / words.len() as f32
+                }
+            };
+            (score > 0.0).then(|| (score, guideline.priority, words.join(" ")))
+        })
+        .collect();
+
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0).then(b.1.cmp(&a.1)));
+    let mut labels: Vec<String> = Vec::new();
+    for (_, _, label) in scored {
+        if labels.len() == limit {
+            break;
+        }
+        if !labels.contains(&label) {
+            labels.push(label);
+        }
+    }
+    labels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn literal(text: &str, priority: i32) -> Guideline {
+        Guideline::new(
+            GuidelineCondition::Literal(text.to_string()),
+            GuidelineAction::template(text),
+            priority,
+        )
+    }
+
+    #[test]
+    fn test_near_misses_rank_by_shared_words() {
+        let guidelines = vec![
+            literal("refund policy", 5),
+            literal("shipping times", 5),
+            literal("cancel order", 5),
+            Guideline::new(
+                GuidelineCondition::Regex(r"(?i)track(ing)?\s+order".to_string()),
+                GuidelineAction::template("Tracking"),
+                1,
+            ),
+        ];
+
+        let suggestions = near_misses("Where are my refunds and orders?", &[], &guidelines, 2);
+        // "refund" shares a stem with "refunds", "order" with "orders"
+        assert_eq!(suggestions, vec!["refund policy", "cancel order"]);
+
+        let suggestions = near_misses("Where are my refunds and orders?", &[], &guidelines, 5);
+        assert_eq!(suggestions.len(), 3);
+        assert_eq!(suggestions[2], "track ing order");
+
+        assert!(near_misses("hello", &[], &guidelines, 3).is_empty());
+    }
+
+    #[test]
+    fn test_repeated_strategy_applies_after_threshold() {
+        let policy = FallbackPolicy::new(
+            FallbackStrategy::template("Sorry?"),
+            Some((2, FallbackStrategy::escalate("Connecting you to a person."))),
+        );
+
+        assert_eq!(
+            policy.strategy_for(0),
+            &FallbackStrategy::template("Sorry?")
+        );
+        assert_eq!(
+            policy.strategy_for(1),
+            &FallbackStrategy::template("Sorry?")
+        );
+        let guideline = policy.guideline(policy.strategy_for(2), "hi", &[], &[]);
+        assert_eq!(guideline.id, policy.id);
+        assert!(guideline.action.escalate);
+        assert!(!guideline.action.requires_llm);
+    }
+
+    #[test]
+    fn test_strategy_serialization() {
+        let strategy = FallbackStrategy::clarify("Did you mean:", 3);
+        let json = serde_json::to_value(&strategy).unwrap();
+        assert_eq!(json["strategy"], "clarify");
+        assert_eq!(json["max_suggestions"], 3);
+        assert_eq!(
+            serde_json::from_value::<FallbackStrategy>(json).unwrap(),
+            strategy
+        );
+    }
+}
@@ -0,0 +1,339 @@
+//! Output guardrails
+//!
+//! Guardrails check LLM-generated responses before `Agent::process_message`
+//! returns them. The agent-wide rules live in [`Guardrails`]; the matched
+//! guideline can add its own through `GuidelineAction::must_mention` and
+//! `GuidelineAction::output_schema`.
+//!
+//! When a response breaks a rule, the agent asks the LLM to rewrite it with the
+//! violations explained, up to [`Guardrails::max_retries`] times, and then falls
+//! back to [`Guardrails::fallback_response`]. Template and journey prompt
+//! responses are written by the agent author and are not checked.
+
+use crate::error::{AgentError, Result};
+use crate::guideline::Guideline;
+use async_trait::async_trait;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+/// A guardrail rule broken by a response
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Violation {
+    /// Name of the rule, e.g. `max_length` or a custom validator's name
+    pub rule: String,
+    /// What is wrong with the response
+    pub reason: String,
+}
+
+impl Violation {
+    pub fn new(rule: impl Into<String>, reason: impl Into<String>) -> Self {
+        Self {
+            rule: rule.into(),
+            reason: reason.into(),
+        }
+    }
+}
+
+/// Custom check run against every LLM-generated response
+#[async_trait]
+pub trait ResponseValidator: Send + Sync {
+    /// Name reported as the rule of violations
+    fn name(&self) -> &str;
+
+    /// Check a response produced for the applied guidelines
+    ///
+    /// Returns the reason when the response is not acceptable.
+    async fn validate(
+        &self,
+        response: &str,
+        guidelines: &[Guideline],
+    ) -> std::result::Result<(), String>;
+}
+
+/// Agent-wide rules for LLM-generated responses
+pub struct Guardrails {
+    forbidden_phrases: Vec<String>,
+    forbidden_patterns: Vec<Regex>,
+    max_length: Option<usize>,
+    validators: Vec<Box<dyn ResponseValidator>>,
+    max_retries: usize,
+    fallback_response: String,
+}
+
+impl Guardrails {
+    pub fn new() -> Self {
+        Self {
+            forbidden_phrases: Vec::new(),
+            forbidden_patterns: Vec::new(),
+            max_length: None,
+            validators: Vec::new(),
+            max_retries: 2,
+            fallback_response: "I'm sorry, I can't help with that right now.".to_string(),
+        }
+    }
+
+    /// Reject responses containing this phrase (case-insensitive)
+    pub fn forbid_phrase(mut self, phrase: impl Into<String>) -> Self {
+        self.forbidden_phrases.push(phrase.into());
+        self
+    }
+
+    /// Reject responses matching this regex
+    pub fn forbid_pattern(mut self, pattern: &str) -> Result<Self> {
+        let regex = Regex::new(pattern).map_err(|e| {
+            AgentError::Configuration(format!("Invalid guardrail pattern '{}': {}", pattern, e))
+        })?;
+        self.forbidden_patterns.push(regex);
+        Ok(self)
+    }
+
+    /// Reject responses longer than this many characters
+    pub fn max_length(mut self, max_length: usize) -> Self {
+        self.max_length = Some(max_length);
+        self
+    }
+
+    /// Run a custom validator against every response
+    pub fn validator(mut self, validator: Box<dyn ResponseValidator>) -> Self {
+        self.validators.push(validator);
+        self
+    }
+
+    /// Number of times the LLM is asked to fix a response (default 2)
+    pub fn max_retries(mut self, max_retries: usize) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Response used when every retry still breaks a rule
+    pub fn fallback_response(mut self, response: impl Into<String>) -> Self {
+        self.fallback_response = response.into();
+        self
+    }
+
+    pub fn get_max_retries(&self) -> usize {
+        self.max_retries
+    }
+
+    pub fn get_fallback_response(&self) -> &str {
+        &self.fallback_response
+    }
+
+    /// Whether any rule applies to responses for these guidelines
+    pub fn applies_to(&self, guidelines: &[Guideline]) -> bool {
+        !self.forbidden_phrases.is_empty()
+            || !self.forbidden_patterns.is_empty()
+            || self.max_length.is_some()
+            || !self.validators.is_empty()
+            || guidelines
+                .iter()
+                .any(|g| !g.action.must_mention.is_empty() || g.action.output_schema.is_some())
+    }
+
+    /// Check a response against every rule, returning all violations
+    pub async fn check(&self, response: &str, guidelines: &[Guideline]) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        let lowercase = response.to_lowercase();
+
+        for phrase in &self.forbidden_phrases {
+            if lowercase.contains(&phrase.to_lowercase()) {
+                violations.push(Violation::new(
+                    "forbidden_phrase",
+                    format!("Response contains the forbidden phrase '{}'", phrase),
+                ));
+            }
+        }
+
+        for pattern in &self.forbidden_patterns {
+            if pattern.is_match(response) {
+                violations.push(Violation::new(
+                    "forbidden_pattern",
+                    format!("Response matches the forbidden pattern '{}'", pattern),
+                ));
+            }
+        }
+
+        if let Some(max_length) = self.max_length {
+            let length = response.chars().count();
+            if length > max_length {
+                violations.push(Violation::new(
+                    "max_length",
+                    format!(
+                        "Response is {} characters long; the maximum is {}",
+                        length, max_length
+                    ),
+                ));
+            }
+        }
+
+        for guideline in guidelines {
+            for phrase in &guideline.action.must_mention {
+                if !lowercase.contains(&phrase.to_lowercase()) {
+                    violations.push(Violation::new(
+                        "must_mention",
+                        format!("Response must mention '{}'", phrase),
+                    ));
+                }
+            }
+
+            if let Some(schema) = &guideline.action.output_schema {
+                violations.extend(check_schema(response, schema));
+            }
+        }
+
+        for validator in &self.validators {
+            if let Err(reason) = validator.validate(response, guidelines).await {
+                violations.push(Violation::new(validator.name(), reason));
+            }
+        }
+
+        violations
+    }
+}
+
+impl Default for Guardrails {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Check that a response is JSON matching `schema`
+fn check_schema(response: &str, schema: &serde_json::Value) -> Vec<Violation> {
+    let validator = match jsonschema::validator_for(schema) {
+        Ok(validator) => validator,
+        Err(e) => {
+            warn!(error = %e, "Invalid output schema, skipping check");
+            return Vec::new();
+        }
+    };
+
+    let instance: serde_json::Value = match serde_json::from_str(response.trim()) {
+        Ok(instance) => instance,
+        Err(e) => {
+            return vec![Violation::new(
+                "output_schema",
+                format!("Response is not valid JSON: {}", e),
+            )];
+        }
+    };
+
+    validator
+        .iter_errors(&instance)
+        .map(|error| {
+            Violation::new(
+                "output_schema",
+                format!(
+                    "Response does not match the output schema at '{}': {}",
+                    error.instance_path, error
+                ),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::guideline::{GuidelineAction, GuidelineCondition};
+    use serde_json::json;
+
+    fn create_guideline(action: GuidelineAction) -> Guideline {
+        Guideline::new(GuidelineCondition::Literal("test".to_string()), action, 10)
+    }
+
+    #[tokio::test]
+    async fn test_forbidden_phrases_patterns_and_length() {
+        let guardrails = Guardrails::new()
+            .forbid_phrase("guaranteed")
+            .forbid_pattern(r"\b\d{16}\b")
+            .unwrap()
+            .max_length(40);
+
+        let violations = guardrails
+            .check("Returns are GUARANTEED for card 4111111111111111", &[])
+            .await;
+        let rules: Vec<_> = violations.iter().map(|v| v.rule.as_str()).collect();
+        assert_eq!(
+            rules,
+            vec!["forbidden_phrase", "forbidden_pattern", "max_length"]
+        );
+
+        assert!(guardrails
+            .check("Returns take 5 days.", &[])
+            .await
+            .is_empty());
+    }
+
+    #[test]
+    fn test_invalid_pattern_is_configuration_error() {
+        assert!(matches!(
+            Guardrails::new().forbid_pattern("("),
+            Err(AgentError::Configuration(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_guideline_must_mention_and_schema() {
+        let guidelines = vec![
+            create_guideline(
+                GuidelineAction::llm_with_template("Explain refunds")
+                    .with_must_mention(vec!["5 days".to_string()]),
+            ),
+            create_guideline(
+                GuidelineAction::llm_with_template("Return JSON").with_output_schema(json!({
+                    "type": "object",
+                    "properties": {"days": {"type": "integer"}},
+                    "required": ["days"]
+                })),
+            ),
+        ];
+        let guardrails = Guardrails::new();
+        assert!(guardrails.applies_to(&guidelines));
+
+        let violations = guardrails.check("Refunds are quick", &guidelines).await;
+        assert_eq!(violations.len(), 2);
+        assert_eq!(violations[0].rule, "must_mention");
+        assert!(violations[1].reason.contains("not valid JSON"));
+
+        let violations = guardrails.check(r#"{"days": "5 days"}"#, &guidelines).await;
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].rule, "output_schema");
+
+        assert!(guardrails
+            .check(r#"{"days": 5, "note": "5 days"}"#, &guidelines)
+            .await
+            .is_empty());
+    }
+
+    struct NoUrls;
+
+    #[async_trait]
+    impl ResponseValidator for NoUrls {
+        fn name(&self) -> &str {
+            "no_urls"
+        }
+
+        async fn validate(
+            &self,
+            response: &str,
+            _guidelines: &[Guideline],
+        ) -> std::result::Result<(), String> {
+            if response.contains("http") {
+                Err("Response must not contain links".to_string())
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_validator() {
+        let guardrails = Guardrails::new().validator(Box::new(NoUrls));
+        let violations = guardrails.check("See https://example.com", &[]).await;
+        assert_eq!(
+            violations,
+            vec![Violation::new("no_urls", "Response must not contain links")]
+        );
+    }
+}
@@ -3,14 +3,16 @@
 // This module implements the guideline matching system that determines
 // which guideline should be activated based on user input.
 
-use crate::context::Context;
-use crate::error::Result;
+use crate::context::{Context, VariableCapture};
+use crate::error::{AgentError, Result};
+use crate::template::{self, MissingVariablePolicy, TemplateContext};
 use crate::types::{GuidelineId, ToolId};
 use aho_corasick::{AhoCorasick, AhoCorasickBuilder};
 use chrono::{DateTime, Utc};
 use regex::{Regex, RegexSet};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::ops::Range;
 use tracing::{debug, info, trace};
 
 /// Behavioral guideline defining when to activate and what to do
@@ -22,11 +24,44 @@ pub struct Guideline {
     pub priority: i32,
     pub tools: Vec<ToolId>,
     pub parameters: HashMap<String, ParameterDef>,
+    /// Context variables captured from messages that match this guideline
+    #[serde(default)]
+    pub captures: Vec<VariableCapture>,
     pub created_at: DateTime<Utc>,
+    /// Disabled guidelines are never matched
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl Guideline {
+    /// Create a new guideline with no tools or parameters
+    pub fn new(condition: GuidelineCondition, action: GuidelineAction, priority: i32) -> Self {
+        Self {
+            id: GuidelineId::new(),
+            condition,
+            action,
+            priority,
+            tools: Vec::new(),
+            parameters: HashMap::new(),
+            captures: Vec::new(),
+            created_at: Utc::now(),
+            enabled: true,
+        }
+    }
+
+    /// Capture context variables from messages that match this guideline
+    pub fn with_captures(mut self, captures: Vec<VariableCapture>) -> Self {
+        self.captures = captures;
+        self
+    }
 }
 
 /// Condition that triggers a guideline
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum GuidelineCondition {
     /// Exact text match (case-insensitive substring)
     Literal(String),
@@ -40,6 +75,88 @@ pub struct GuidelineAction {
     pub response_template: String,
     pub requires_llm: bool,
     pub parameters: Vec<String>,
+    /// Phrases an LLM-generated response must mention (case-insensitive)
+    #[serde(default)]
+    pub must_mention: Vec<String>,
+    /// JSON Schema an LLM-generated response must satisfy
+    #[serde(default)]
+    pub output_schema: Option<serde_json::Value>,
+    /// Name of the agent an [`AgentRouter`](crate::AgentRouter) hands the
+    /// conversation off to after this response
+    #[serde(default)]
+    pub handoff: Option<String>,
+    /// Hand the session to a human operator after this response
+    #[serde(default)]
+    pub escalate: bool,
+}
+
+impl GuidelineAction {
+    /// Create an action that returns the template verbatim
+    pub fn template(response_template: impl Into<String>) -> Self {
+        Self {
+            response_template: response_template.into(),
+            requires_llm: false,
+            parameters: Vec::new(),
+            must_mention: Vec::new(),
+            output_schema: None,
+            handoff: None,
+            escalate: false,
+        }
+    }
+
+    /// Create an action that uses the template as LLM instructions
+    pub fn llm_with_template(response_template: impl Into<String>) -> Self {
+        Self {
+            response_template: response_template.into(),
+            requires_llm: true,
+            parameters: Vec::new(),
+            must_mention: Vec::new(),
+            output_schema: None,
+            handoff: None,
+            escalate: false,
+        }
+    }
+
+    /// Name the regex capture groups extracted as parameters, in order
+    pub fn with_parameters(mut self, parameters: Vec<String>) -> Self {
+        self.parameters = parameters;
+        self
+    }
+
+    /// Require LLM-generated responses to mention each of these phrases
+    pub fn with_must_mention(mut self, phrases: Vec<String>) -> Self {
+        self.must_mention = phrases;
+        self
+    }
+
+    /// Require LLM-generated responses to be JSON matching this schema
+    pub fn with_output_schema(mut self, schema: serde_json::Value) -> Self {
+        self.output_schema = Some(schema);
+        self
+    }
+
+    /// Hand the conversation off to another agent of the router after responding
+    pub fn with_handoff(mut self, agent: impl Into<String>) -> Self {
+        self.handoff = Some(agent.into());
+        self
+    }
+
+    /// Hand the session to a human operator after responding
+    pub fn with_escalation(mut self) -> Self {
+        self.escalate = true;
+        self
+    }
+
+    /// Render the response template, filling in placeholders from `context`
+    ///
+    /// See [`crate::template`] for the placeholder syntax.
+    pub fn render(
+        &self,
+        context: &TemplateContext,
+        missing: &MissingVariablePolicy,
+    ) -> Result<String> {
+        template::render(&self.response_template, context, missing)
+    }
 }
 
 /// Parameter definition for tools
@@ -60,6 +177,9 @@ pub struct GuidelineMatch {
     pub matched_condition: String,
     pub extracted_parameters: HashMap<String, serde_json::Value>,
     pub explanation: Option<String>,
+    /// Byte range of the message the condition matched, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub span: Option<Range<usize>>,
 }
 
 /// Trait for guideline matching
@@ -75,12 +195,55 @@ pub trait GuidelineMatcher: Send + Sync {
     /// Select the best matching guideline (by priority and relevance)
     async fn select_best_match(&self, matches: Vec<GuidelineMatch>) -> Option<GuidelineMatch>;
 
+    /// Select every match at or above both thresholds, best first
+    ///
+    /// Matches are ordered like [`GuidelineMatcher::select_best_match`]: by
+    /// priority, then by newest guideline.
+    async fn select_matches(
+        &self,
+        mut matches: Vec<GuidelineMatch>,
+        min_relevance: f32,
+        min_priority: i32,
+    ) -> Vec<GuidelineMatch> {
+        let guidelines = self.get_guidelines();
+        let rank = |m: &GuidelineMatch| {
+            guidelines
+                .iter()
+                .find(|g| g.id == m.guideline_id)
+                .map(|g| (g.priority, g.created_at))
+        };
+
+        matches.retain(|m| {
+            m.relevance_score >= min_relevance
+                && rank(m).is_some_and(|(priority, _)| priority >= min_priority)
+        });
+        matches.sort_by_key(|m| std::cmp::Reverse(rank(m)));
+
+        debug!(
+            selected_count = matches.len(),
+            min_relevance = min_relevance,
+            min_priority = min_priority,
+            "Selected matches above thresholds"
+        );
+
+        matches
+    }
+
     /// Add a guideline to the matcher
     async fn add_guideline(&mut self, guideline: Guideline) -> Result<GuidelineId>;
 
     /// Remove a guideline
     async fn remove_guideline(&mut self, id: &GuidelineId) -> Result<()>;
 
+    /// Replace the guideline with the same ID
+    ///
+    /// The default implementation removes the old guideline and adds the new
+    /// one; override it to keep the guideline's position.
+    async fn update_guideline(&mut self, guideline: Guideline) -> Result<()> {
+        self.remove_guideline(&guideline.id).await?;
+        self.add_guideline(guideline).await.map(|_| ())
+    }
+
     /// Get all guidelines
     fn get_guidelines(&self) -> &[Guideline];
 }
@@ -109,19 +272,26 @@ impl DefaultGuidelineMatcher {
         }
     }
 
+    fn enabled_guidelines(&self) -> impl Iterator<Item = (usize, &Guideline)> {
+        self.guidelines
+            .iter()
+            .enumerate()
+            .filter(|(_, guideline)| guideline.enabled)
+    }
+
     /// Rebuild pattern matchers after guidelines change
     fn rebuild_matchers(&mut self) {
-        // Build Aho-Corasick automaton for literal conditions
+        // Build Aho-Corasick automaton for literal conditions of enabled guidelines
         // Track which pattern maps to which guideline indices (handle duplicates)
         let mut literals: Vec<String> = Vec::new();
         let mut literal_to_guideline_map: HashMap<String, Vec<usize>> = HashMap::new();
 
-        for (guideline_idx, guideline) in self.guidelines.iter().enumerate() {
+        for (guideline_idx, guideline) in self.enabled_guidelines() {
             if let GuidelineCondition::Literal(s) = &guideline.condition {
                 let lowercase = s.to_lowercase();
                 literal_to_guideline_map
                     .entry(lowercase.clone())
-                    .or_insert_with(Vec::new)
+                    .or_default()
                     .push(guideline_idx);
             }
         }
@@ -150,11 +320,11 @@ impl DefaultGuidelineMatcher {
         let mut patterns: Vec<String> = Vec::new();
         let mut regex_to_guideline_map: HashMap<String, Vec<usize>> = HashMap::new();
 
-        for (guideline_idx, guideline) in self.guidelines.iter().enumerate() {
+        for (guideline_idx, guideline) in self.enabled_guidelines() {
             if let GuidelineCondition::Regex(r) = &guideline.condition {
                 regex_to_guideline_map
                     .entry(r.clone())
-                    .or_insert_with(Vec::new)
+                    .or_default()
                     .push(guideline_idx);
             }
         }
@@ -180,14 +350,17 @@ impl DefaultGuidelineMatcher {
         }
     }
 
-    /// Match literal conditions using Aho-Corasick
-    fn match_literal_conditions(&self, message: &str) -> Vec<(usize, &Guideline)> {
+    /// Match literal conditions using Aho-Corasick, with the span each one matched
+    fn match_literal_conditions(&self, message: &str) -> Vec<(Option<Range<usize>>, &Guideline)> {
         let mut matches = Vec::new();
 
         if let Some(ref ac) = self.aho_corasick {
             let lowercase_message = message.to_lowercase();
+            // Lowercasing some characters changes their length, shifting offsets
+            let offsets_match = lowercase_message.len() == message.len();
             for mat in ac.find_iter(&lowercase_message) {
                 let pattern_idx = mat.pattern().as_usize();
+                let span = offsets_match.then(|| mat.range());
 
                 // Get all guidelines that match this pattern
                 if let Some(guideline_indices) =
@@ -195,7 +368,7 @@ impl DefaultGuidelineMatcher {
                 {
                     for &guideline_idx in guideline_indices {
                         if let Some(guideline) = self.guidelines.get(guideline_idx) {
-                            matches.push((guideline_idx, guideline));
+                            matches.push((span.clone(), guideline));
                         }
                     }
                 }
@@ -205,18 +378,23 @@ impl DefaultGuidelineMatcher {
         matches
     }
 
-    /// Match regex conditions using RegexSet
-    fn match_regex_conditions(&self, message: &str) -> Vec<(usize, &Guideline)> {
+    /// Match regex conditions using RegexSet, with the span each one matched
+    fn match_regex_conditions(&self, message: &str) -> Vec<(Option<Range<usize>>, &Guideline)> {
         let mut matches = Vec::new();
 
         if let Some(ref regex_set) = self.regex_set {
             for pattern_idx in regex_set.matches(message).into_iter() {
+                let span = self
+                    .individual_regexes
+                    .get(pattern_idx)
+                    .and_then(|re| re.find(message))
+                    .map(|m| m.range());
                 // Get all guidelines that match this pattern
                 if let Some(guideline_indices) = self.regex_pattern_to_guidelines.get(&pattern_idx)
                 {
                     for &guideline_idx in guideline_indices {
                         if let Some(guideline) = self.guidelines.get(guideline_idx) {
-                            matches.push((guideline_idx, guideline));
+                            matches.push((span.clone(), guideline));
                         }
                     }
                 }
@@ -277,7 +455,7 @@ impl GuidelineMatcher for DefaultGuidelineMatcher {
             "Literal conditions matched"
         );
 
-        for (_idx, guideline) in literal_matches {
+        for (span, guideline) in literal_matches {
             trace!(
                 guideline_id = %guideline.id,
                 priority = guideline.priority,
@@ -290,6 +468,7 @@ impl GuidelineMatcher for DefaultGuidelineMatcher {
                 matched_condition: format!("{:?}", guideline.condition),
                 extracted_parameters: HashMap::new(),
                 explanation: Some("Exact literal match".to_string()),
+                span,
             });
         }
 
@@ -300,7 +479,7 @@ impl GuidelineMatcher for DefaultGuidelineMatcher {
             "Regex conditions matched"
         );
 
-        for (_idx, guideline) in regex_matches {
+        for (span, guideline) in regex_matches {
             let params = self.extract_parameters(message, guideline);
             trace!(
                 guideline_id = %guideline.id,
@@ -315,6 +494,7 @@ impl GuidelineMatcher for DefaultGuidelineMatcher {
                 matched_condition: format!("{:?}", guideline.condition),
                 extracted_parameters: params,
                 explanation: Some("Regex pattern match".to_string()),
+                span,
             });
         }
 
@@ -401,6 +581,17 @@ impl GuidelineMatcher for DefaultGuidelineMatcher {
         Ok(())
     }
 
+    async fn update_guideline(&mut self, guideline: Guideline) -> Result<()> {
+        let existing = self
+            .guidelines
+            .iter_mut()
+            .find(|g| g.id == guideline.id)
+            .ok_or(AgentError::GuidelineNotFound(guideline.id))?;
+        *existing = guideline;
+        self.rebuild_matchers();
+        Ok(())
+    }
+
     fn get_guidelines(&self) -> &[Guideline] {
         &self.guidelines
     }
@@ -421,11 +612,17 @@ mod tests {
                 response_template: "Pricing info".to_string(),
                 requires_llm: false,
                 parameters: vec![],
+                must_mention: vec![],
+                output_schema: None,
+                handoff: None,
+                escalate: false,
             },
             priority: 10,
             tools: vec![],
             parameters: HashMap::new(),
+            captures: vec![],
             created_at: Utc::now(),
+            enabled: true,
         };
 
         matcher.add_guideline(guideline).await.unwrap();
@@ -439,6 +636,7 @@ mod tests {
 
         assert_eq!(matches.len(), 1);
         assert_eq!(matches[0].relevance_score, 1.0);
+        assert_eq!(matches[0].span, Some(13..20));
     }
 
     #[tokio::test]
@@ -452,11 +650,17 @@ mod tests {
                 response_template: "Cancel info".to_string(),
                 requires_llm: false,
                 parameters: vec![],
+                must_mention: vec![],
+                output_schema: None,
+                handoff: None,
+                escalate: false,
             },
             priority: 10,
             tools: vec![],
             parameters: HashMap::new(),
+            captures: vec![],
             created_at: Utc::now(),
+            enabled: true,
         };
 
         matcher.add_guideline(guideline).await.unwrap();
@@ -470,6 +674,7 @@ mod tests {
 
         assert_eq!(matches.len(), 1);
         assert_eq!(matches[0].relevance_score, 0.9);
+        assert_eq!(matches[0].span, Some(10..32));
     }
 
     #[tokio::test]
@@ -484,11 +689,17 @@ mod tests {
                 response_template: "Low".to_string(),
                 requires_llm: false,
                 parameters: vec![],
+                must_mention: vec![],
+                output_schema: None,
+                handoff: None,
+                escalate: false,
             },
             priority: 5,
             tools: vec![],
             parameters: HashMap::new(),
+            captures: vec![],
             created_at: Utc::now(),
+            enabled: true,
         };
 
         let high_priority = Guideline {
@@ -498,11 +709,17 @@ mod tests {
                 response_template: "High".to_string(),
                 requires_llm: false,
                 parameters: vec![],
+                must_mention: vec![],
+                output_schema: None,
+                handoff: None,
+                escalate: false,
             },
             priority: 20,
             tools: vec![],
             parameters: HashMap::new(),
+            captures: vec![],
             created_at: Utc::now(),
+            enabled: true,
         };
 
         let low_id = low_priority.id;
@@ -534,4 +751,88 @@ mod tests {
             "Should select high priority guideline"
         );
     }
+
+    #[tokio::test]
+    async fn test_select_matches_applies_thresholds() {
+        let mut matcher = DefaultGuidelineMatcher::new();
+
+        let refund = Guideline::new(
+            GuidelineCondition::Literal("refund".to_string()),
+            GuidelineAction::template("Mention the refund policy"),
+            10,
+        );
+        let empathy = Guideline::new(
+            GuidelineCondition::Regex(r"angry|upset".to_string()),
+            GuidelineAction::template("Be empathetic"),
+            20,
+        );
+        let chatter = Guideline::new(
+            GuidelineCondition::Literal("refund".to_string()),
+            GuidelineAction::template("Small talk"),
+            1,
+        );
+        let (refund_id, empathy_id) = (refund.id, empathy.id);
+
+        matcher.add_guideline(refund).await.unwrap();
+        matcher.add_guideline(empathy).await.unwrap();
+        matcher.add_guideline(chatter).await.unwrap();
+
+        let matches = matcher
+            .match_guidelines("I am angry, I want a refund", &Context::new())
+            .await
+            .unwrap();
+        assert_eq!(matches.len(), 3);
+
+        let selected = matcher.select_matches(matches.clone(), 0.5, 5).await;
+        let ids: Vec<_> = selected.iter().map(|m| m.guideline_id).collect();
+        assert_eq!(ids, vec![empathy_id, refund_id]);
+
+        // Regex matches score below literal matches
+        let selected = matcher.select_matches(matches, 0.95, 5).await;
+        let ids: Vec<_> = selected.iter().map(|m| m.guideline_id).collect();
+        assert_eq!(ids, vec![refund_id]);
+    }
+
+    #[tokio::test]
+    async fn test_update_and_disable_guideline() {
+        let mut matcher = DefaultGuidelineMatcher::new();
+        let mut guideline = Guideline::new(
+            GuidelineCondition::Literal("refund".to_string()),
+            GuidelineAction::template("Refunds take 5 days"),
+            10,
+        );
+        matcher.add_guideline(guideline.clone()).await.unwrap();
+
+        guideline.condition = GuidelineCondition::Literal("return".to_string());
+        matcher.update_guideline(guideline.clone()).await.unwrap();
+        let matches = matcher
+            .match_guidelines("I want a refund", &Context::new())
+            .await
+            .unwrap();
+        assert!(matches.is_empty());
+        let matches = matcher
+            .match_guidelines("Can I return this?", &Context::new())
+            .await
+            .unwrap();
+        assert_eq!(matches.len(), 1);
+
+        guideline.enabled = false;
+        matcher.update_guideline(guideline).await.unwrap();
+        let matches = matcher
+            .match_guidelines("Can I return this?", &Context::new())
+            .await
+            .unwrap();
+        assert!(matches.is_empty());
+        assert_eq!(matcher.get_guidelines().len(), 1);
+
+        let unknown = Guideline::new(
+            GuidelineCondition::Literal("other".to_string()),
+            GuidelineAction::template("Other"),
+            1,
+        );
+        assert!(matches!(
+            matcher.update_guideline(unknown).await,
+            Err(AgentError::GuidelineNotFound(_))
+        ));
+    }
 }
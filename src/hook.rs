@@ -0,0 +1,111 @@
+//! Agent hooks
+//!
+//! Hooks are middleware that run at fixed points while an [`Agent`](crate::Agent)
+//! processes a message. Each hook point receives the data for that stage by
+//! mutable reference, so a hook can inspect it, change it, or stop processing:
+//!
+//! - `Ok(HookAction::Continue)` - keep going (with any changes the hook made)
+//! - `Ok(HookAction::Respond(reply))` - skip the remaining stages and answer with `reply`
+//! - `Err(..)` - abort processing; nothing is saved and the error is returned
+//!
+//! Hooks run in the order they were registered with
+//! [`AgentBuilder::hook`](crate::AgentBuilder::hook). When a hook stops
+//! processing, the hooks after it are not run for that point.
+
+use crate::agent::AgentResponse;
+use crate::context::Message;
+use crate::error::Result;
+use crate::guideline::GuidelineMatch;
+use crate::tool::ToolResult;
+use crate::types::{AgentId, SessionId, ToolId};
+use async_trait::async_trait;
+use std::collections::HashMap;
+
+/// What the agent should do after a hook runs
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum HookAction {
+    /// Continue processing the message
+    #[default]
+    Continue,
+    /// Stop processing and reply with this message
+    ///
+    /// The reply is saved to the session like any other response, after the
+    /// `before_save` hooks have run.
+    Respond(String),
+}
+
+/// Identifies the message being processed
+#[derive(Debug, Clone)]
+pub struct HookContext {
+    pub agent_id: AgentId,
+    pub session_id: SessionId,
+}
+
+/// Middleware that runs at fixed points in `Agent::process_message`
+///
+/// Every method has a default implementation that continues, so a hook only
+/// implements the points it cares about.
+#[async_trait]
+pub trait AgentHook: Send + Sync {
+    /// Before guideline matching; `message` is the user's input
+    ///
+    /// Changes to `message` are what gets matched and saved to the session.
+    async fn before_matching(
+        &self,
+        _context: &HookContext,
+        _message: &mut String,
+    ) -> Result<HookAction> {
+        Ok(HookAction::Continue)
+    }
+
+    /// After the guideline matches to apply have been selected
+    ///
+    /// Removing every match makes the agent use its fallback strategy.
+    async fn after_match_selected(
+        &self,
+        _context: &HookContext,
+        _matches: &mut Vec<GuidelineMatch>,
+    ) -> Result<HookAction> {
+        Ok(HookAction::Continue)
+    }
+
+    /// Before a tool runs, with the parameters it will receive
+    async fn before_tool(
+        &self,
+        _context: &HookContext,
+        _tool_id: &ToolId,
+        _parameters: &mut HashMap<String, serde_json::Value>,
+    ) -> Result<HookAction> {
+        Ok(HookAction::Continue)
+    }
+
+    /// After a tool runs, with its result or error
+    async fn after_tool(
+        &self,
+        _context: &HookContext,
+        _tool_id: &ToolId,
+        _result: &mut Result<ToolResult>,
+    ) -> Result<HookAction> {
+        Ok(HookAction::Continue)
+    }
+
+    /// Before each LLM call that generates the response, with the prompt messages
+    async fn before_llm(
+        &self,
+        _context: &HookContext,
+        _messages: &mut Vec<Message>,
+    ) -> Result<HookAction> {
+        Ok(HookAction::Continue)
+    }
+
+    /// Before the response is saved to the session and returned
+    ///
+    /// Returning `HookAction::Respond` replaces the response message.
+    async fn before_save(
+        &self,
+        _context: &HookContext,
+        _response: &mut AgentResponse,
+    ) -> Result<HookAction> {
+        Ok(HookAction::Continue)
+    }
+}
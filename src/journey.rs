@@ -26,6 +26,7 @@
 //!                 next_step: step2_id,
 //!             }],
 //!             actions: vec![],
+//!             captures: vec![],
 //!         },
 //!         JourneyStep {
 //!             id: step2_id,
@@ -34,15 +35,17 @@
 //!             expected_response: None,
 //!             transitions: vec![],
 //!             actions: vec!["complete".to_string()],
+//!             captures: vec![],
 //!         },
 //!     ],
 //!     initial_step: step1_id,
 //!     current_step: None,
 //!     created_at: Utc::now(),
+//!     enabled: true,
 //! };
 //! ```
 
-use crate::context::Context;
+use crate::context::{Context, VariableCapture};
 use crate::error::AgentError;
 use crate::types::{JourneyId, SessionId, StepId};
 use crate::Result;
@@ -76,6 +79,14 @@ pub struct Journey {
 
     /// Creation timestamp
     pub created_at: DateTime<Utc>,
+
+    /// Disabled journeys cannot be started; sessions already in them continue
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
 }
 
 /// Individual step within a journey
@@ -98,6 +109,10 @@ pub struct JourneyStep {
 
     /// Actions to execute when reaching this step
     pub actions: Vec<String>,
+
+    /// Context variables captured from the user's reply to this step
+    #[serde(default)]
+    pub captures: Vec<VariableCapture>,
 }
 
 /// Transition from one step to another
@@ -124,7 +139,10 @@ pub enum TransitionCondition {
 }
 
 /// Runtime state of a journey for a session
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// Stored in [`Session::journey_state`](crate::session::Session::journey_state) so that
+/// journey progress is persisted by whichever `SessionStore` backs the agent.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct JourneyState {
     /// Journey being executed
     pub journey_id: JourneyId,
@@ -133,18 +151,26 @@ pub struct JourneyState {
     pub current_step: StepId,
 
     /// Steps already completed
+    #[serde(default)]
     pub completed_steps: Vec<StepId>,
 
+    /// Ordered list of visited steps, starting with the initial step
+    #[serde(default)]
+    pub step_history: Vec<StepId>,
+
     /// Whether journey is complete
+    #[serde(default)]
     pub is_complete: bool,
 
     /// Additional state data
+    #[serde(skip_serializing_if = "HashMap::is_empty", default)]
     pub metadata: HashMap<String, serde_json::Value>,
 
     /// When journey was started
     pub started_at: DateTime<Utc>,
 
     /// When journey was completed (if is_complete)
+    #[serde(skip_serializing_if = "Option::is_none", default)]
     pub completed_at: Option<DateTime<Utc>>,
 }
 
@@ -155,6 +181,7 @@ impl JourneyState {
             journey_id,
             current_step: initial_step,
             completed_steps: Vec::new(),
+            step_history: vec![initial_step],
             is_complete: false,
             metadata: HashMap::new(),
             started_at: Utc::now(),
@@ -169,11 +196,22 @@ impl JourneyState {
         }
     }
 
+    /// Move to the next step
+    pub fn move_to_step(&mut self, step_id: StepId) {
+        self.current_step = step_id;
+        self.step_history.push(step_id);
+    }
+
     /// Mark journey as complete
     pub fn mark_complete(&mut self) {
         self.is_complete = true;
         self.completed_at = Some(Utc::now());
     }
+
+    /// Check if the journey is completed
+    pub fn is_completed(&self) -> bool {
+        self.is_complete
+    }
 }
 
 /// Trait for managing conversation journeys
@@ -200,6 +238,31 @@ pub trait JourneyManager: Send + Sync {
 
     /// Get a journey by ID
     fn get_journey(&self, journey_id: &JourneyId) -> Option<&Journey>;
+
+    /// Find a journey by name
+    ///
+    /// The default implementation finds nothing; override it to support
+    /// journeys loaded from agent definitions by name.
+    fn find_journey(&self, _name: &str) -> Option<&Journey> {
+        None
+    }
+
+    /// Remove a journey
+    ///
+    /// The default implementation does not support removing journeys.
+    async fn remove_journey(&mut self, journey_id: &JourneyId) -> Result<()> {
+        Err(AgentError::Journey(format!(
+            "Journey manager cannot remove journey {:?}",
+            journey_id
+        )))
+    }
+
+    /// List all registered journeys
+    ///
+    /// The default implementation lists nothing.
+    fn list_journeys(&self) -> Vec<&Journey> {
+        Vec::new()
+    }
 }
 
 /// Default implementation of JourneyManager
@@ -364,6 +427,12 @@ impl JourneyManager for DefaultJourneyManager {
             .journeys
             .get(journey_id)
             .ok_or_else(|| AgentError::Journey(format!("Journey {:?} not found", journey_id)))?;
+        if !journey.enabled {
+            return Err(AgentError::Journey(format!(
+                "Journey '{}' is disabled",
+                journey.name
+            )));
+        }
 
         info!(
             session_id = ?session_id,
@@ -451,6 +520,29 @@ impl JourneyManager for DefaultJourneyManager {
     fn get_journey(&self, journey_id: &JourneyId) -> Option<&Journey> {
         self.journeys.get(journey_id)
     }
+
+    fn find_journey(&self, name: &str) -> Option<&Journey> {
+        self.journeys.values().find(|journey| journey.name == name)
+    }
+
+    async fn remove_journey(&mut self, journey_id: &JourneyId) -> Result<()> {
+        self.journeys
+            .remove(journey_id)
+            .map(|journey| {
+                info!(
+                    journey_id = ?journey_id,
+                    journey_name = %journey.name,
+                    "Removed journey"
+                );
+            })
+            .ok_or_else(|| AgentError::Journey(format!("Journey {:?} not found", journey_id)))
+    }
+
+    fn list_journeys(&self) -> Vec<&Journey> {
+        let mut journeys: Vec<&Journey> = self.journeys.values().collect();
+        journeys.sort_by(|a, b| a.name.cmp(&b.name));
+        journeys
+    }
 }
 
 #[cfg(test)]
@@ -466,9 +558,25 @@ mod tests {
         assert_eq!(state.journey_id, journey_id);
         assert_eq!(state.current_step, step_id);
         assert_eq!(state.completed_steps.len(), 0);
+        assert_eq!(state.step_history, vec![step_id]);
         assert!(!state.is_complete);
     }
 
+    #[test]
+    fn test_journey_state_move_to_step() {
+        let journey_id = JourneyId::new();
+        let step1 = StepId::new();
+        let step2 = StepId::new();
+        let step3 = StepId::new();
+
+        let mut state = JourneyState::new(journey_id, step1);
+        state.move_to_step(step2);
+        state.move_to_step(step3);
+
+        assert_eq!(state.current_step, step3);
+        assert_eq!(state.step_history, vec![step1, step2, step3]);
+    }
+
     #[test]
     fn test_journey_state_complete_step() {
         let journey_id = JourneyId::new();
@@ -495,9 +603,26 @@ mod tests {
 
         state.mark_complete();
         assert!(state.is_complete);
+        assert!(state.is_completed());
         assert!(state.completed_at.is_some());
     }
 
+    #[test]
+    fn test_journey_state_deserializes_without_progress_fields() {
+        let journey_id = JourneyId::new();
+        let step_id = StepId::new();
+        let json = serde_json::json!({
+            "journey_id": journey_id,
+            "current_step": step_id,
+            "started_at": Utc::now(),
+        });
+
+        let state: JourneyState = serde_json::from_value(json).unwrap();
+        assert_eq!(state.current_step, step_id);
+        assert!(state.completed_steps.is_empty());
+        assert!(!state.is_complete);
+    }
+
     #[tokio::test]
     async fn test_validate_journey_invalid_initial_step() {
         let manager = DefaultJourneyManager::new();
@@ -512,10 +637,12 @@ mod tests {
                 expected_response: None,
                 transitions: vec![],
                 actions: vec![],
+                captures: vec![],
             }],
             initial_step: StepId::new(), // Different ID
             current_step: None,
             created_at: Utc::now(),
+            enabled: true,
         };
 
         let result = manager.validate_journey(&journey);
@@ -543,6 +670,7 @@ mod tests {
                         next_step: step2_id,
                     }],
                     actions: vec![],
+                    captures: vec![],
                 },
                 JourneyStep {
                     id: step2_id,
@@ -554,11 +682,13 @@ mod tests {
                         next_step: step1_id, // Circular!
                     }],
                     actions: vec![],
+                    captures: vec![],
                 },
             ],
             initial_step: step1_id,
             current_step: None,
             created_at: Utc::now(),
+            enabled: true,
         };
 
         let result = manager.validate_journey(&journey);
@@ -24,10 +24,10 @@
 //! # #[tokio::main]
 //! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
 //! // Create LLM provider
-//! let provider = OpenAIProvider::new(std::env::var("OPENAI_API_KEY")?);
+//! let provider = OpenAIProvider::new(std::env::var("OPENAI_API_KEY")?, "gpt-4");
 //!
 //! // Build agent
-//! let mut agent = Agent::builder()
+//! let agent = Agent::builder()
 //!     .name("Customer Support")
 //!     .provider(Box::new(provider))
 //!     .build()?;
@@ -55,7 +55,7 @@
 //! ### Agent with Tool Integration
 //!
 //! ```no_run
-//! use talk::{Agent, Tool, ToolResult, Guideline, GuidelineCondition, OpenAIProvider};
+//! use talk::{Agent, Tool, ToolResult, Guideline, GuidelineAction, GuidelineCondition, OpenAIProvider};
 //! use std::collections::HashMap;
 //!
 //! # #[tokio::main]
@@ -82,8 +82,8 @@
 //!     }
 //! }
 //!
-//! let provider = OpenAIProvider::new(std::env::var("OPENAI_API_KEY")?);
-//! let mut agent = Agent::builder()
+//! let provider = OpenAIProvider::new(std::env::var("OPENAI_API_KEY")?, "gpt-4");
+//! let agent = Agent::builder()
 //!     .name("Weather Bot")
 //!     .provider(Box::new(provider))
 //!     .build()?;
@@ -144,6 +144,8 @@
 //! - [`storage`]: Session storage backends
 //! - [`context`]: Conversation context and variables
 //! - [`session`]: Session lifecycle management
+//! - [`router`]: Routing and handoff between several agents
+//! - [`definition`]: Agent definitions loaded from YAML, JSON or TOML files
 //! - [`error`]: Error types and result aliases
 //!
 //! ## Examples
@@ -194,22 +196,74 @@ pub mod tool;
 // Journey system
 pub mod journey;
 
+// Response templates
+pub mod template;
+
+// Agent hooks
+pub mod hook;
+
+// Output guardrails
+pub mod guardrail;
+
+// Declarative agent definitions
+pub mod definition;
+
+// Multi-agent routing
+pub mod router;
+
+// Built-in metrics
+pub mod metrics;
+
+// Decision traces
+pub mod trace;
+
+// Fallback strategies
+pub mod fallback;
+
+// HTTP server
+#[cfg(feature = "server")]
+pub mod server;
+
 // Public API exports will be added as modules are implemented
 pub use agent::{
-    Agent, AgentBuilder, AgentConfig, AgentResponse, LogLevel, ResponseExplanation, ToolExecution,
+    Agent, AgentBuilder, AgentConfig, AgentEvent, AgentEventStream, AgentResponse,
+    GuidelineSelection, LogLevel, MessageOptions, ResponseExplanation, ToolExecution,
+};
+pub use context::{
+    CaptureSource, Context, ContextVariable, Message, MessageRole, Validator, VariableCapture,
+};
+pub use definition::{
+    AgentDefinition, DefinitionFormat, DefinitionIssue, GuidelineDefinition, JourneyDefinition,
+    ProviderDefinition, ProviderKind, StepDefinition, TransitionDefinition,
 };
-pub use context::{Context, ContextVariable, Message, MessageRole, Validator};
+pub use fallback::FallbackStrategy;
 pub use error::{AgentError, GuidelineError, JourneyError, Result, StorageError, ToolError};
+pub use guardrail::{Guardrails, ResponseValidator, Violation};
 pub use guideline::{
     DefaultGuidelineMatcher, Guideline, GuidelineAction, GuidelineCondition, GuidelineMatch,
     GuidelineMatcher, ParameterDef,
 };
+pub use hook::{AgentHook, HookAction, HookContext};
 pub use journey::{
     DefaultJourneyManager, Journey, JourneyManager, JourneyState, JourneyStep, Transition,
     TransitionCondition,
 };
-pub use provider::{AnthropicProvider, LLMProvider, OpenAIProvider, ProviderConfig, StreamChunk};
-pub use session::{Session, SessionStatus};
+pub use metrics::{Histogram, Metrics, MetricsSnapshot, ToolStats};
+pub use provider::{
+    AnthropicProvider, CompletionResponse, LLMProvider, ModelPrice, OpenAIProvider,
+    ProviderConfig, StreamChunk, TokenUsage, ToolCompletion,
+};
+#[cfg(feature = "testing")]
+pub use provider::{
+    Cassette, RecordingProvider, ReplayProvider, ScriptedProvider, ScriptedReply,
+};
+pub use router::{AgentRouter, AgentRouterBuilder, RoutedResponse};
+pub use session::{IdempotentResponse, Session, SessionStatus};
+pub use template::{MissingVariablePolicy, TemplateContext};
 pub use storage::{memory::InMemorySessionStore, SessionStore};
-pub use tool::{ParameterSchema, Tool, ToolRegistry, ToolResult};
+pub use tool::{ParameterSchema, Tool, ToolCall, ToolDefinition, ToolRegistry, ToolResult};
+pub use trace::{
+    CandidateOutcome, CandidateTrace, DecisionTrace, LlmCallKind, LlmCallTrace, ToolCallTrace,
+    DECISION_TRACE_KEY,
+};
 pub use types::*;
@@ -0,0 +1,430 @@
+//! Built-in metrics
+//!
+//! Every [`Agent`](crate::Agent) records counters, latency histograms and the
+//! number of active sessions in a [`Metrics`] registry. Read them in code with
+//! [`Metrics::snapshot`] or render them in the Prometheus text exposition format
+//! with [`Metrics::render_prometheus`] and serve the text from your own endpoint.
+//!
+//! Agents create their own registry by default; pass one to
+//! [`AgentBuilder::metrics`](crate::AgentBuilder::metrics) to share it between
+//! agents.
+
+use crate::provider::TokenUsage;
+use crate::types::GuidelineId;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Upper bounds of the latency histogram buckets, in seconds
+pub const LATENCY_BUCKETS: [f64; 12] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0,
+];
+
+/// Latency distribution with fixed buckets
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Histogram {
+    /// Upper bound of each bucket, in seconds
+    pub bounds: Vec<f64>,
+    /// Observations per bucket; observations above the last bound are only in `count`
+    pub buckets: Vec<u64>,
+    pub count: u64,
+    /// Sum of all observations, in seconds
+    pub sum: f64,
+}
+
+impl Histogram {
+    pub fn new() -> Self {
+        Self {
+            bounds: LATENCY_BUCKETS.to_vec(),
+            buckets: vec![0; LATENCY_BUCKETS.len()],
+            count: 0,
+            sum: 0.0,
+        }
+    }
+
+    /// Record one observation
+    pub fn observe(&mut self, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        if let Some(bucket) = self.bounds.iter().position(|bound| seconds <= *bound) {
+            self.buckets[bucket] += 1;
+        }
+        self.count += 1;
+        self.sum += seconds;
+    }
+
+    /// Mean observation, if anything was observed
+    pub fn mean(&self) -> Option<Duration> {
+        (self.count > 0).then(|| Duration::from_secs_f64(self.sum / self.count as f64))
+    }
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Outcome counts for one tool
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolStats {
+    /// Executions that eventually succeeded
+    pub successes: u64,
+    /// Executions that failed after all retries
+    pub failures: u64,
+    /// Attempts that were retried
+    pub retries: u64,
+    /// Attempts that timed out
+    pub timeouts: u64,
+}
+
+/// Point-in-time copy of all metrics
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MetricsSnapshot {
+    pub messages_processed: u64,
+    pub fallbacks_used: u64,
+    pub guideline_hits: HashMap<GuidelineId, u64>,
+    /// Outcome counts by tool name
+    pub tools: HashMap<String, ToolStats>,
+    /// Time spent matching guidelines per message
+    pub matching_latency: Histogram,
+    /// Time per tool execution, including retries, by tool name
+    pub tool_latency: HashMap<String, Histogram>,
+    /// Time per LLM call
+    pub llm_latency: Histogram,
+    /// Tokens used by all LLM calls
+    pub token_usage: TokenUsage,
+    /// Cost of those tokens, priced with `AgentConfig::model_prices`
+    pub cost: f64,
+    pub active_sessions: u64,
+}
+
+/// Thread-safe metrics registry
+#[derive(Debug, Default)]
+pub struct Metrics {
+    state: Mutex<MetricsSnapshot>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Copy the current values
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        self.state().clone()
+    }
+
+    pub fn record_message(&self) {
+        self.state().messages_processed += 1;
+    }
+
+    pub fn record_fallback(&self) {
+        self.state().fallbacks_used += 1;
+    }
+
+    pub fn record_guideline_hit(&self, guideline_id: GuidelineId) {
+        *self.state().guideline_hits.entry(guideline_id).or_default() += 1;
+    }
+
+    pub fn record_matching(&self, duration: Duration) {
+        self.state().matching_latency.observe(duration);
+    }
+
+    pub fn record_llm_call(&self, duration: Duration) {
+        self.state().llm_latency.observe(duration);
+    }
+
+    pub fn record_token_usage(&self, usage: TokenUsage, cost: f64) {
+        let mut state = self.state();
+        state.token_usage += usage;
+        state.cost += cost;
+    }
+
+    /// Record a finished tool execution and how long it took, retries included
+    pub fn record_tool_execution(&self, tool_name: &str, success: bool, duration: Duration) {
+        let mut state = self.state();
+        let stats = state.tools.entry(tool_name.to_string()).or_default();
+        if success {
+            stats.successes += 1;
+        } else {
+            stats.failures += 1;
+        }
+        state
+            .tool_latency
+            .entry(tool_name.to_string())
+            .or_default()
+            .observe(duration);
+    }
+
+    pub fn record_tool_retry(&self, tool_name: &str) {
+        self.state()
+            .tools
+            .entry(tool_name.to_string())
+            .or_default()
+            .retries += 1;
+    }
+
+    pub fn record_tool_timeout(&self, tool_name: &str) {
+        self.state()
+            .tools
+            .entry(tool_name.to_string())
+            .or_default()
+            .timeouts += 1;
+    }
+
+    pub fn session_started(&self) {
+        self.state().active_sessions += 1;
+    }
+
+    pub fn session_ended(&self) {
+        let mut state = self.state();
+        state.active_sessions = state.active_sessions.saturating_sub(1);
+    }
+
+    /// Render all metrics in the Prometheus text exposition format
+    pub fn render_prometheus(&self) -> String {
+        let snapshot = self.snapshot();
+        let mut out = String::new();
+
+        counter(
+            &mut out,
+            "talk_messages_processed_total",
+            "Messages processed",
+            [(None, snapshot.messages_processed)],
+        );
+        counter(
+            &mut out,
+            "talk_fallbacks_total",
+            "Messages answered by the fallback guideline",
+            [(None, snapshot.fallbacks_used)],
+        );
+        let hits: BTreeMap<String, u64> = snapshot
+            .guideline_hits
+            .iter()
+            .map(|(id, hits)| (id.to_string(), *hits))
+            .collect();
+        counter(
+            &mut out,
+            "talk_guideline_hits_total",
+            "Times each guideline was applied",
+            hits.iter()
+                .map(|(id, hits)| (Some(("guideline_id", id.as_str())), *hits)),
+        );
+
+        let tools: BTreeMap<&String, &ToolStats> = snapshot.tools.iter().collect();
+        let per_tool = |field: fn(&ToolStats) -> u64| {
+            tools
+                .iter()
+                .map(move |(name, stats)| (Some(("tool", name.as_str())), field(stats)))
+        };
+        counter(
+            &mut out,
+            "talk_tool_successes_total",
+            "Tool executions that succeeded",
+            per_tool(|stats| stats.successes),
+        );
+        counter(
+            &mut out,
+            "talk_tool_failures_total",
+            "Tool executions that failed after all retries",
+            per_tool(|stats| stats.failures),
+        );
+        counter(
+            &mut out,
+            "talk_tool_retries_total",
+            "Tool attempts that were retried",
+            per_tool(|stats| stats.retries),
+        );
+        counter(
+            &mut out,
+            "talk_tool_timeouts_total",
+            "Tool attempts that timed out",
+            per_tool(|stats| stats.timeouts),
+        );
+
+        header(
+            &mut out,
+            "talk_matching_duration_seconds",
+            "Time spent matching guidelines",
+            "histogram",
+        );
+        histogram(
+            &mut out,
+            "talk_matching_duration_seconds",
+            None,
+            &snapshot.matching_latency,
+        );
+        header(
+            &mut out,
+            "talk_tool_duration_seconds",
+            "Time per tool execution, including retries",
+            "histogram",
+        );
+        let tool_latency: BTreeMap<&String, &Histogram> = snapshot.tool_latency.iter().collect();
+        for (name, latency) in tool_latency {
+            histogram(
+                &mut out,
+                "talk_tool_duration_seconds",
+                Some(("tool", name)),
+                latency,
+            );
+        }
+        header(
+            &mut out,
+            "talk_llm_duration_seconds",
+            "Time per LLM call",
+            "histogram",
+        );
+        histogram(
+            &mut out,
+            "talk_llm_duration_seconds",
+            None,
+            &snapshot.llm_latency,
+        );
+
+        counter(
+            &mut out,
+            "talk_prompt_tokens_total",
+            "Input tokens sent to the LLM",
+            [(None, snapshot.token_usage.prompt_tokens as u64)],
+        );
+        counter(
+            &mut out,
+            "talk_completion_tokens_total",
+            "Output tokens generated by the LLM",
+            [(None, snapshot.token_usage.completion_tokens as u64)],
+        );
+        header(
+            &mut out,
+            "talk_llm_cost_total",
+            "Cost of LLM tokens",
+            "counter",
+        );
+        let _ = writeln!(out, "talk_llm_cost_total {}", snapshot.cost);
+
+        header(&mut out, "talk_active_sessions", "Active sessions", "gauge");
+        let _ = writeln!(out, "talk_active_sessions {}", snapshot.active_sessions);
+
+        out
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, MetricsSnapshot> {
+        // Metrics stay usable even if a thread panicked while recording
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+type Label<'a> = Option<(&'a str, &'a str)>;
+
+fn header(out: &mut String, name: &str, help: &str, kind: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn counter<'a>(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    values: impl IntoIterator<Item = (Label<'a>, u64)>,
+) {
+    header(out, name, help, "counter");
+    for (label, value) in values {
+        let _ = writeln!(out, "{}{} {}", name, labels(label, None), value);
+    }
+}
+
+fn histogram(out: &mut String, name: &str, label: Label<'_>, histogram: &Histogram) {
+    let mut cumulative = 0;
+    for (bound, count) in histogram.bounds.iter().zip(&histogram.buckets) {
+        cumulative += count;
+        let _ = writeln!(
+            out,
+            "{}_bucket{} {}",
+            name,
+            labels(label, Some(&bound.to_string())),
+            cumulative
+        );
+    }
+    let _ = writeln!(
+        out,
+        "{}_bucket{} {}",
+        name,
+        labels(label, Some("+Inf")),
+        histogram.count
+    );
+    let _ = writeln!(out, "{}_sum{} {}", name, labels(label, None), histogram.sum);
+    let _ = writeln!(
+        out,
+        "{}_count{} {}",
+        name,
+        labels(label, None),
+        histogram.count
+    );
+}
+
+fn labels(label: Label<'_>, le: Option<&str>) -> String {
+    let mut pairs = Vec::new();
+    if let Some((key, value)) = label {
+        pairs.push(format!("{}=\"{}\"", key, escape(value)));
+    }
+    if let Some(le) = le {
+        pairs.push(format!("le=\"{}\"", le));
+    }
+    if pairs.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", pairs.join(","))
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_histogram_buckets() {
+        let mut histogram = Histogram::new();
+        histogram.observe(Duration::from_millis(3));
+        histogram.observe(Duration::from_millis(30));
+        histogram.observe(Duration::from_secs(60));
+
+        assert_eq!(histogram.count, 3);
+        assert_eq!(histogram.buckets[0], 1);
+        assert_eq!(histogram.buckets[3], 1);
+        assert_eq!(histogram.buckets.iter().sum::<u64>(), 2);
+        assert!((histogram.sum - 60.033).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_render_prometheus() {
+        let metrics = Metrics::new();
+        metrics.record_message();
+        metrics.record_tool_execution("weather \"v2\"", true, Duration::from_millis(20));
+        metrics.record_tool_retry("weather \"v2\"");
+        metrics.session_started();
+        metrics.session_started();
+        metrics.session_ended();
+
+        let text = metrics.render_prometheus();
+        assert!(text.contains("# TYPE talk_messages_processed_total counter\n"));
+        assert!(text.contains("talk_messages_processed_total 1\n"));
+        assert!(text.contains("talk_tool_retries_total{tool=\"weather \\\"v2\\\"\"} 1\n"));
+        assert!(text.contains(
+            "talk_tool_duration_seconds_bucket{tool=\"weather \\\"v2\\\"\",le=\"0.025\"} 1\n"
+        ));
+        assert!(text.contains(
+            "talk_tool_duration_seconds_bucket{tool=\"weather \\\"v2\\\"\",le=\"+Inf\"} 1\n"
+        ));
+        assert!(text.contains("talk_llm_duration_seconds_count 0\n"));
+        assert!(text.contains("talk_active_sessions 1\n"));
+    }
+}
@@ -4,11 +4,17 @@
 
 use crate::context::{Message, MessageRole};
 use crate::error::AgentError;
-use crate::provider::{LLMProvider, ProviderConfig, StreamChunk};
+use crate::provider::{
+    CompletionResponse, LLMProvider, ProviderConfig, StreamChunk, TokenUsage, ToolCompletion,
+};
+use crate::tool::{ToolCall, ToolDefinition};
 use async_trait::async_trait;
 use futures::Stream;
+use serde_json::{json, Value};
 use std::pin::Pin;
-use tracing::info;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::{debug, info};
 
 /// Anthropic LLM provider
 pub struct AnthropicProvider {
@@ -56,29 +62,20 @@ impl AnthropicProvider {
         self.config = self.config.with_max_tokens(max_tokens);
         self
     }
-}
-
-#[async_trait]
-impl LLMProvider for AnthropicProvider {
-    async fn complete(&self, messages: Vec<Message>) -> std::result::Result<String, AgentError> {
-        use serde_json::json;
-        use std::sync::Arc;
-        use tokio::sync::Mutex;
-
-        info!(
-            model = %self.config.model,
-            message_count = messages.len(),
-            "Requesting Anthropic completion"
-        );
 
-        // Convert Talk messages to Anthropic JSON format
-        let mut anthropic_messages = Vec::new();
-        let mut system_prompt = String::new();
+    /// Convert Talk messages to Anthropic JSON format
+    ///
+    /// Returns the combined system prompt and the conversation messages. Tool
+    /// calls become `tool_use` blocks, and tool results answering them become
+    /// `tool_result` blocks in a user message.
+    fn convert_messages(messages: Vec<Message>) -> (String, Vec<Value>) {
+        let mut anthropic_messages: Vec<Value> = Vec::new();
+        let mut system_prompts = Vec::new();
 
         for msg in messages {
             match msg.role {
                 MessageRole::System => {
-                    system_prompt = msg.content;
+                    system_prompts.push(msg.content);
                 }
                 MessageRole::User => {
                     anthropic_messages.push(json!({
@@ -86,22 +83,119 @@ impl LLMProvider for AnthropicProvider {
                         "content": msg.content
                     }));
                 }
-                MessageRole::Assistant => {
-                    anthropic_messages.push(json!({
-                        "role": "assistant",
-                        "content": msg.content
-                    }));
+                MessageRole::Assistant | MessageRole::Operator => {
+                    let tool_calls = msg.tool_calls();
+                    if tool_calls.is_empty() {
+                        anthropic_messages.push(json!({
+                            "role": "assistant",
+                            "content": msg.content
+                        }));
+                    } else {
+                        let mut blocks = Vec::new();
+                        if !msg.content.is_empty() {
+                            blocks.push(json!({"type": "text", "text": msg.content}));
+                        }
+                        blocks.extend(tool_calls.into_iter().map(|call| {
+                            json!({
+                                "type": "tool_use",
+                                "id": call.id,
+                                "name": call.name,
+                                "input": call.arguments
+                            })
+                        }));
+                        anthropic_messages.push(json!({
+                            "role": "assistant",
+                            "content": blocks
+                        }));
+                    }
                 }
                 MessageRole::Tool => {
-                    // Anthropic uses "user" role for tool results
-                    anthropic_messages.push(json!({
-                        "role": "user",
+                    let Some(tool_use_id) = msg.tool_call_id() else {
+                        // Anthropic uses "user" role for tool results
+                        anthropic_messages.push(json!({
+                            "role": "user",
+                            "content": msg.content
+                        }));
+                        continue;
+                    };
+
+                    let block = json!({
+                        "type": "tool_result",
+                        "tool_use_id": tool_use_id,
                         "content": msg.content
-                    }));
+                    });
+
+                    // Results for the same assistant turn share one user message
+                    let previous_blocks = anthropic_messages
+                        .last_mut()
+                        .filter(|m| m["role"] == "user")
+                        .and_then(|m| m["content"].as_array_mut());
+                    match previous_blocks {
+                        Some(blocks) => blocks.push(block),
+                        None => anthropic_messages.push(json!({
+                            "role": "user",
+                            "content": [block]
+                        })),
+                    }
+                }
+            }
+        }
+
+        (system_prompts.join("\n\n"), anthropic_messages)
+    }
+
+    /// Parse a raw (non-streaming) Messages API response body
+    fn parse_tool_response(body: &str) -> Result<ToolCompletion, AgentError> {
+        let response: Value = serde_json::from_str(body).map_err(|e| {
+            AgentError::ProviderError(format!("Invalid Anthropic response: {}", e))
+        })?;
+
+        let mut completion = ToolCompletion::default();
+        for block in response["content"].as_array().into_iter().flatten() {
+            match block["type"].as_str() {
+                Some("text") => {
+                    completion
+                        .content
+                        .push_str(block["text"].as_str().unwrap_or_default());
+                }
+                Some("tool_use") => {
+                    completion.tool_calls.push(ToolCall {
+                        id: block["id"].as_str().unwrap_or_default().to_string(),
+                        name: block["name"].as_str().unwrap_or_default().to_string(),
+                        arguments: serde_json::from_value(block["input"].clone())
+                            .unwrap_or_default(),
+                    });
                 }
+                _ => {}
             }
         }
+        let usage = &response["usage"];
+        completion.usage = TokenUsage::new(
+            usage["input_tokens"].as_u64().unwrap_or_default() as u32,
+            usage["output_tokens"].as_u64().unwrap_or_default() as u32,
+        );
 
+        Ok(completion)
+    }
+}
+
+#[async_trait]
+impl LLMProvider for AnthropicProvider {
+    async fn complete(&self, messages: Vec<Message>) -> std::result::Result<String, AgentError> {
+        Ok(self.complete_with_usage(messages).await?.content)
+    }
+
+    async fn complete_with_usage(
+        &self,
+        messages: Vec<Message>,
+    ) -> std::result::Result<CompletionResponse, AgentError> {
+        info!(
+            model = %self.config.model,
+            message_count = messages.len(),
+            "Requesting Anthropic completion"
+        );
+
+        let (system_prompt, anthropic_messages) = Self::convert_messages(messages);
         let messages_value = json!(anthropic_messages);
 
         // Build request - create new client each time since Client doesn't implement Clone
@@ -110,6 +204,75 @@ impl LLMProvider for AnthropicProvider {
             .model(&self.config.model)
             .messages(&messages_value)
             .max_tokens(self.config.max_tokens.unwrap_or(4096) as i32)
+            .temperature(self.config.temperature)
+            // Hand back the raw response body, which includes the token usage
+            .verbose(true);
+
+        if !system_prompt.is_empty() {
+            client_builder = client_builder.system(&system_prompt);
+        }
+
+        let request = client_builder
+            .build()
+            .map_err(|e| AgentError::ProviderError(format!("Failed to build request: {}", e)))?;
+
+        let response_body = Arc::new(Mutex::new(String::new()));
+        let response_body_clone = Arc::clone(&response_body);
+
+        request
+            .execute(|body| {
+                let response_body = Arc::clone(&response_body_clone);
+                async move {
+                    response_body.lock().await.push_str(&body);
+                }
+            })
+            .await
+            .map_err(|e| AgentError::ProviderError(format!("Anthropic API error: {}", e)))?;
+
+        let completion = Self::parse_tool_response(&response_body.lock().await)?;
+
+        debug!(
+            response_length = completion.content.len(),
+            total_tokens = completion.usage.total_tokens,
+            "Anthropic completion successful"
+        );
+
+        Ok(CompletionResponse::new(completion.content, completion.usage))
+    }
+
+    async fn complete_with_tools(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<ToolDefinition>,
+    ) -> std::result::Result<ToolCompletion, AgentError> {
+        info!(
+            model = %self.config.model,
+            message_count = messages.len(),
+            tool_count = tools.len(),
+            "Requesting Anthropic completion with tools"
+        );
+
+        let (system_prompt, anthropic_messages) = Self::convert_messages(messages);
+        let messages_value = json!(anthropic_messages);
+        let tools_value = Value::Array(
+            tools
+                .into_iter()
+                .map(|tool| {
+                    json!({
+                        "name": tool.name,
+                        "description": tool.description,
+                        "input_schema": tool.parameters
+                    })
+                })
+                .collect(),
+        );
+
+        let mut client_builder = anthropic_sdk::Client::new()
+            .auth(&self.api_key)
+            .model(&self.config.model)
+            .messages(&messages_value)
+            .tools(&tools_value)
+            .max_tokens(self.config.max_tokens.unwrap_or(4096) as i32)
             .temperature(self.config.temperature);
 
         if !system_prompt.is_empty() {
@@ -120,23 +283,28 @@ impl LLMProvider for AnthropicProvider {
             .build()
             .map_err(|e| AgentError::ProviderError(format!("Failed to build request: {}", e)))?;
 
-        // Collect response text
-        let response_text = Arc::new(Mutex::new(String::new()));
-        let response_text_clone = Arc::clone(&response_text);
+        // With tools set, the SDK hands back the raw response body
+        let response_body = Arc::new(Mutex::new(String::new()));
+        let response_body_clone = Arc::clone(&response_body);
 
         request
-            .execute(|chunk| {
-                let response_text = Arc::clone(&response_text_clone);
+            .execute(|body| {
+                let response_body = Arc::clone(&response_body_clone);
                 async move {
-                    let mut text = response_text.lock().await;
-                    text.push_str(&chunk);
+                    response_body.lock().await.push_str(&body);
                 }
             })
             .await
             .map_err(|e| AgentError::ProviderError(format!("Anthropic API error: {}", e)))?;
 
-        let final_text = response_text.lock().await.clone();
-        Ok(final_text)
+        let completion = Self::parse_tool_response(&response_body.lock().await)?;
+
+        debug!(
+            tool_call_count = completion.tool_calls.len(),
+            "Anthropic completion with tools successful"
+        );
+
+        Ok(completion)
     }
 
     async fn stream(
@@ -182,6 +350,50 @@ mod tests {
         assert_eq!(provider.config().temperature, 0.5);
     }
 
+    #[test]
+    fn test_convert_messages_with_tool_calls() {
+        let call = ToolCall {
+            id: "toolu_1".to_string(),
+            name: "weather".to_string(),
+            arguments: Default::default(),
+        };
+        let messages = vec![
+            Message::system("You are helpful"),
+            Message::system("Be brief"),
+            Message::user("Weather?"),
+            Message::assistant_tool_calls("", &[call]),
+            Message::tool_result("toolu_1", "sunny"),
+        ];
+
+        let (system, converted) = AnthropicProvider::convert_messages(messages);
+        assert_eq!(system, "You are helpful\n\nBe brief");
+        assert_eq!(converted.len(), 3);
+        assert_eq!(converted[1]["content"][0]["type"], "tool_use");
+        assert_eq!(converted[1]["content"][0]["id"], "toolu_1");
+        assert_eq!(converted[2]["role"], "user");
+        assert_eq!(converted[2]["content"][0]["type"], "tool_result");
+        assert_eq!(converted[2]["content"][0]["tool_use_id"], "toolu_1");
+    }
+
+    #[test]
+    fn test_parse_tool_response() {
+        let body = r#"{
+            "content": [
+                {"type": "text", "text": "Let me check."},
+                {"type": "tool_use", "id": "toolu_1", "name": "weather", "input": {"city": "Paris"}}
+            ],
+            "stop_reason": "tool_use",
+            "usage": {"input_tokens": 120, "output_tokens": 30}
+        }"#;
+
+        let completion = AnthropicProvider::parse_tool_response(body).unwrap();
+        assert_eq!(completion.content, "Let me check.");
+        assert_eq!(completion.tool_calls.len(), 1);
+        assert_eq!(completion.tool_calls[0].name, "weather");
+        assert_eq!(completion.tool_calls[0].arguments["city"], "Paris");
+        assert_eq!(completion.usage, TokenUsage::new(120, 30));
+    }
+
     #[test]
     fn test_anthropic_provider_with_max_tokens() {
         let provider = AnthropicProvider::new("test-api-key", "claude-3-5-sonnet-20241022").with_max_tokens(1000);
@@ -0,0 +1,458 @@
+//! Record and replay LLM traffic for offline tests
+//!
+//! [`RecordingProvider`] wraps a real provider and writes every request and
+//! response pair to a cassette file. [`ReplayProvider`] serves those pairs
+//! back without network access, so flows recorded once against a real LLM can
+//! run offline in CI.
+//!
+//! Requests are keyed by a hash of the normalized messages, the tool
+//! definitions and the [`ProviderConfig`]. Message IDs and timestamps are
+//! ignored and whitespace is collapsed, so the same conversation replays even
+//! though each run creates new messages.
+//!
+//! Available with the `testing` feature.
+
+use crate::context::{Message, MessageRole};
+use crate::error::AgentError;
+use crate::provider::{
+    CompletionResponse, LLMProvider, ProviderConfig, StreamChunk, ToolCompletion,
+};
+use crate::tool::ToolDefinition;
+use async_trait::async_trait;
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use tokio::sync::Mutex;
+use tracing::debug;
+
+/// Provider method a cassette entry was recorded from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CallKind {
+    /// `complete` or `complete_with_usage`
+    Complete,
+    /// `complete_with_tools`
+    CompleteWithTools,
+    /// `stream`, recorded as the full text
+    Stream,
+}
+
+/// One recorded request and its response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interaction {
+    /// Hash of `request`
+    pub key: String,
+    pub kind: CallKind,
+    /// The normalized request, kept to make cassettes readable
+    pub request: serde_json::Value,
+    pub response: ToolCompletion,
+}
+
+/// Recorded interactions with one provider
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Cassette {
+    /// Name of the provider that was recorded
+    pub provider: String,
+    /// Configuration of the provider that was recorded
+    pub config: ProviderConfig,
+    pub interactions: Vec<Interaction>,
+}
+
+impl Cassette {
+    /// Create an empty cassette for a provider
+    pub fn new(provider: impl Into<String>, config: ProviderConfig) -> Self {
+        Self {
+            provider: provider.into(),
+            config,
+            interactions: Vec::new(),
+        }
+    }
+
+    /// Read a cassette from a JSON file
+    pub fn load(path: impl AsRef<Path>) -> std::result::Result<Self, AgentError> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            AgentError::Configuration(format!(
+                "Failed to read cassette '{}': {}",
+                path.display(),
+                e
+            ))
+        })?;
+        serde_json::from_str(&content).map_err(|e| {
+            AgentError::Configuration(format!("Invalid cassette '{}': {}", path.display(), e))
+        })
+    }
+
+    /// Write the cassette to a JSON file
+    pub async fn save(&self, path: impl AsRef<Path>) -> std::result::Result<(), AgentError> {
+        let path = path.as_ref();
+        let content = serde_json::to_string_pretty(self)?;
+        tokio::fs::write(path, content).await.map_err(|e| {
+            AgentError::Configuration(format!(
+                "Failed to write cassette '{}': {}",
+                path.display(),
+                e
+            ))
+        })
+    }
+}
+
+/// Normalize a request and compute its cassette key
+///
+/// Only the role, the content (with whitespace collapsed) and the tool call
+/// metadata of each message are kept.
+pub fn request_key(
+    kind: CallKind,
+    messages: &[Message],
+    tools: &[ToolDefinition],
+    config: &ProviderConfig,
+) -> (String, serde_json::Value) {
+    let messages: Vec<serde_json::Value> = messages
+        .iter()
+        .map(|message| {
+            let content: Vec<&str> = message.content.split_whitespace().collect();
+            let mut normalized = serde_json::json!({
+                "role": message.role,
+                "content": content.join(" "),
+            });
+            let tool_calls = message.tool_calls();
+            if !tool_calls.is_empty() {
+                normalized["tool_calls"] = serde_json::json!(tool_calls);
+            }
+            if let Some(id) = message.tool_call_id() {
+                normalized["tool_call_id"] = serde_json::json!(id);
+            }
+            normalized
+        })
+        .collect();
+    let request = serde_json::json!({
+        "kind": kind,
+        "config": config,
+        "messages": messages,
+        "tools": tools,
+    });
+
+    // FNV-1a, so keys stay the same across Rust versions and platforms
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in request.to_string().bytes() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    (format!("{:016x}", hash), request)
+}
+
+/// Provider that records another provider's traffic to a cassette file
+///
+/// The cassette is rewritten after every successful call, so it is complete
+/// even if the test fails part way. Failed calls are not recorded.
+pub struct RecordingProvider {
+    inner: Box<dyn LLMProvider>,
+    path: PathBuf,
+    cassette: Mutex<Cassette>,
+}
+
+impl RecordingProvider {
+    /// Record `inner` to `path`, replacing any cassette already there
+    pub fn new(inner: Box<dyn LLMProvider>, path: impl Into<PathBuf>) -> Self {
+        let cassette = Cassette::new(inner.name(), inner.config().clone());
+        Self {
+            inner,
+            path: path.into(),
+            cassette: Mutex::new(cassette),
+        }
+    }
+
+    /// Path of the cassette file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    async fn record(
+        &self,
+        kind: CallKind,
+        messages: &[Message],
+        tools: &[ToolDefinition],
+        response: ToolCompletion,
+    ) -> std::result::Result<(), AgentError> {
+        let (key, request) = request_key(kind, messages, tools, self.inner.config());
+        debug!(key = %key, kind = ?kind, "Recording LLM interaction");
+
+        let mut cassette = self.cassette.lock().await;
+        cassette.interactions.push(Interaction {
+            key,
+            kind,
+            request,
+            response,
+        });
+        cassette.save(&self.path).await
+    }
+}
+
+#[async_trait]
+impl LLMProvider for RecordingProvider {
+    async fn complete(&self, messages: Vec<Message>) -> std::result::Result<String, AgentError> {
+        Ok(self.complete_with_usage(messages).await?.content)
+    }
+
+    async fn complete_with_usage(
+        &self,
+        messages: Vec<Message>,
+    ) -> std::result::Result<CompletionResponse, AgentError> {
+        let response = self.inner.complete_with_usage(messages.clone()).await?;
+        let recorded = ToolCompletion {
+            content: response.content.clone(),
+            tool_calls: Vec::new(),
+            usage: response.usage,
+        };
+        self.record(CallKind::Complete, &messages, &[], recorded)
+            .await?;
+        Ok(response)
+    }
+
+    /// Stream from the inner provider, recording the text once it has all arrived
+    async fn stream(
+        &self,
+        messages: Vec<Message>,
+    ) -> std::result::Result<Pin<Box<dyn Stream<Item = StreamChunk> + Send>>, AgentError> {
+        let chunks: Vec<StreamChunk> = self.inner.stream(messages.clone()).await?.collect().await;
+        if chunks.iter().all(|chunk| chunk.is_ok()) {
+            let content = chunks.iter().flatten().map(String::as_str).collect();
+            let recorded = ToolCompletion {
+                content,
+                ..Default::default()
+            };
+            self.record(CallKind::Stream, &messages, &[], recorded)
+                .await?;
+        }
+        Ok(Box::pin(futures::stream::iter(chunks)))
+    }
+
+    async fn complete_with_tools(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<ToolDefinition>,
+    ) -> std::result::Result<ToolCompletion, AgentError> {
+        let response = self
+            .inner
+            .complete_with_tools(messages.clone(), tools.clone())
+            .await?;
+        self.record(
+            CallKind::CompleteWithTools,
+            &messages,
+            &tools,
+            response.clone(),
+        )
+        .await?;
+        Ok(response)
+    }
+
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn config(&self) -> &ProviderConfig {
+        self.inner.config()
+    }
+
+    fn context_window(&self) -> Option<usize> {
+        self.inner.context_window()
+    }
+}
+
+/// Provider that answers from a cassette file without network access
+///
+/// A request that was recorded several times is answered with its recordings
+/// in order, repeating the last one. A request missing from the cassette
+/// fails with an `AgentError::LLMProvider` naming its key and last user message.
+pub struct ReplayProvider {
+    path: PathBuf,
+    cassette: Cassette,
+    served: std::sync::Mutex<HashMap<String, usize>>,
+}
+
+impl ReplayProvider {
+    /// Load a cassette written by [`RecordingProvider`]
+    pub fn from_file(path: impl Into<PathBuf>) -> std::result::Result<Self, AgentError> {
+        let path = path.into();
+        let cassette = Cassette::load(&path)?;
+        Ok(Self::new(cassette, path))
+    }
+
+    /// Replay a cassette that is already loaded; `path` is only used in errors
+    pub fn new(cassette: Cassette, path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            cassette,
+            served: std::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The cassette being replayed
+    pub fn cassette(&self) -> &Cassette {
+        &self.cassette
+    }
+
+    fn replay(
+        &self,
+        kind: CallKind,
+        messages: &[Message],
+        tools: &[ToolDefinition],
+    ) -> std::result::Result<ToolCompletion, AgentError> {
+        let (key, _) = request_key(kind, messages, tools, &self.cassette.config);
+        let recorded: Vec<&Interaction> = self
+            .cassette
+            .interactions
+            .iter()
+            .filter(|interaction| interaction.key == key)
+            .collect();
+
+        if recorded.is_empty() {
+            let last_user = messages
+                .iter()
+                .rev()
+                .find(|m| m.role == MessageRole::User)
+                .map(|m| m.content.as_str())
+                .unwrap_or_default();
+            return Err(AgentError::LLMProvider(
+                format!(
+                    "Request {} ({:?}, last user message {:?}) is not in cassette '{}'; re-record it",
+                    key,
+                    kind,
+                    last_user,
+                    self.path.display()
+                )
+                .into(),
+            ));
+        }
+
+        let mut served = self.served.lock().unwrap_or_else(|e| e.into_inner());
+        let count = served.entry(key.clone()).or_default();
+        let interaction = recorded[(*count).min(recorded.len() - 1)];
+        *count += 1;
+        debug!(key = %key, kind = ?kind, "Replaying LLM interaction");
+        Ok(interaction.response.clone())
+    }
+}
+
+#[async_trait]
+impl LLMProvider for ReplayProvider {
+    async fn complete(&self, messages: Vec<Message>) -> std::result::Result<String, AgentError> {
+        Ok(self.complete_with_usage(messages).await?.content)
+    }
+
+    async fn complete_with_usage(
+        &self,
+        messages: Vec<Message>,
+    ) -> std::result::Result<CompletionResponse, AgentError> {
+        let response = self.replay(CallKind::Complete, &messages, &[])?;
+        Ok(CompletionResponse::new(response.content, response.usage))
+    }
+
+    async fn stream(
+        &self,
+        messages: Vec<Message>,
+    ) -> std::result::Result<Pin<Box<dyn Stream<Item = StreamChunk> + Send>>, AgentError> {
+        let response = self.replay(CallKind::Stream, &messages, &[])?;
+        Ok(Box::pin(futures::stream::iter(vec![Ok(response.content)])))
+    }
+
+    async fn complete_with_tools(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<ToolDefinition>,
+    ) -> std::result::Result<ToolCompletion, AgentError> {
+        self.replay(CallKind::CompleteWithTools, &messages, &tools)
+    }
+
+    fn name(&self) -> &str {
+        "replay"
+    }
+
+    fn config(&self) -> &ProviderConfig {
+        &self.cassette.config
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::{ScriptedProvider, TokenUsage};
+
+    fn cassette_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("talk-{}-{}.json", name, uuid::Uuid::new_v4()))
+    }
+
+    #[tokio::test]
+    async fn test_recorded_requests_replay_offline() {
+        let path = cassette_path("replay");
+        let scripted = ScriptedProvider::new()
+            .with_reply("Hello!")
+            .with_reply("It is sunny")
+            .with_usage(TokenUsage::new(12, 3));
+        let recorder = RecordingProvider::new(Box::new(scripted), &path);
+
+        let conversation = vec![Message::system("Be brief"), Message::user("Hi")];
+        assert_eq!(
+            recorder.complete(conversation.clone()).await.unwrap(),
+            "Hello!"
+        );
+        let chunks: Vec<StreamChunk> = recorder
+            .stream(vec![Message::user("Weather?")])
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        assert_eq!(chunks.len(), 3);
+
+        let replay = ReplayProvider::from_file(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(replay.cassette().interactions.len(), 2);
+
+        // New message IDs, timestamps and extra whitespace still match
+        let conversation = vec![Message::system("Be  brief\n"), Message::user("Hi")];
+        let response = replay.complete_with_usage(conversation).await.unwrap();
+        assert_eq!(response.content, "Hello!");
+        assert_eq!(response.usage.total_tokens, 15);
+
+        let chunks: Vec<StreamChunk> = replay
+            .stream(vec![Message::user("Weather?")])
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        assert_eq!(chunks[0].as_deref().unwrap(), "It is sunny");
+    }
+
+    #[tokio::test]
+    async fn test_missing_request_fails_clearly() {
+        let cassette = Cassette::new("scripted", ProviderConfig::new("gpt-4o"));
+        let replay = ReplayProvider::new(cassette, "weather.json");
+
+        let error = replay
+            .complete(vec![Message::user("What's the weather?")])
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("What's the weather?"));
+        assert!(error.contains("weather.json"));
+    }
+
+    #[test]
+    fn test_request_key_depends_on_config_and_tools() {
+        let messages = vec![Message::user("Hi")];
+        let config = ProviderConfig::new("gpt-4o");
+        let (key, _) = request_key(CallKind::Complete, &messages, &[], &config);
+
+        let (same, _) = request_key(CallKind::Complete, &[Message::user(" Hi ")], &[], &config);
+        assert_eq!(key, same);
+
+        let other_model = ProviderConfig::new("gpt-4o-mini");
+        let (other, _) = request_key(CallKind::Complete, &messages, &[], &other_model);
+        assert_ne!(key, other);
+
+        let (as_stream, _) = request_key(CallKind::Stream, &messages, &[], &config);
+        assert_ne!(key, as_stream);
+    }
+}
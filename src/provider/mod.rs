@@ -5,15 +5,26 @@
 
 pub mod anthropic;
 pub mod openai;
+#[cfg(feature = "testing")]
+pub mod cassette;
+#[cfg(feature = "testing")]
+pub mod scripted;
 
 pub use anthropic::AnthropicProvider;
 pub use openai::OpenAIProvider;
+#[cfg(feature = "testing")]
+pub use cassette::{Cassette, RecordingProvider, ReplayProvider};
+#[cfg(feature = "testing")]
+pub use scripted::{ScriptedProvider, ScriptedReply};
 
 use crate::context::{Message, MessageRole};
 use crate::error::AgentError;
+use crate::tool::{ToolCall, ToolDefinition};
 use async_trait::async_trait;
 use futures::Stream;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ops::AddAssign;
 use std::pin::Pin;
 
 /// Configuration for an LLM provider
@@ -89,6 +100,116 @@ impl ProviderConfig {
 /// Type alias for streaming response chunks
 pub type StreamChunk = std::result::Result<String, AgentError>;
 
+/// Tokens consumed by one or more LLM calls
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    /// Input tokens
+    pub prompt_tokens: u32,
+    /// Output tokens
+    pub completion_tokens: u32,
+    /// Total tokens
+    pub total_tokens: u32,
+}
+
+impl TokenUsage {
+    /// Create usage from input and output token counts
+    pub fn new(prompt_tokens: u32, completion_tokens: u32) -> Self {
+        Self {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens + completion_tokens,
+        }
+    }
+
+    /// Whether no tokens were used
+    pub fn is_empty(&self) -> bool {
+        self.total_tokens == 0
+    }
+
+    /// Cost of this usage at the given price
+    pub fn cost(&self, price: &ModelPrice) -> f64 {
+        (self.prompt_tokens as f64 * price.prompt_per_million
+            + self.completion_tokens as f64 * price.completion_per_million)
+            / 1_000_000.0
+    }
+}
+
+impl AddAssign for TokenUsage {
+    fn add_assign(&mut self, other: Self) {
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.total_tokens += other.total_tokens;
+    }
+}
+
+/// Price of a model's tokens, per million
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModelPrice {
+    pub prompt_per_million: f64,
+    pub completion_per_million: f64,
+}
+
+impl ModelPrice {
+    pub fn new(prompt_per_million: f64, completion_per_million: f64) -> Self {
+        Self {
+            prompt_per_million,
+            completion_per_million,
+        }
+    }
+}
+
+/// Look up a model's price in a price table
+///
+/// An exact model name wins; otherwise the longest key the model name starts
+/// with is used, so `gpt-4o` covers `gpt-4o-2024-08-06`.
+pub fn model_price<'a>(
+    prices: &'a HashMap<String, ModelPrice>,
+    model: &str,
+) -> Option<&'a ModelPrice> {
+    prices.get(model).or_else(|| {
+        prices
+            .iter()
+            .filter(|(prefix, _)| model.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, price)| price)
+    })
+}
+
+/// Response from a completion, with the tokens it used
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CompletionResponse {
+    /// Generated message content
+    pub content: String,
+    /// Token usage, empty when the provider does not report it
+    #[serde(default)]
+    pub usage: TokenUsage,
+    /// Provider-specific metadata
+    #[serde(default)]
+    pub metadata: HashMap<String, serde_json::Value>,
+}
+
+impl CompletionResponse {
+    pub fn new(content: impl Into<String>, usage: TokenUsage) -> Self {
+        Self {
+            content: content.into(),
+            usage,
+            metadata: HashMap::new(),
+        }
+    }
+}
+
+/// Response from a tool-aware completion
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ToolCompletion {
+    /// Text content of the response (may be empty when tools are called)
+    pub content: String,
+    /// Tool calls requested by the model
+    pub tool_calls: Vec<ToolCall>,
+    /// Token usage, empty when the provider does not report it
+    #[serde(default)]
+    pub usage: TokenUsage,
+}
+
 /// Trait for LLM provider implementations
 ///
 /// This trait defines the interface that all LLM providers must implement
@@ -106,6 +227,29 @@ pub trait LLMProvider: Send + Sync {
     /// The generated response text or an error
     async fn complete(&self, messages: Vec<Message>) -> std::result::Result<String, AgentError>;
 
+    /// Generate a complete response along with the tokens it used
+    ///
+    /// The agent calls this instead of [`LLMProvider::complete`] to account for
+    /// token usage. The default implementation calls `complete` and reports no
+    /// usage.
+    ///
+    /// # Arguments
+    ///
+    /// * `messages` - Vector of messages representing the conversation history
+    ///
+    /// # Returns
+    ///
+    /// The generated response and its token usage, or an error
+    async fn complete_with_usage(
+        &self,
+        messages: Vec<Message>,
+    ) -> std::result::Result<CompletionResponse, AgentError> {
+        Ok(CompletionResponse::new(
+            self.complete(messages).await?,
+            TokenUsage::default(),
+        ))
+    }
+
     /// Generate a streaming response for the given messages
     ///
     /// # Arguments
@@ -120,6 +264,34 @@ pub trait LLMProvider: Send + Sync {
         messages: Vec<Message>,
     ) -> std::result::Result<Pin<Box<dyn Stream<Item = StreamChunk> + Send>>, AgentError>;
 
+    /// Generate a response that may request tool calls
+    ///
+    /// Providers with native function calling send `tools` along with the
+    /// request and return any tool calls the model makes. The default
+    /// implementation ignores the tools and falls back to [`LLMProvider::complete`].
+    ///
+    /// # Arguments
+    ///
+    /// * `messages` - Vector of messages representing the conversation history
+    /// * `tools` - Definitions of the tools the model may call
+    ///
+    /// # Returns
+    ///
+    /// The response text and requested tool calls, or an error
+    async fn complete_with_tools(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<ToolDefinition>,
+    ) -> std::result::Result<ToolCompletion, AgentError> {
+        let _ = tools;
+        let response = self.complete_with_usage(messages).await?;
+        Ok(ToolCompletion {
+            content: response.content,
+            tool_calls: Vec::new(),
+            usage: response.usage,
+        })
+    }
+
     /// Get the name of the provider
     ///
     /// # Returns
@@ -133,6 +305,44 @@ pub trait LLMProvider: Send + Sync {
     ///
     /// A reference to the provider configuration
     fn config(&self) -> &ProviderConfig;
+
+    /// Get the model's context window in tokens (prompt plus response)
+    ///
+    /// The default implementation looks the configured model up with
+    /// [`model_context_window`]. Providers for other models can override it.
+    ///
+    /// # Returns
+    ///
+    /// The context window size, or None if it is unknown
+    fn context_window(&self) -> Option<usize> {
+        model_context_window(&self.config().model)
+    }
+}
+
+/// Known context window sizes, matched by model name prefix
+const MODEL_CONTEXT_WINDOWS: &[(&str, usize)] = &[
+    ("gpt-5", 400_000),
+    ("gpt-4.1", 1_047_576),
+    ("gpt-4o", 128_000),
+    ("gpt-4-turbo", 128_000),
+    ("gpt-4-32k", 32_768),
+    ("gpt-4", 8_192),
+    ("gpt-3.5-turbo", 16_385),
+    ("o1", 200_000),
+    ("o3", 200_000),
+    ("o4", 200_000),
+    ("claude", 200_000),
+];
+
+/// Look up the context window of a well-known model
+///
+/// Longer prefixes are listed first, so `gpt-4o-mini` resolves to the
+/// `gpt-4o` entry rather than `gpt-4`.
+pub fn model_context_window(model: &str) -> Option<usize> {
+    MODEL_CONTEXT_WINDOWS
+        .iter()
+        .find(|(prefix, _)| model.starts_with(prefix))
+        .map(|(_, window)| *window)
 }
 
 /// Convert Talk messages to the format expected by the provider
@@ -158,6 +368,36 @@ mod tests {
         assert!(config.max_tokens.is_none());
     }
 
+    #[test]
+    fn test_model_context_window() {
+        assert_eq!(model_context_window("gpt-4o-mini"), Some(128_000));
+        assert_eq!(model_context_window("gpt-4"), Some(8_192));
+        assert_eq!(
+            model_context_window("claude-3-5-sonnet-20241022"),
+            Some(200_000)
+        );
+        assert_eq!(model_context_window("my-local-model"), None);
+    }
+
+    #[test]
+    fn test_token_usage_and_prices() {
+        let mut usage = TokenUsage::new(1_000, 500);
+        usage += TokenUsage::new(1_000, 500);
+        assert_eq!(usage, TokenUsage::new(2_000, 1_000));
+        assert_eq!(usage.total_tokens, 3_000);
+
+        let mut prices = HashMap::new();
+        prices.insert("gpt-4o".to_string(), ModelPrice::new(2.5, 10.0));
+        prices.insert("gpt-4o-mini".to_string(), ModelPrice::new(0.15, 0.6));
+        let price = model_price(&prices, "gpt-4o-2024-08-06").unwrap();
+        assert!((usage.cost(price) - 0.015).abs() < 1e-12);
+        assert_eq!(
+            model_price(&prices, "gpt-4o-mini-2024-07-18"),
+            Some(&ModelPrice::new(0.15, 0.6))
+        );
+        assert!(model_price(&prices, "claude-3-5-sonnet").is_none());
+    }
+
     #[test]
     fn test_provider_config_with_temperature() {
         let config = ProviderConfig::new("gpt-5").with_temperature(0.5);
@@ -4,13 +4,19 @@
 
 use crate::context::{Message, MessageRole};
 use crate::error::AgentError;
-use crate::provider::{LLMProvider, ProviderConfig, StreamChunk};
+use crate::provider::{
+    CompletionResponse, LLMProvider, ProviderConfig, StreamChunk, TokenUsage, ToolCompletion,
+};
+use crate::tool::{ToolCall, ToolDefinition};
 use async_openai::{
     config::OpenAIConfig,
     types::{
-        ChatCompletionRequestAssistantMessage, ChatCompletionRequestMessage,
-        ChatCompletionRequestSystemMessage, ChatCompletionRequestUserMessage,
-        CreateChatCompletionRequestArgs,
+        ChatCompletionMessageToolCall, ChatCompletionRequestAssistantMessage,
+        ChatCompletionRequestMessage, ChatCompletionRequestSystemMessage,
+        ChatCompletionRequestToolMessage, ChatCompletionRequestToolMessageContent,
+        ChatCompletionRequestUserMessage, ChatCompletionTool, ChatCompletionToolType,
+        CompletionUsage, CreateChatCompletionRequest, CreateChatCompletionRequestArgs,
+        FunctionCall, FunctionObject,
     },
     Client,
 };
@@ -105,20 +111,45 @@ impl OpenAIProvider {
                         name: None,
                     })
                 }
-                MessageRole::Assistant => {
-                    ChatCompletionRequestMessage::Assistant(ChatCompletionRequestAssistantMessage {
-                        content: Some(
+                MessageRole::Assistant | MessageRole::Operator => {
+                    let tool_calls: Vec<ChatCompletionMessageToolCall> = m
+                        .tool_calls()
+                        .into_iter()
+                        .map(|call| ChatCompletionMessageToolCall {
+                            id: call.id,
+                            r#type: ChatCompletionToolType::Function,
+                            function: FunctionCall {
+                                name: call.name,
+                                arguments: serde_json::to_string(&call.arguments)
+                                    .unwrap_or_default(),
+                            },
+                        })
+                        .collect();
+                    let content = if m.content.is_empty() && !tool_calls.is_empty() {
+                        None
+                    } else {
+                        Some(
                             async_openai::types::ChatCompletionRequestAssistantMessageContent::Text(
                                 m.content,
                             ),
-                        ),
+                        )
+                    };
+
+                    ChatCompletionRequestMessage::Assistant(ChatCompletionRequestAssistantMessage {
+                        content,
                         name: None,
-                        tool_calls: None,
+                        tool_calls: (!tool_calls.is_empty()).then_some(tool_calls),
                         refusal: None,
                         #[allow(deprecated)]
                         function_call: None,
                     })
                 }
+                MessageRole::Tool if m.tool_call_id().is_some() => {
+                    ChatCompletionRequestMessage::Tool(ChatCompletionRequestToolMessage {
+                        tool_call_id: m.tool_call_id().unwrap_or_default().to_string(),
+                        content: ChatCompletionRequestToolMessageContent::Text(m.content),
+                    })
+                }
                 MessageRole::Tool => {
                     // Convert tool messages to system messages for now
                     ChatCompletionRequestMessage::System(ChatCompletionRequestSystemMessage {
@@ -132,17 +163,13 @@ impl OpenAIProvider {
             })
             .collect()
     }
-}
-
-#[async_trait]
-impl LLMProvider for OpenAIProvider {
-    async fn complete(&self, messages: Vec<Message>) -> std::result::Result<String, AgentError> {
-        info!(
-            model = %self.config.model,
-            message_count = messages.len(),
-            "Requesting OpenAI completion"
-        );
 
+    /// Build a chat completion request with the configured sampling parameters
+    fn build_request(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<ToolDefinition>,
+    ) -> Result<CreateChatCompletionRequest, AgentError> {
         let openai_messages = self.convert_messages(messages);
 
         let mut request_builder = CreateChatCompletionRequestArgs::default();
@@ -151,6 +178,23 @@ impl LLMProvider for OpenAIProvider {
             .messages(openai_messages)
             .temperature(self.config.temperature);
 
+        if !tools.is_empty() {
+            request_builder.tools(
+                tools
+                    .into_iter()
+                    .map(|tool| ChatCompletionTool {
+                        r#type: ChatCompletionToolType::Function,
+                        function: FunctionObject {
+                            name: tool.name,
+                            description: Some(tool.description),
+                            parameters: Some(tool.parameters),
+                            strict: None,
+                        },
+                    })
+                    .collect::<Vec<_>>(),
+            );
+        }
+
         if let Some(max_tokens) = self.config.max_tokens {
             request_builder.max_tokens(max_tokens);
         }
@@ -167,9 +211,39 @@ impl LLMProvider for OpenAIProvider {
             request_builder.presence_penalty(presence_penalty);
         }
 
-        let request = request_builder
+        request_builder
             .build()
-            .map_err(|e| AgentError::ProviderError(format!("Failed to build request: {}", e)))?;
+            .map_err(|e| AgentError::ProviderError(format!("Failed to build request: {}", e)))
+    }
+}
+
+fn token_usage(usage: Option<CompletionUsage>) -> TokenUsage {
+    usage
+        .map(|usage| TokenUsage {
+            prompt_tokens: usage.prompt_tokens,
+            completion_tokens: usage.completion_tokens,
+            total_tokens: usage.total_tokens,
+        })
+        .unwrap_or_default()
+}
+
+#[async_trait]
+impl LLMProvider for OpenAIProvider {
+    async fn complete(&self, messages: Vec<Message>) -> std::result::Result<String, AgentError> {
+        Ok(self.complete_with_usage(messages).await?.content)
+    }
+
+    async fn complete_with_usage(
+        &self,
+        messages: Vec<Message>,
+    ) -> std::result::Result<CompletionResponse, AgentError> {
+        info!(
+            model = %self.config.model,
+            message_count = messages.len(),
+            "Requesting OpenAI completion"
+        );
+
+        let request = self.build_request(messages, Vec::new())?;
 
         trace!("Sending request to OpenAI");
 
@@ -187,12 +261,77 @@ impl LLMProvider for OpenAIProvider {
                 AgentError::ProviderError("No content in OpenAI response".to_string())
             })?;
 
+        let usage = token_usage(response.usage);
         debug!(
             response_length = message.len(),
+            total_tokens = usage.total_tokens,
             "OpenAI completion successful"
         );
 
-        Ok(message)
+        Ok(CompletionResponse::new(message, usage))
+    }
+
+    async fn complete_with_tools(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<ToolDefinition>,
+    ) -> std::result::Result<ToolCompletion, AgentError> {
+        info!(
+            model = %self.config.model,
+            message_count = messages.len(),
+            tool_count = tools.len(),
+            "Requesting OpenAI completion with tools"
+        );
+
+        let request = self.build_request(messages, tools)?;
+
+        trace!("Sending request to OpenAI");
+
+        let response = self.client.chat().create(request).await.map_err(|e| {
+            warn!(error = %e, "OpenAI API error");
+            AgentError::ProviderError(format!("OpenAI API error: {}", e))
+        })?;
+
+        let usage = token_usage(response.usage);
+        let message = response
+            .choices
+            .into_iter()
+            .next()
+            .map(|choice| choice.message)
+            .ok_or_else(|| {
+                warn!("No choices in OpenAI response");
+                AgentError::ProviderError("No choices in OpenAI response".to_string())
+            })?;
+
+        let tool_calls = message
+            .tool_calls
+            .unwrap_or_default()
+            .into_iter()
+            .map(|call| {
+                let arguments = serde_json::from_str(&call.function.arguments).map_err(|e| {
+                    AgentError::ProviderError(format!(
+                        "Invalid arguments for tool call '{}': {}",
+                        call.function.name, e
+                    ))
+                })?;
+                Ok(ToolCall {
+                    id: call.id,
+                    name: call.function.name,
+                    arguments,
+                })
+            })
+            .collect::<Result<Vec<_>, AgentError>>()?;
+
+        debug!(
+            tool_call_count = tool_calls.len(),
+            "OpenAI completion with tools successful"
+        );
+
+        Ok(ToolCompletion {
+            content: message.content.unwrap_or_default(),
+            tool_calls,
+            usage,
+        })
     }
 
     async fn stream(
@@ -305,4 +444,52 @@ mod tests {
         let converted = provider.convert_messages(messages);
         assert_eq!(converted.len(), 3);
     }
+
+    #[test]
+    fn test_tool_message_conversion() {
+        let provider = OpenAIProvider::new("test-api-key", "gpt-4");
+        let call = ToolCall {
+            id: "call_1".to_string(),
+            name: "weather".to_string(),
+            arguments: Default::default(),
+        };
+        let messages = vec![
+            Message::assistant_tool_calls("", &[call]),
+            Message::tool_result("call_1", "sunny"),
+        ];
+
+        let converted = provider.convert_messages(messages);
+        match &converted[0] {
+            ChatCompletionRequestMessage::Assistant(message) => {
+                let tool_calls = message.tool_calls.as_ref().unwrap();
+                assert_eq!(tool_calls[0].id, "call_1");
+                assert_eq!(tool_calls[0].function.name, "weather");
+                assert!(message.content.is_none());
+            }
+            other => panic!("expected assistant message, got {:?}", other),
+        }
+        match &converted[1] {
+            ChatCompletionRequestMessage::Tool(message) => {
+                assert_eq!(message.tool_call_id, "call_1");
+            }
+            other => panic!("expected tool message, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_build_request_with_tools() {
+        let provider = OpenAIProvider::new("test-api-key", "gpt-4");
+        let tools = vec![ToolDefinition {
+            name: "weather".to_string(),
+            description: "Get the weather".to_string(),
+            parameters: serde_json::json!({"type": "object", "properties": {}}),
+        }];
+
+        let request = provider
+            .build_request(vec![Message::user("Hi")], tools)
+            .unwrap();
+        let tools = request.tools.unwrap();
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].function.name, "weather");
+    }
 }
@@ -0,0 +1,320 @@
+//! Scripted LLM provider for tests
+//!
+//! [`ScriptedProvider`] answers with canned responses instead of calling an
+//! LLM, so guidelines and journeys can be tested deterministically in CI.
+//! Responses are either queued, and used once in order, or chosen by matching
+//! the last user message. Every message list it receives is recorded.
+//!
+//! Available with the `testing` feature.
+
+use crate::context::{Message, MessageRole};
+use crate::error::AgentError;
+use crate::provider::{CompletionResponse, LLMProvider, ProviderConfig, StreamChunk, TokenUsage};
+use async_trait::async_trait;
+use futures::Stream;
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// A canned provider response
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScriptedReply {
+    /// Answer with this text
+    Text(String),
+    /// Fail with an `AgentError::LLMProvider` carrying this message
+    Error(String),
+}
+
+impl ScriptedReply {
+    fn into_result(self) -> std::result::Result<String, AgentError> {
+        match self {
+            ScriptedReply::Text(text) => Ok(text),
+            ScriptedReply::Error(message) => Err(AgentError::LLMProvider(message.into())),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Script {
+    queue: VecDeque<ScriptedReply>,
+    rules: Vec<(String, ScriptedReply)>,
+    default: Option<ScriptedReply>,
+    requests: Vec<Vec<Message>>,
+}
+
+/// LLM provider that answers from a script
+///
+/// For each request, the provider uses the next queued reply if there is one,
+/// then the first rule whose pattern the last user message contains
+/// (case-insensitive), then the default reply. A request with no reply fails.
+///
+/// Clones share the script and the recorded requests, so keep a clone to
+/// inspect what the agent sent after boxing the provider:
+///
+/// ```rust
+/// use talk::{Agent, ScriptedProvider};
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let provider = ScriptedProvider::new()
+///     .with_rule("refund", "Refunds take 5 days.")
+///     .with_default("How can I help?");
+/// let agent = Agent::builder()
+///     .name("Support")
+///     .provider(Box::new(provider.clone()))
+///     .build()?;
+///
+/// let session_id = agent.create_session().await?;
+/// let response = agent
+///     .process_message(session_id, "Can I get a refund?".to_string())
+///     .await?;
+///
+/// assert_eq!(response.message, "Refunds take 5 days.");
+/// assert_eq!(provider.request_count(), 1);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ScriptedProvider {
+    config: ProviderConfig,
+    script: Arc<Mutex<Script>>,
+    latency: Option<Duration>,
+    usage: TokenUsage,
+}
+
+impl ScriptedProvider {
+    /// Create a provider with an empty script
+    pub fn new() -> Self {
+        Self {
+            config: ProviderConfig::new("scripted"),
+            script: Arc::new(Mutex::new(Script::default())),
+            latency: None,
+            usage: TokenUsage::default(),
+        }
+    }
+
+    /// Queue a text reply, used once after the replies queued before it
+    pub fn with_reply(self, text: impl Into<String>) -> Self {
+        self.push(ScriptedReply::Text(text.into()));
+        self
+    }
+
+    /// Queue an error, returned once in place of a reply
+    pub fn with_error(self, message: impl Into<String>) -> Self {
+        self.push(ScriptedReply::Error(message.into()));
+        self
+    }
+
+    /// Answer with `text` whenever the last user message contains `pattern`
+    pub fn with_rule(self, pattern: impl Into<String>, text: impl Into<String>) -> Self {
+        self.with_rule_reply(pattern, ScriptedReply::Text(text.into()))
+    }
+
+    /// Use `reply` whenever the last user message contains `pattern`
+    pub fn with_rule_reply(self, pattern: impl Into<String>, reply: ScriptedReply) -> Self {
+        self.lock()
+            .rules
+            .push((pattern.into().to_lowercase(), reply));
+        self
+    }
+
+    /// Answer with `text` when nothing is queued and no rule matches
+    pub fn with_default(self, text: impl Into<String>) -> Self {
+        self.lock().default = Some(ScriptedReply::Text(text.into()));
+        self
+    }
+
+    /// Wait this long before answering each request
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = Some(latency);
+        self
+    }
+
+    /// Report this token usage for each completion
+    pub fn with_usage(mut self, usage: TokenUsage) -> Self {
+        self.usage = usage;
+        self
+    }
+
+    /// Report a different model name in the provider config
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.config.model = model.into();
+        self
+    }
+
+    /// Queue a reply, e.g. on a clone of a provider the agent already uses
+    pub fn push(&self, reply: ScriptedReply) {
+        self.lock().queue.push_back(reply);
+    }
+
+    /// Every message list received so far, oldest first
+    pub fn requests(&self) -> Vec<Vec<Message>> {
+        self.lock().requests.clone()
+    }
+
+    /// The most recent message list received
+    pub fn last_request(&self) -> Option<Vec<Message>> {
+        self.lock().requests.last().cloned()
+    }
+
+    /// Number of requests received so far
+    pub fn request_count(&self) -> usize {
+        self.lock().requests.len()
+    }
+
+    /// Number of queued replies not used yet
+    pub fn remaining(&self) -> usize {
+        self.lock().queue.len()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Script> {
+        self.script.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Record the request and pick its reply
+    async fn answer(&self, messages: Vec<Message>) -> std::result::Result<String, AgentError> {
+        if let Some(latency) = self.latency {
+            tokio::time::sleep(latency).await;
+        }
+
+        let mut script = self.lock();
+        let last_user = messages
+            .iter()
+            .rev()
+            .find(|m| m.role == MessageRole::User)
+            .map(|m| m.content.to_lowercase())
+            .unwrap_or_default();
+        script.requests.push(messages);
+
+        let reply = match script.queue.pop_front() {
+            Some(reply) => Some(reply),
+            None => script
+                .rules
+                .iter()
+                .find(|(pattern, _)| last_user.contains(pattern.as_str()))
+                .map(|(_, reply)| reply.clone())
+                .or_else(|| script.default.clone()),
+        };
+        reply
+            .unwrap_or_else(|| {
+                ScriptedReply::Error(format!(
+                    "ScriptedProvider has no reply for request {}",
+                    script.requests.len()
+                ))
+            })
+            .into_result()
+    }
+}
+
+impl Default for ScriptedProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl LLMProvider for ScriptedProvider {
+    async fn complete(&self, messages: Vec<Message>) -> std::result::Result<String, AgentError> {
+        self.answer(messages).await
+    }
+
+    async fn complete_with_usage(
+        &self,
+        messages: Vec<Message>,
+    ) -> std::result::Result<CompletionResponse, AgentError> {
+        let content = self.answer(messages).await?;
+        Ok(CompletionResponse::new(content, self.usage))
+    }
+
+    /// Stream the reply word by word
+    async fn stream(
+        &self,
+        messages: Vec<Message>,
+    ) -> std::result::Result<Pin<Box<dyn Stream<Item = StreamChunk> + Send>>, AgentError> {
+        let text = self.answer(messages).await?;
+        let chunks: Vec<StreamChunk> = text
+            .split_inclusive(' ')
+            .map(|chunk| Ok(chunk.to_string()))
+            .collect();
+        Ok(Box::pin(futures::stream::iter(chunks)))
+    }
+
+    fn name(&self) -> &str {
+        "scripted"
+    }
+
+    fn config(&self) -> &ProviderConfig {
+        &self.config
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    #[tokio::test]
+    async fn test_queue_then_rules_then_default() {
+        let provider = ScriptedProvider::new()
+            .with_reply("first")
+            .with_error("rate limited")
+            .with_rule("refund", "Refunds take 5 days.")
+            .with_default("fallback");
+
+        let ask = |text: &str| vec![Message::system("prompt"), Message::user(text)];
+        assert_eq!(provider.complete(ask("refund")).await.unwrap(), "first");
+        assert!(matches!(
+            provider.complete(ask("refund")).await,
+            Err(AgentError::LLMProvider(_))
+        ));
+        assert_eq!(
+            provider.complete(ask("I want a REFUND")).await.unwrap(),
+            "Refunds take 5 days."
+        );
+        assert_eq!(provider.complete(ask("hello")).await.unwrap(), "fallback");
+
+        assert_eq!(provider.request_count(), 4);
+        assert_eq!(provider.last_request().unwrap()[1].content, "hello");
+        assert_eq!(provider.remaining(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_clones_share_script_and_requests() {
+        let provider = ScriptedProvider::new();
+        let boxed: Box<dyn LLMProvider> = Box::new(provider.clone());
+
+        assert!(boxed.complete(vec![Message::user("hi")]).await.is_err());
+        provider.push(ScriptedReply::Text("later".to_string()));
+        assert_eq!(
+            boxed.complete(vec![Message::user("hi")]).await.unwrap(),
+            "later"
+        );
+        assert_eq!(provider.request_count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_stream_usage_and_latency() {
+        let provider = ScriptedProvider::new()
+            .with_default("It is sunny")
+            .with_usage(TokenUsage::new(10, 3))
+            .with_latency(Duration::from_millis(20));
+
+        let started = std::time::Instant::now();
+        let chunks: Vec<String> = provider
+            .stream(vec![Message::user("weather?")])
+            .await
+            .unwrap()
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+        assert_eq!(chunks, vec!["It ", "is ", "sunny"]);
+        assert!(started.elapsed() >= Duration::from_millis(20));
+
+        let response = provider
+            .complete_with_usage(vec![Message::user("weather?")])
+            .await
+            .unwrap();
+        assert_eq!(response.usage.total_tokens, 13);
+    }
+}
@@ -0,0 +1,381 @@
+//! Multi-agent routing
+//!
+//! An [`AgentRouter`] owns several specialised [`Agent`]s (e.g. billing,
+//! technical and sales) that share one [`SessionStore`]. Each session is routed
+//! to one agent on its first message:
+//!
+//! 1. the agent with the best guideline match (highest priority, then relevance),
+//! 2. otherwise the agent picked by the LLM classifier, if one is configured,
+//! 3. otherwise the default agent.
+//!
+//! That agent keeps handling the session until the conversation is handed off,
+//! either by a guideline whose action names another agent
+//! ([`GuidelineAction::with_handoff`](crate::GuidelineAction::with_handoff)) or
+//! explicitly with [`AgentRouter::handoff`]. Agents share the session, so the
+//! new agent sees the whole conversation. The active agent and the agent that
+//! handled each turn are recorded in the session metadata under
+//! [`ACTIVE_AGENT_KEY`] and [`AGENT_TURNS_KEY`].
+
+use crate::agent::{Agent, AgentResponse, SessionLocks};
+use crate::context::{Context, Message};
+use crate::error::{AgentError, Result};
+use crate::provider::LLMProvider;
+use crate::session::Session;
+use crate::storage::SessionStore;
+use crate::types::{AgentId, SessionId};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::{debug, info, warn};
+
+/// Session metadata key holding the name of the agent handling the session
+pub const ACTIVE_AGENT_KEY: &str = "active_agent";
+
+/// Session metadata key holding the names of the agents that handled each turn
+pub const AGENT_TURNS_KEY: &str = "agent_turns";
+
+/// Response from the agent a message was routed to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoutedResponse {
+    /// Name of the agent that handled the message
+    pub agent: String,
+    pub response: AgentResponse,
+}
+
+/// Routes sessions between several agents sharing one session store
+pub struct AgentRouter {
+    id: AgentId,
+    agents: Vec<Agent>,
+    default_agent: usize,
+    classifier: Option<Box<dyn LLMProvider>>,
+    session_store: Arc<dyn SessionStore>,
+    session_locks: SessionLocks,
+}
+
+impl AgentRouter {
+    /// Create a new router builder
+    pub fn builder() -> AgentRouterBuilder {
+        AgentRouterBuilder::new()
+    }
+
+    /// ID recorded as the `agent_id` of sessions created by the router
+    pub fn id(&self) -> AgentId {
+        self.id
+    }
+
+    /// Get an agent by name
+    pub fn agent(&self, name: &str) -> Option<&Agent> {
+        self.agents.iter().find(|agent| agent.name() == name)
+    }
+
+    /// All routed agents, in the order they were added
+    pub fn agents(&self) -> impl Iterator<Item = &Agent> {
+        self.agents.iter()
+    }
+
+    /// Create a new conversation session
+    pub async fn create_session(&self) -> Result<SessionId> {
+        let config = self.agents[self.default_agent].config();
+        let mut session = Session::with_context(
+            self.id,
+            Context::with_max_messages(config.max_context_messages),
+        );
+        if let Some(idle_timeout) = config.session_idle_timeout {
+            session.extend_expiration(idle_timeout);
+        }
+        let session_id = session.id;
+
+        self.session_store
+            .create(session)
+            .await
+            .map_err(AgentError::Storage)?;
+
+        Ok(session_id)
+    }
+
+    /// Get a session by ID
+    pub async fn get_session(&self, session_id: &SessionId) -> Result<Option<Session>> {
+        self.session_store
+            .get(session_id)
+            .await
+            .map_err(AgentError::Storage)
+    }
+
+    /// Name of the agent handling the session, if it has been routed yet
+    pub async fn active_agent(&self, session_id: &SessionId) -> Result<Option<String>> {
+        let session = self.load_session(session_id).await?;
+        Ok(active_agent(&session).map(str::to_string))
+    }
+
+    /// Process a message with the session's agent, routing the session first if needed
+    ///
+    /// When the applied guideline hands off to another agent, its response is
+    /// returned and the next message goes to that agent. Handoffs to unknown
+    /// agents are ignored.
+    pub async fn process_message(
+        &self,
+        session_id: SessionId,
+        message: String,
+    ) -> Result<RoutedResponse> {
+        let _guard = self.session_locks.lock(session_id).await;
+        let session = self.load_session(&session_id).await?;
+
+        let index = match active_agent(&session).and_then(|name| self.index_of(name)) {
+            Some(index) => index,
+            None => self.route(&message, &session.context).await?,
+        };
+        let agent = &self.agents[index];
+        debug!(
+            session_id = %session_id,
+            agent = agent.name(),
+            "Routing message"
+        );
+
+        let response = agent.process_message(session_id, message).await?;
+
+        let mut session = self.load_session(&session_id).await?;
+        record_turn(&mut session, agent.name());
+        let mut active = agent.name();
+        if let Some(target) = &response.handoff {
+            match self.agent(target) {
+                Some(target) => {
+                    info!(
+                        session_id = %session_id,
+                        from = agent.name(),
+                        to = target.name(),
+                        "Handing off conversation"
+                    );
+                    active = target.name();
+                }
+                None => warn!(
+                    session_id = %session_id,
+                    target = %target,
+                    "Ignoring handoff to unknown agent"
+                ),
+            }
+        }
+        self.set_active_agent(&mut session, active);
+        session.touch();
+        self.session_store
+            .update(&session_id, session)
+            .await
+            .map_err(AgentError::Storage)?;
+
+        Ok(RoutedResponse {
+            agent: agent.name().to_string(),
+            response,
+        })
+    }
+
+    /// Hand the session off to another agent
+    pub async fn handoff(&self, session_id: &SessionId, agent: &str) -> Result<()> {
+        let agent = self
+            .agent(agent)
+            .ok_or_else(|| AgentError::InvalidInput(format!("Unknown agent '{}'", agent)))?;
+
+        let _guard = self.session_locks.lock(*session_id).await;
+        let mut session = self.load_session(session_id).await?;
+        info!(
+            session_id = %session_id,
+            from = ?active_agent(&session),
+            to = agent.name(),
+            "Handing off conversation"
+        );
+        self.set_active_agent(&mut session, agent.name());
+        session.touch();
+
+        self.session_store
+            .update(session_id, session)
+            .await
+            .map_err(AgentError::Storage)
+    }
+
+    async fn load_session(&self, session_id: &SessionId) -> Result<Session> {
+        self.session_store
+            .get(session_id)
+            .await
+            .map_err(AgentError::Storage)?
+            .ok_or_else(|| AgentError::SessionNotFound(*session_id))
+    }
+
+    fn index_of(&self, name: &str) -> Option<usize> {
+        self.agents.iter().position(|agent| agent.name() == name)
+    }
+
+    /// Pick the agent for a session's first message
+    async fn route(&self, message: &str, context: &Context) -> Result<usize> {
+        let mut best: Option<(usize, i32, f32)> = None;
+        for (index, agent) in self.agents.iter().enumerate() {
+            if let Some((guideline, matched)) = agent.best_match(message, context).await? {
+                let rank = (guideline.priority, matched.relevance_score);
+                if best.is_none_or(|(_, priority, relevance)| rank > (priority, relevance)) {
+                    best = Some((index, rank.0, rank.1));
+                }
+            }
+        }
+        if let Some((index, _, _)) = best {
+            return Ok(index);
+        }
+
+        if let Some(classifier) = &self.classifier {
+            match self.classify(classifier.as_ref(), message).await {
+                Ok(Some(index)) => return Ok(index),
+                Ok(None) => warn!("Classifier did not name a known agent, using the default"),
+                Err(e) => warn!(error = %e, "Classifier failed, using the default agent"),
+            }
+        }
+
+        Ok(self.default_agent)
+    }
+
+    /// Ask the LLM which agent should handle the message
+    async fn classify(&self, classifier: &dyn LLMProvider, message: &str) -> Result<Option<usize>> {
+        let agents: Vec<String> = self
+            .agents
+            .iter()
+            .map(|agent| match agent.description() {
+                Some(description) => format!("- {}: {}", agent.name(), description),
+                None => format!("- {}", agent.name()),
+            })
+            .collect();
+        let messages = vec![
+            Message::system(format!(
+                "Route the user's message to the agent best suited to handle it.\n\nAgents:\n{}\n\nReply with only the agent's name.",
+                agents.join("\n")
+            )),
+            Message::user(message.to_string()),
+        ];
+
+        let output = classifier.complete(messages).await?;
+        let answer = output
+            .trim()
+            .trim_matches(|c: char| c == '"' || c == '\'' || c == '.')
+            .to_lowercase();
+
+        Ok(self
+            .agents
+            .iter()
+            .position(|agent| agent.name().to_lowercase() == answer)
+            .or_else(|| {
+                self.agents
+                    .iter()
+                    .position(|agent| answer.contains(&agent.name().to_lowercase()))
+            }))
+    }
+
+    /// Make `agent` the session's active agent
+    ///
+    /// A journey in progress belongs to the previous agent, so it is dropped.
+    fn set_active_agent(&self, session: &mut Session, agent: &str) {
+        if active_agent(session).is_some_and(|current| current != agent) {
+            session.journey_state = None;
+        }
+        session
+            .metadata
+            .insert(ACTIVE_AGENT_KEY.to_string(), serde_json::json!(agent));
+    }
+}
+
+fn active_agent(session: &Session) -> Option<&str> {
+    session
+        .metadata
+        .get(ACTIVE_AGENT_KEY)
+        .and_then(|value| value.as_str())
+}
+
+fn record_turn(session: &mut Session, agent: &str) {
+    let turns = session
+        .metadata
+        .entry(AGENT_TURNS_KEY.to_string())
+        .or_insert_with(|| serde_json::json!([]));
+    if let Some(turns) = turns.as_array_mut() {
+        turns.push(serde_json::json!(agent));
+    }
+}
+
+/// Builder for creating an agent router
+///
+/// Every agent must be built with the same session store.
+pub struct AgentRouterBuilder {
+    agents: Vec<Agent>,
+    default_agent: Option<String>,
+    classifier: Option<Box<dyn LLMProvider>>,
+}
+
+impl AgentRouterBuilder {
+    pub fn new() -> Self {
+        Self {
+            agents: Vec::new(),
+            default_agent: None,
+            classifier: None,
+        }
+    }
+
+    /// Add an agent, routed to by its name
+    pub fn agent(mut self, agent: Agent) -> Self {
+        self.agents.push(agent);
+        self
+    }
+
+    /// Agent used when nothing else routes the session (default: the first agent)
+    pub fn default_agent(mut self, name: impl Into<String>) -> Self {
+        self.default_agent = Some(name.into());
+        self
+    }
+
+    /// Ask this LLM to pick an agent when no agent's guidelines match
+    pub fn classifier(mut self, provider: Box<dyn LLMProvider>) -> Self {
+        self.classifier = Some(provider);
+        self
+    }
+
+    pub fn build(self) -> Result<AgentRouter> {
+        let first = self.agents.first().ok_or_else(|| {
+            AgentError::Configuration("Router requires at least one agent".to_string())
+        })?;
+        let session_store = first.session_store().clone();
+
+        for (index, agent) in self.agents.iter().enumerate() {
+            if self.agents[..index]
+                .iter()
+                .any(|other| other.name() == agent.name())
+            {
+                return Err(AgentError::Configuration(format!(
+                    "Duplicate agent name '{}'",
+                    agent.name()
+                )));
+            }
+            if !Arc::ptr_eq(agent.session_store(), &session_store) {
+                return Err(AgentError::Configuration(format!(
+                    "Agent '{}' must use the same session store as the other agents",
+                    agent.name()
+                )));
+            }
+        }
+
+        let default_agent = match &self.default_agent {
+            Some(name) => self
+                .agents
+                .iter()
+                .position(|agent| agent.name() == name)
+                .ok_or_else(|| {
+                    AgentError::Configuration(format!("Unknown default agent '{}'", name))
+                })?,
+            None => 0,
+        };
+
+        Ok(AgentRouter {
+            id: AgentId::new(),
+            agents: self.agents,
+            default_agent,
+            classifier: self.classifier,
+            session_store,
+            session_locks: SessionLocks::default(),
+        })
+    }
+}
+
+impl Default for AgentRouterBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
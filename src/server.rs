@@ -0,0 +1,264 @@
+//! HTTP server exposing an agent as a REST + Server-Sent Events API
+//!
+//! | Method   | Path                             | Response                      |
+//! |----------|----------------------------------|-------------------------------|
+//! | `GET`    | `/health`                        | `{"status": "ok", "agent": …}` |
+//! | `POST`   | `/sessions`                      | `201 {"session_id": …}`       |
+//! | `GET`    | `/sessions/{id}`                 | the [`Session`]               |
+//! | `DELETE` | `/sessions/{id}`                 | `204`, the session is ended   |
+//! | `POST`   | `/sessions/{id}/messages`        | the [`AgentResponse`]         |
+//! | `POST`   | `/sessions/{id}/messages/stream` | [`AgentEvent`]s as SSE        |
+//! | `GET`    | `/sessions/{id}/journey`         | the [`JourneyState`] or `null` |
+//!
+//! Messages are posted as `{"message": "…"}`, optionally with an
+//! `idempotency_key`. Errors are returned as `{"error": "…"}` with the status
+//! code from [`status_code`].
+//!
+//! Available with the `server` feature, which also builds the `talk-server`
+//! binary.
+//!
+//! [`Session`]: crate::Session
+//! [`JourneyState`]: crate::JourneyState
+
+use crate::agent::{Agent, AgentEvent, AgentResponse, MessageOptions};
+use crate::error::{AgentError, StorageError};
+use crate::types::SessionId;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use futures::channel::mpsc;
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// Body of a posted message
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageRequest {
+    pub message: String,
+    /// Replays the stored response when a client retries a message
+    ///
+    /// Not supported for streamed messages.
+    #[serde(default)]
+    pub idempotency_key: Option<String>,
+}
+
+/// Body of a created session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionCreated {
+    pub session_id: SessionId,
+}
+
+/// An [`AgentError`] returned as a JSON response
+#[derive(Debug)]
+pub struct ApiError(pub AgentError);
+
+impl From<AgentError> for ApiError {
+    fn from(error: AgentError) -> Self {
+        Self(error)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = status_code(&self.0);
+        if status.is_server_error() {
+            warn!(status = %status, error = %self.0, "Request failed");
+        }
+        let body = serde_json::json!({ "error": self.0.to_string() });
+        (status, Json(body)).into_response()
+    }
+}
+
+/// The HTTP status code for an agent error
+pub fn status_code(error: &AgentError) -> StatusCode {
+    match error {
+        AgentError::SessionNotFound(_)
+        | AgentError::GuidelineNotFound(_)
+        | AgentError::ToolNotFound(_)
+        | AgentError::Storage(StorageError::NotFound(_)) => StatusCode::NOT_FOUND,
+        AgentError::SessionExpired(_) => StatusCode::GONE,
+        AgentError::SessionAlreadyExists(_)
+        | AgentError::SessionPaused(_)
+        | AgentError::SessionCompleted(_)
+        | AgentError::SessionTerminated(_)
+        | AgentError::ToolAlreadyRegistered(_)
+        | AgentError::Storage(StorageError::AlreadyExists(_))
+        | AgentError::Storage(StorageError::Conflict { .. }) => StatusCode::CONFLICT,
+        AgentError::InvalidInput(_)
+        | AgentError::InvalidDefinition(_)
+        | AgentError::InvalidToolParameters { .. } => StatusCode::BAD_REQUEST,
+        AgentError::LLMProvider(_) | AgentError::ProviderError(_) => StatusCode::BAD_GATEWAY,
+        AgentError::ToolTimeout { .. } => StatusCode::GATEWAY_TIMEOUT,
+        AgentError::Storage(StorageError::Connection(_))
+        | AgentError::Storage(StorageError::BackendUnavailable(_)) => {
+            StatusCode::SERVICE_UNAVAILABLE
+        }
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+type ApiResult<T> = std::result::Result<T, ApiError>;
+
+/// Routes serving `agent`
+pub fn router(agent: Arc<Agent>) -> Router {
+    Router::new()
+        .route("/health", get(health))
+        .route("/sessions", post(create_session))
+        .route("/sessions/{id}", get(get_session).delete(end_session))
+        .route("/sessions/{id}/messages", post(post_message))
+        .route("/sessions/{id}/messages/stream", post(stream_message))
+        .route("/sessions/{id}/journey", get(journey_state))
+        .with_state(agent)
+}
+
+/// Serve `agent` on `listener` until the process stops
+pub async fn serve(listener: TcpListener, agent: Arc<Agent>) -> std::io::Result<()> {
+    info!(
+        agent_name = %agent.name(),
+        address = ?listener.local_addr().ok(),
+        "Serving agent"
+    );
+    axum::serve(listener, router(agent)).await
+}
+
+async fn health(State(agent): State<Arc<Agent>>) -> Json<serde_json::Value> {
+    Json(serde_json::json!({ "status": "ok", "agent": agent.name() }))
+}
+
+async fn create_session(
+    State(agent): State<Arc<Agent>>,
+) -> ApiResult<(StatusCode, Json<SessionCreated>)> {
+    let session_id = agent.create_session().await?;
+    Ok((StatusCode::CREATED, Json(SessionCreated { session_id })))
+}
+
+async fn get_session(
+    State(agent): State<Arc<Agent>>,
+    Path(id): Path<Uuid>,
+) -> ApiResult<Json<crate::session::Session>> {
+    let session_id = SessionId::from(id);
+    let session = agent
+        .get_session(&session_id)
+        .await?
+        .ok_or(AgentError::SessionNotFound(session_id))?;
+    Ok(Json(session))
+}
+
+async fn end_session(
+    State(agent): State<Arc<Agent>>,
+    Path(id): Path<Uuid>,
+) -> ApiResult<StatusCode> {
+    agent.end_session(&SessionId::from(id)).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn post_message(
+    State(agent): State<Arc<Agent>>,
+    Path(id): Path<Uuid>,
+    Json(request): Json<MessageRequest>,
+) -> ApiResult<Json<AgentResponse>> {
+    let options = MessageOptions {
+        idempotency_key: request.idempotency_key,
+    };
+    let response = agent
+        .process_message_with_options(SessionId::from(id), request.message, options)
+        .await?;
+    Ok(Json(response))
+}
+
+/// Stream the events of a message, ending with `completed` or `error`
+///
+/// The message is processed to the end even if the client disconnects.
+async fn stream_message(
+    State(agent): State<Arc<Agent>>,
+    Path(id): Path<Uuid>,
+    Json(request): Json<MessageRequest>,
+) -> ApiResult<Sse<impl Stream<Item = std::result::Result<Event, Infallible>>>> {
+    if request.idempotency_key.is_some() {
+        return Err(AgentError::InvalidInput(
+            "Idempotency keys are not supported for streamed messages".to_string(),
+        )
+        .into());
+    }
+
+    let (sender, receiver) = mpsc::unbounded();
+    tokio::spawn(async move {
+        let mut events = agent.process_message_stream(SessionId::from(id), request.message);
+        while let Some(event) = events.next().await {
+            let _ = sender.unbounded_send(event);
+        }
+    });
+
+    let events = receiver.map(|event| {
+        Ok(match event {
+            Ok(event) => sse_event(&event),
+            Err(e) => Event::default().event("error").data(
+                serde_json::json!({
+                    "error": e.to_string(),
+                    "status": status_code(&e).as_u16(),
+                })
+                .to_string(),
+            ),
+        })
+    });
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+/// An SSE event named after the agent event's type, with its data as JSON
+fn sse_event(event: &AgentEvent) -> Event {
+    let value = serde_json::to_value(event).unwrap_or_default();
+    let name = value["type"].as_str().unwrap_or("message").to_string();
+    Event::default().event(name).data(value["data"].to_string())
+}
+
+async fn journey_state(
+    State(agent): State<Arc<Agent>>,
+    Path(id): Path<Uuid>,
+) -> ApiResult<Json<Option<crate::journey::JourneyState>>> {
+    let state = agent.get_journey_state(&SessionId::from(id)).await?;
+    Ok(Json(state))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_codes() {
+        let session_id = SessionId::new();
+        assert_eq!(
+            status_code(&AgentError::SessionNotFound(session_id)),
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            status_code(&AgentError::SessionExpired(session_id)),
+            StatusCode::GONE
+        );
+        assert_eq!(
+            status_code(&AgentError::SessionCompleted(session_id)),
+            StatusCode::CONFLICT
+        );
+        assert_eq!(
+            status_code(&AgentError::Storage(StorageError::Conflict {
+                expected: 1,
+                actual: 2
+            })),
+            StatusCode::CONFLICT
+        );
+        assert_eq!(
+            status_code(&AgentError::ProviderError("rate limited".to_string())),
+            StatusCode::BAD_GATEWAY
+        );
+        assert_eq!(
+            status_code(&AgentError::Internal("bug".to_string())),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+}
@@ -4,6 +4,8 @@
 //! including session metadata, status tracking, and journey state.
 
 use crate::context::Context;
+use crate::journey::JourneyState;
+use crate::provider::TokenUsage;
 use crate::types::{AgentId, JourneyId, SessionId, StepId};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
@@ -21,51 +23,9 @@ pub enum SessionStatus {
     Completed,
     /// Session has been terminated/cancelled
     Terminated,
-}
-
-/// Journey state within a session
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct JourneyState {
-    /// ID of the journey being executed
-    pub journey_id: JourneyId,
-    /// Current step in the journey
-    pub current_step: StepId,
-    /// When the journey was started
-    pub started_at: DateTime<Utc>,
-    /// When the journey was completed (if applicable)
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub completed_at: Option<DateTime<Utc>>,
-    /// Step history (ordered list of visited steps)
-    pub step_history: Vec<StepId>,
-}
-
-impl JourneyState {
-    /// Create a new journey state
-    pub fn new(journey_id: JourneyId, initial_step: StepId) -> Self {
-        Self {
-            journey_id,
-            current_step: initial_step,
-            started_at: Utc::now(),
-            completed_at: None,
-            step_history: vec![initial_step],
-        }
-    }
-
-    /// Move to the next step
-    pub fn move_to_step(&mut self, step_id: StepId) {
-        self.current_step = step_id;
-        self.step_history.push(step_id);
-    }
-
-    /// Mark the journey as completed
-    pub fn complete(&mut self) {
-        self.completed_at = Some(Utc::now());
-    }
-
-    /// Check if the journey is completed
-    pub fn is_completed(&self) -> bool {
-        self.completed_at.is_some()
-    }
+    /// A human operator is in control; the agent only records user messages
+    #[serde(rename = "awaiting_human")]
+    AwaitingHuman,
 }
 
 /// A conversation session
@@ -92,6 +52,33 @@ pub struct Session {
     /// When the session expires (if applicable)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub expires_at: Option<DateTime<Utc>>,
+    /// Version of the stored session, incremented by every `SessionStore::update`
+    #[serde(default)]
+    pub version: u64,
+    /// Tokens used by the LLM calls made for this session
+    #[serde(default)]
+    pub token_usage: TokenUsage,
+    /// Cost of those tokens, priced with `AgentConfig::model_prices`
+    #[serde(default)]
+    pub cost: f64,
+    /// Responses to recent messages sent with an idempotency key, oldest first
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub idempotent_responses: Vec<IdempotentResponse>,
+    /// Messages in a row answered by the agent's fallback strategy
+    #[serde(default)]
+    pub consecutive_fallbacks: u32,
+}
+
+/// Number of idempotency keys remembered per session
+pub const MAX_IDEMPOTENCY_KEYS: usize = 100;
+
+/// Response stored for a message sent with an idempotency key
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IdempotentResponse {
+    pub key: String,
+    /// The serialized `AgentResponse`
+    pub response: serde_json::Value,
+    pub created_at: DateTime<Utc>,
 }
 
 impl Session {
@@ -108,6 +95,11 @@ impl Session {
             created_at: now,
             updated_at: now,
             expires_at: None,
+            version: 0,
+            token_usage: TokenUsage::default(),
+            cost: 0.0,
+            idempotent_responses: Vec::new(),
+            consecutive_fallbacks: 0,
         }
     }
 
@@ -124,6 +116,11 @@ impl Session {
             created_at: now,
             updated_at: now,
             expires_at: None,
+            version: 0,
+            token_usage: TokenUsage::default(),
+            cost: 0.0,
+            idempotent_responses: Vec::new(),
+            consecutive_fallbacks: 0,
         }
     }
 
@@ -158,6 +155,43 @@ impl Session {
         }
     }
 
+    /// Get the response stored for an idempotency key
+    pub fn idempotent_response(&self, key: &str) -> Option<&serde_json::Value> {
+        self.idempotent_responses
+            .iter()
+            .find(|stored| stored.key == key)
+            .map(|stored| &stored.response)
+    }
+
+    /// Store the response for an idempotency key
+    ///
+    /// Only the last [`MAX_IDEMPOTENCY_KEYS`] keys are kept.
+    pub fn record_idempotent_response(
+        &mut self,
+        key: impl Into<String>,
+        response: serde_json::Value,
+    ) {
+        let key = key.into();
+        self.idempotent_responses.retain(|stored| stored.key != key);
+        self.idempotent_responses.push(IdempotentResponse {
+            key,
+            response,
+            created_at: Utc::now(),
+        });
+        let excess = self
+            .idempotent_responses
+            .len()
+            .saturating_sub(MAX_IDEMPOTENCY_KEYS);
+        self.idempotent_responses.drain(..excess);
+    }
+
+    /// Move the expiry to `idle_timeout` from now
+    pub fn extend_expiration(&mut self, idle_timeout: std::time::Duration) {
+        let idle_timeout =
+            chrono::Duration::from_std(idle_timeout).unwrap_or(chrono::Duration::MAX);
+        self.expires_at = Utc::now().checked_add_signed(idle_timeout);
+    }
+
     /// Pause the session
     pub fn pause(&mut self) {
         self.status = SessionStatus::Paused;
@@ -184,6 +218,32 @@ impl Session {
         self.touch();
     }
 
+    /// Hand the session to a human operator
+    ///
+    /// The reason is kept in the metadata under `escalation_reason`.
+    pub fn escalate(&mut self, reason: impl Into<String>) {
+        self.status = SessionStatus::AwaitingHuman;
+        self.metadata.insert(
+            "escalation_reason".to_string(),
+            serde_json::Value::String(reason.into()),
+        );
+        self.touch();
+    }
+
+    /// Check if a human operator is in control of the session
+    pub fn is_awaiting_human(&self) -> bool {
+        self.status == SessionStatus::AwaitingHuman
+    }
+
+    /// Hand control back to the agent after an escalation
+    pub fn return_to_agent(&mut self) {
+        if self.status == SessionStatus::AwaitingHuman {
+            self.status = SessionStatus::Active;
+            self.metadata.remove("escalation_reason");
+            self.touch();
+        }
+    }
+
     /// Start a journey in this session
     pub fn start_journey(&mut self, journey_id: JourneyId, initial_step: StepId) {
         self.journey_state = Some(JourneyState::new(journey_id, initial_step));
@@ -203,7 +263,7 @@ impl Session {
     /// Complete the active journey
     pub fn complete_journey(&mut self) {
         if let Some(ref mut journey) = self.journey_state {
-            journey.complete();
+            journey.mark_complete();
             self.touch();
         }
     }
@@ -231,48 +291,6 @@ mod tests {
         assert_eq!(status, deserialized);
     }
 
-    #[test]
-    fn test_journey_state_creation() {
-        let journey_id = JourneyId::new();
-        let step_id = StepId::new();
-        let state = JourneyState::new(journey_id, step_id);
-
-        assert_eq!(state.journey_id, journey_id);
-        assert_eq!(state.current_step, step_id);
-        assert_eq!(state.step_history.len(), 1);
-        assert!(!state.is_completed());
-    }
-
-    #[test]
-    fn test_journey_state_move_to_step() {
-        let journey_id = JourneyId::new();
-        let step1 = StepId::new();
-        let step2 = StepId::new();
-        let step3 = StepId::new();
-
-        let mut state = JourneyState::new(journey_id, step1);
-        state.move_to_step(step2);
-        state.move_to_step(step3);
-
-        assert_eq!(state.current_step, step3);
-        assert_eq!(state.step_history.len(), 3);
-        assert_eq!(state.step_history[0], step1);
-        assert_eq!(state.step_history[1], step2);
-        assert_eq!(state.step_history[2], step3);
-    }
-
-    #[test]
-    fn test_journey_state_complete() {
-        let journey_id = JourneyId::new();
-        let step_id = StepId::new();
-        let mut state = JourneyState::new(journey_id, step_id);
-
-        assert!(!state.is_completed());
-        state.complete();
-        assert!(state.is_completed());
-        assert!(state.completed_at.is_some());
-    }
-
     #[test]
     fn test_session_creation() {
         let agent_id = AgentId::new();
@@ -315,6 +333,31 @@ mod tests {
         assert!(session.is_expired());
     }
 
+    #[test]
+    fn test_idempotent_responses_keep_latest_keys() {
+        let mut session = Session::new(AgentId::new());
+        for i in 0..=MAX_IDEMPOTENCY_KEYS {
+            session.record_idempotent_response(format!("key-{}", i), serde_json::json!(i));
+        }
+
+        assert_eq!(session.idempotent_responses.len(), MAX_IDEMPOTENCY_KEYS);
+        assert!(session.idempotent_response("key-0").is_none());
+        assert_eq!(
+            session.idempotent_response("key-1"),
+            Some(&serde_json::json!(1))
+        );
+    }
+
+    #[test]
+    fn test_extend_expiration() {
+        let mut session = Session::new(AgentId::new()).with_expiration(Utc::now());
+        session.extend_expiration(std::time::Duration::from_secs(600));
+
+        let remaining = session.expires_at.unwrap() - Utc::now();
+        assert!(remaining > Duration::minutes(9) && remaining <= Duration::minutes(10));
+        assert!(!session.is_expired());
+    }
+
     #[test]
     fn test_session_with_metadata() {
         let agent_id = AgentId::new();
@@ -359,6 +402,28 @@ mod tests {
         assert!(!session.is_active());
     }
 
+    #[test]
+    fn test_session_escalation() {
+        let agent_id = AgentId::new();
+        let mut session = Session::new(agent_id);
+
+        session.escalate("Customer asked for a human");
+        assert!(session.is_awaiting_human());
+        assert!(!session.is_active());
+        assert_eq!(
+            serde_json::to_string(&session.status).unwrap(),
+            "\"awaiting_human\""
+        );
+        assert_eq!(
+            session.metadata["escalation_reason"],
+            "Customer asked for a human"
+        );
+
+        session.return_to_agent();
+        assert!(session.is_active());
+        assert!(!session.metadata.contains_key("escalation_reason"));
+    }
+
     #[test]
     fn test_session_touch() {
         let agent_id = AgentId::new();
@@ -5,11 +5,12 @@
 
 use crate::error::StorageError;
 use crate::session::Session;
-use crate::storage::SessionStore;
+use crate::storage::{is_past_retention, SessionStore};
 use crate::types::SessionId;
 use async_trait::async_trait;
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
 
 /// In-memory session storage implementation
@@ -99,17 +100,26 @@ impl SessionStore for InMemorySessionStore {
         Ok(sessions.get(id).cloned())
     }
 
-    async fn update(&self, id: &SessionId, session: Session) -> Result<(), StorageError> {
+    async fn update(&self, id: &SessionId, mut session: Session) -> Result<(), StorageError> {
         let mut sessions = self.sessions.write().await;
 
         // Check if session exists before updating
-        if !sessions.contains_key(id) {
+        let Some(stored) = sessions.get(id) else {
             return Err(StorageError::NotFound(format!(
                 "Session with ID {} not found",
                 id
             )));
+        };
+
+        // Reject updates based on a stale copy of the session
+        if stored.version != session.version {
+            return Err(StorageError::Conflict {
+                expected: session.version,
+                actual: stored.version,
+            });
         }
 
+        session.version += 1;
         sessions.insert(*id, session);
         Ok(())
     }
@@ -136,6 +146,13 @@ impl SessionStore for InMemorySessionStore {
         let sessions = self.sessions.read().await;
         Ok(sessions.contains_key(id))
     }
+
+    async fn cleanup_expired(&self, retention: Duration) -> Result<usize, StorageError> {
+        let mut sessions = self.sessions.write().await;
+        let before = sessions.len();
+        sessions.retain(|_, session| !is_past_retention(session, retention));
+        Ok(before - sessions.len())
+    }
 }
 
 #[cfg(test)]
@@ -207,6 +224,34 @@ mod tests {
         assert_eq!(retrieved.status, crate::session::SessionStatus::Completed);
     }
 
+    #[tokio::test]
+    async fn test_update_with_stale_version_conflicts() {
+        let store = InMemorySessionStore::new();
+        let session = Session::new(AgentId::new());
+        let session_id = session.id;
+        store.create(session).await.unwrap();
+
+        let mut first = store.get(&session_id).await.unwrap().unwrap();
+        let mut second = first.clone();
+
+        first.pause();
+        store.update(&session_id, first).await.unwrap();
+
+        second.complete();
+        let result = store.update(&session_id, second).await;
+        assert!(matches!(
+            result,
+            Err(StorageError::Conflict {
+                expected: 0,
+                actual: 1
+            })
+        ));
+
+        let retrieved = store.get(&session_id).await.unwrap().unwrap();
+        assert_eq!(retrieved.status, crate::session::SessionStatus::Paused);
+        assert_eq!(retrieved.version, 1);
+    }
+
     #[tokio::test]
     async fn test_update_nonexistent_session() {
         let store = InMemorySessionStore::new();
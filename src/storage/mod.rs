@@ -7,6 +7,8 @@ use crate::error::StorageError;
 use crate::session::Session;
 use crate::types::SessionId;
 use async_trait::async_trait;
+use chrono::Utc;
+use std::time::Duration;
 
 pub mod memory;
 
@@ -40,6 +42,10 @@ pub trait SessionStore: Send + Sync {
 
     /// Update an existing session
     ///
+    /// This is a compare-and-swap: the update only succeeds if the stored
+    /// session still has `session.version`, and the stored copy then gets
+    /// `session.version + 1`. Re-read the session before updating it again.
+    ///
     /// # Arguments
     ///
     /// * `id` - The session ID to update
@@ -47,7 +53,8 @@ pub trait SessionStore: Send + Sync {
     ///
     /// # Returns
     ///
-    /// Ok on success, or a storage error
+    /// Ok on success, `StorageError::Conflict` if the stored version differs,
+    /// or another storage error
     async fn update(&self, id: &SessionId, session: Session) -> Result<(), StorageError>;
 
     /// Delete a session by ID
@@ -80,4 +87,44 @@ pub trait SessionStore: Send + Sync {
     async fn exists(&self, id: &SessionId) -> Result<bool, StorageError> {
         Ok(self.get(id).await?.is_some())
     }
+
+    /// Delete sessions that expired more than `retention` ago
+    ///
+    /// Expired sessions are kept for `retention` so they can still be
+    /// inspected, then deleted. The default implementation loads every
+    /// session; backends that can query by expiry should override it.
+    ///
+    /// # Arguments
+    ///
+    /// * `retention` - How long to keep sessions after they expire
+    ///
+    /// # Returns
+    ///
+    /// The number of sessions deleted, or a storage error
+    async fn cleanup_expired(&self, retention: Duration) -> Result<usize, StorageError> {
+        let mut deleted = 0;
+        for id in self.list().await? {
+            let Some(session) = self.get(&id).await? else {
+                continue;
+            };
+            if is_past_retention(&session, retention) {
+                match self.delete(&id).await {
+                    Ok(()) => deleted += 1,
+                    // Deleted concurrently
+                    Err(StorageError::NotFound(_)) => {}
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+        Ok(deleted)
+    }
+}
+
+/// Check if a session expired more than `retention` ago
+pub fn is_past_retention(session: &Session, retention: Duration) -> bool {
+    let retention = chrono::Duration::from_std(retention).unwrap_or(chrono::Duration::MAX);
+    session
+        .expires_at
+        .and_then(|expires_at| expires_at.checked_add_signed(retention))
+        .is_some_and(|deadline| Utc::now() > deadline)
 }
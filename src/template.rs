@@ -0,0 +1,327 @@
+//! Response template rendering
+//!
+//! This module implements the small template language used by
+//! `GuidelineAction::response_template`. Placeholders are written in braces:
+//!
+//! - `{order_id}` - a parameter extracted by the guideline (or a context variable)
+//! - `{context.email}` - a session context variable
+//! - `{tool.weather.current.temp}` - a tool output, selected with a JSON path
+//! - `{session.plan}` - session metadata
+//! - `{order_id|unknown}` - a placeholder with an inline fallback
+//!
+//! Paths may index arrays (`{tool.search.results[0].title}`), and `{{` / `}}`
+//! produce literal braces.
+
+use crate::error::{AgentError, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// What to do when a template placeholder cannot be resolved
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MissingVariablePolicy {
+    /// Fail rendering with `AgentError::Template`
+    Error,
+    /// Leave the placeholder text in the output unchanged
+    #[default]
+    Keep,
+    /// Replace the placeholder with an empty string
+    Empty,
+    /// Replace the placeholder with the given text
+    Fallback(String),
+}
+
+/// Values available to a response template
+#[derive(Debug, Clone, Default)]
+pub struct TemplateContext {
+    /// Parameters extracted by the matched guideline
+    pub parameters: HashMap<String, Value>,
+    /// Session context variables
+    pub variables: HashMap<String, Value>,
+    /// Tool outputs keyed by tool name
+    pub tool_outputs: HashMap<String, Value>,
+    /// Session metadata
+    pub session_metadata: HashMap<String, Value>,
+}
+
+impl TemplateContext {
+    /// Resolve a placeholder expression such as `tool.weather.temp`
+    fn resolve(&self, expression: &str) -> Option<&Value> {
+        let segments = parse_path(expression)?;
+        let (root, rest) = segments.split_first()?;
+        let PathSegment::Key(root) = root else {
+            return None;
+        };
+
+        match root.as_str() {
+            "context" => select_named(&self.variables, rest),
+            "tool" => select_named(&self.tool_outputs, rest),
+            "session" => select_named(&self.session_metadata, rest),
+            _ => self
+                .parameters
+                .get(root)
+                .or_else(|| self.variables.get(root))
+                .and_then(|value| select(value, rest)),
+        }
+    }
+}
+
+/// Render a template against the given context
+pub fn render(
+    template: &str,
+    context: &TemplateContext,
+    policy: &MissingVariablePolicy,
+) -> Result<String> {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(pos) = rest.find(['{', '}']) {
+        output.push_str(&rest[..pos]);
+        let tail = &rest[pos..];
+
+        if tail.starts_with("{{") || tail.starts_with("}}") {
+            output.push_str(&tail[..1]);
+            rest = &tail[2..];
+            continue;
+        }
+
+        if let Some(after) = tail.strip_prefix('}') {
+            // Stray closing brace is kept as literal text
+            output.push('}');
+            rest = after;
+            continue;
+        }
+
+        let Some(end) = tail.find('}') else {
+            // Unterminated placeholder is kept as literal text
+            output.push_str(tail);
+            rest = "";
+            break;
+        };
+
+        let placeholder = &tail[..=end];
+        let (expression, inline_fallback) = match tail[1..end].split_once('|') {
+            Some((expression, fallback)) => (expression.trim(), Some(fallback)),
+            None => (tail[1..end].trim(), None),
+        };
+
+        match context.resolve(expression) {
+            Some(value) => output.push_str(&format_value(value)),
+            None => match (inline_fallback, policy) {
+                (Some(fallback), _) => output.push_str(fallback),
+                (None, MissingVariablePolicy::Error) => {
+                    return Err(AgentError::Template(format!(
+                        "Missing template variable '{}'",
+                        expression
+                    )));
+                }
+                (None, MissingVariablePolicy::Keep) => output.push_str(placeholder),
+                (None, MissingVariablePolicy::Empty) => {}
+                (None, MissingVariablePolicy::Fallback(fallback)) => output.push_str(fallback),
+            },
+        }
+
+        rest = &tail[end + 1..];
+    }
+
+    output.push_str(rest);
+    Ok(output)
+}
+
+/// Format a resolved value for inclusion in text
+fn format_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+/// Parse a path like `$.items[0].name` into segments
+fn parse_path(path: &str) -> Option<Vec<PathSegment>> {
+    let path = path.strip_prefix('$').unwrap_or(path);
+    let path = path.strip_prefix('.').unwrap_or(path);
+    if path.is_empty() {
+        return None;
+    }
+
+    let mut segments = Vec::new();
+    for part in path.split('.') {
+        let (key, mut indices) = match part.find('[') {
+            Some(pos) => (&part[..pos], &part[pos..]),
+            None => (part, ""),
+        };
+
+        if !key.is_empty() {
+            if !key
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+            {
+                return None;
+            }
+            segments.push(PathSegment::Key(key.to_string()));
+        }
+
+        while !indices.is_empty() {
+            let close = indices.find(']')?;
+            let index = indices[1..close].trim().parse().ok()?;
+            segments.push(PathSegment::Index(index));
+            indices = &indices[close + 1..];
+            if !indices.is_empty() && !indices.starts_with('[') {
+                return None;
+            }
+        }
+    }
+
+    Some(segments)
+}
+
+/// Select a value by name, then descend with the remaining path segments
+fn select_named<'v>(values: &'v HashMap<String, Value>, path: &[PathSegment]) -> Option<&'v Value> {
+    let (name, rest) = path.split_first()?;
+    let PathSegment::Key(name) = name else {
+        return None;
+    };
+    select(values.get(name)?, rest)
+}
+
+/// Select a nested value with parsed path segments
+fn select<'v>(value: &'v Value, path: &[PathSegment]) -> Option<&'v Value> {
+    path.iter()
+        .try_fold(value, |current, segment| match segment {
+            PathSegment::Key(key) => current.get(key),
+            PathSegment::Index(index) => current.get(index),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn create_context() -> TemplateContext {
+        let mut context = TemplateContext::default();
+        context
+            .parameters
+            .insert("order_id".to_string(), json!("12345"));
+        context
+            .variables
+            .insert("email".to_string(), json!("alice@example.com"));
+        context.tool_outputs.insert(
+            "weather".to_string(),
+            json!({"current": {"temp": 72}, "alerts": [{"title": "Heat"}]}),
+        );
+        context
+            .session_metadata
+            .insert("plan".to_string(), json!("pro"));
+        context
+    }
+
+    #[test]
+    fn test_render_extracted_parameter() {
+        let context = create_context();
+        let output = render(
+            "Your order {order_id} is on its way",
+            &context,
+            &MissingVariablePolicy::Error,
+        )
+        .unwrap();
+        assert_eq!(output, "Your order 12345 is on its way");
+    }
+
+    #[test]
+    fn test_render_namespaces() {
+        let context = create_context();
+        let output = render(
+            "{context.email} ({session.plan}): {tool.weather.current.temp}F, {tool.weather.alerts[0].title}",
+            &context,
+            &MissingVariablePolicy::Error,
+        )
+        .unwrap();
+        assert_eq!(output, "alice@example.com (pro): 72F, Heat");
+    }
+
+    #[test]
+    fn test_render_json_path_prefix() {
+        let context = create_context();
+        let output = render(
+            "{tool.weather.$.current.temp}",
+            &context,
+            &MissingVariablePolicy::Error,
+        );
+        // `$` is only allowed at the start of the whole expression
+        assert!(output.is_err());
+
+        let output = render("{$.order_id}", &context, &MissingVariablePolicy::Error).unwrap();
+        assert_eq!(output, "12345");
+    }
+
+    #[test]
+    fn test_render_escaped_braces() {
+        let context = create_context();
+        let output = render(
+            "{{order_id}} is {order_id}",
+            &context,
+            &MissingVariablePolicy::Error,
+        )
+        .unwrap();
+        assert_eq!(output, "{order_id} is 12345");
+    }
+
+    #[test]
+    fn test_missing_variable_policies() {
+        let context = create_context();
+        let template = "Hello {name}!";
+
+        assert!(matches!(
+            render(template, &context, &MissingVariablePolicy::Error),
+            Err(AgentError::Template(_))
+        ));
+        assert_eq!(
+            render(template, &context, &MissingVariablePolicy::Keep).unwrap(),
+            "Hello {name}!"
+        );
+        assert_eq!(
+            render(template, &context, &MissingVariablePolicy::Empty).unwrap(),
+            "Hello !"
+        );
+        assert_eq!(
+            render(
+                template,
+                &context,
+                &MissingVariablePolicy::Fallback("there".to_string())
+            )
+            .unwrap(),
+            "Hello there!"
+        );
+    }
+
+    #[test]
+    fn test_inline_fallback() {
+        let context = create_context();
+        let output = render(
+            "Hello {name|friend}, order {order_id|none}",
+            &context,
+            &MissingVariablePolicy::Error,
+        )
+        .unwrap();
+        assert_eq!(output, "Hello friend, order 12345");
+    }
+
+    #[test]
+    fn test_template_without_placeholders_is_unchanged() {
+        let context = TemplateContext::default();
+        let template = "Our pricing starts at $49/month.";
+        assert_eq!(
+            render(template, &context, &MissingVariablePolicy::Error).unwrap(),
+            template
+        );
+    }
+}
@@ -4,11 +4,12 @@
 //! APIs and functions during conversation processing.
 
 use crate::error::{AgentError, Result};
+use crate::metrics::Metrics;
 use crate::types::ToolId;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 use tokio::time::timeout;
 use tracing::{debug, info, trace, warn};
@@ -30,6 +31,24 @@ pub struct ToolResult {
     pub metadata: HashMap<String, serde_json::Value>,
 }
 
+/// Tool definition offered to an LLM for native function calling
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolDefinition {
+    pub name: String,
+    pub description: String,
+    /// JSON schema of the tool's parameters
+    pub parameters: serde_json::Value,
+}
+
+/// Tool invocation requested by an LLM
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolCall {
+    /// Provider-assigned identifier used to correlate the tool result
+    pub id: String,
+    pub name: String,
+    pub arguments: HashMap<String, serde_json::Value>,
+}
+
 /// Trait for tools that can be executed by the agent
 #[async_trait::async_trait]
 pub trait Tool: Send + Sync {
@@ -48,6 +67,40 @@ pub trait Tool: Send + Sync {
     /// Execute the tool with given parameters
     async fn execute(&self, parameters: HashMap<String, serde_json::Value>) -> Result<ToolResult>;
 
+    /// Build the JSON-schema definition sent to the LLM for function calling
+    fn definition(&self) -> ToolDefinition {
+        let mut properties = serde_json::Map::new();
+        let mut required = Vec::new();
+
+        for (param_name, param_schema) in self.parameters() {
+            let mut property = serde_json::Map::new();
+            property.insert("type".to_string(), param_schema.param_type.clone().into());
+            property.insert(
+                "description".to_string(),
+                param_schema.description.clone().into(),
+            );
+            if let Some(ref default_value) = param_schema.default {
+                property.insert("default".to_string(), default_value.clone());
+            }
+            properties.insert(param_name.clone(), property.into());
+
+            if param_schema.required {
+                required.push(param_name.clone());
+            }
+        }
+        required.sort();
+
+        ToolDefinition {
+            name: self.name().to_string(),
+            description: self.description().to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": properties,
+                "required": required,
+            }),
+        }
+    }
+
     /// Validate parameters before execution
     fn validate_parameters(&self, parameters: &HashMap<String, serde_json::Value>) -> Result<()> {
         trace!(tool_name = %self.name(), "Validating tool parameters");
@@ -137,6 +190,9 @@ fn validate_type(value: &serde_json::Value, expected_type: &str) -> bool {
 pub struct ToolRegistry {
     tools: Arc<RwLock<HashMap<ToolId, Arc<dyn Tool>>>>,
     tools_by_name: Arc<RwLock<HashMap<String, ToolId>>>,
+    /// Registered tools that must not be executed or offered to the LLM
+    disabled: Arc<RwLock<HashSet<ToolId>>>,
+    metrics: Option<Arc<Metrics>>,
 }
 
 impl ToolRegistry {
@@ -146,6 +202,16 @@ impl ToolRegistry {
         Self {
             tools: Arc::new(RwLock::new(HashMap::new())),
             tools_by_name: Arc::new(RwLock::new(HashMap::new())),
+            disabled: Arc::new(RwLock::new(HashSet::new())),
+            metrics: None,
+        }
+    }
+
+    /// Create a tool registry that records executions in `metrics`
+    pub fn with_metrics(metrics: Arc<Metrics>) -> Self {
+        Self {
+            metrics: Some(metrics),
+            ..Self::new()
         }
     }
 
@@ -195,6 +261,7 @@ impl ToolRegistry {
         if let Some(tool) = tools.remove(tool_id) {
             let tool_name = tool.name().to_string();
             tools_by_name.remove(&tool_name);
+            self.disabled.write().await.remove(tool_id);
 
             debug!(
                 tool_id = %tool_id,
@@ -231,6 +298,43 @@ impl ToolRegistry {
         tools.values().cloned().collect()
     }
 
+    /// Enable or disable a registered tool
+    ///
+    /// Disabled tools stay registered but fail to execute and are left out of
+    /// [`ToolRegistry::definitions`].
+    pub async fn set_enabled(&self, tool_id: &ToolId, enabled: bool) -> Result<()> {
+        if !self.tools.read().await.contains_key(tool_id) {
+            return Err(AgentError::ToolNotFound(*tool_id));
+        }
+
+        info!(tool_id = %tool_id, enabled = enabled, "Setting tool state");
+        let mut disabled = self.disabled.write().await;
+        if enabled {
+            disabled.remove(tool_id);
+        } else {
+            disabled.insert(*tool_id);
+        }
+        Ok(())
+    }
+
+    /// Whether a tool may be executed
+    pub async fn is_enabled(&self, tool_id: &ToolId) -> bool {
+        !self.disabled.read().await.contains(tool_id)
+    }
+
+    /// Function-calling definitions for all enabled tools, sorted by name
+    pub async fn definitions(&self) -> Vec<ToolDefinition> {
+        let tools = self.tools.read().await;
+        let disabled = self.disabled.read().await;
+        let mut definitions: Vec<ToolDefinition> = tools
+            .iter()
+            .filter(|(id, _)| !disabled.contains(id))
+            .map(|(_, tool)| tool.definition())
+            .collect();
+        definitions.sort_by(|a, b| a.name.cmp(&b.name));
+        definitions
+    }
+
     /// Execute a tool by ID with parameters
     pub async fn execute(
         &self,
@@ -247,6 +351,9 @@ impl ToolRegistry {
             .get(tool_id)
             .await
             .ok_or_else(|| AgentError::ToolNotFound(*tool_id))?;
+        if !self.is_enabled(tool_id).await {
+            return Err(AgentError::ToolDisabled(tool.name().to_string()));
+        }
 
         // Apply default values
         tool.apply_defaults(&mut parameters);
@@ -323,9 +430,43 @@ impl ToolRegistry {
         max_retries: u32,
         base_backoff_ms: u64,
     ) -> Result<ToolResult> {
+        self.execute_with_attempts(
+            tool_id,
+            parameters,
+            timeout_duration,
+            max_retries,
+            base_backoff_ms,
+        )
+        .await
+        .0
+    }
+
+    /// Execute a tool like [`ToolRegistry::execute_with_retry`], also
+    /// returning how many attempts were made
+    ///
+    /// A disabled tool is not attempted, so it reports zero attempts.
+    pub async fn execute_with_attempts(
+        &self,
+        tool_id: &ToolId,
+        parameters: HashMap<String, serde_json::Value>,
+        timeout_duration: Duration,
+        max_retries: u32,
+        base_backoff_ms: u64,
+    ) -> (Result<ToolResult>, u32) {
         let mut attempts = 0;
         let mut last_error = None;
 
+        // Retrying cannot help a disabled tool
+        if !self.is_enabled(tool_id).await {
+            return (self.execute(tool_id, parameters).await, 0);
+        }
+        let started = Instant::now();
+        let tool_name = self
+            .get(tool_id)
+            .await
+            .map(|t| t.name().to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+
         while attempts <= max_retries {
             info!(
                 tool_id = %tool_id,
@@ -340,28 +481,27 @@ impl ToolRegistry {
             {
                 Ok(result) => {
                     // Check if the result indicates an error
-                    if result.error.is_none() {
-                        debug!(
-                            tool_id = %tool_id,
-                            attempts = attempts + 1,
-                            "Tool execution successful"
-                        );
-                        return Ok(result);
-                    } else {
+                    if let Some(error) = result.error {
                         warn!(
                             tool_id = %tool_id,
                             attempt = attempts + 1,
-                            error = %result.error.as_ref().unwrap(),
+                            error = %error,
                             "Tool returned error result"
                         );
                         last_error = Some(AgentError::ToolExecutionFailed {
-                            tool_name: self
-                                .get(tool_id)
-                                .await
-                                .map(|t| t.name().to_string())
-                                .unwrap_or_else(|| "unknown".to_string()),
-                            reason: result.error.unwrap(),
+                            tool_name: tool_name.clone(),
+                            reason: error,
                         });
+                    } else {
+                        debug!(
+                            tool_id = %tool_id,
+                            attempts = attempts + 1,
+                            "Tool execution successful"
+                        );
+                        self.record(|m| {
+                            m.record_tool_execution(&tool_name, true, started.elapsed())
+                        });
+                        return (Ok(result), attempts + 1);
                     }
                 }
                 Err(e) => {
@@ -371,6 +511,9 @@ impl ToolRegistry {
                         error = %e,
                         "Tool execution failed"
                     );
+                    if matches!(e, AgentError::ToolTimeout { .. }) {
+                        self.record(|m| m.record_tool_timeout(&tool_name));
+                    }
                     last_error = Some(e);
                 }
             }
@@ -379,6 +522,8 @@ impl ToolRegistry {
 
             // Don't sleep after the last attempt
             if attempts <= max_retries {
+                self.record(|m| m.record_tool_retry(&tool_name));
+
                 // Calculate exponential backoff: base * 2^attempt
                 let backoff_ms = base_backoff_ms * 2u64.pow(attempts - 1);
                 let backoff = Duration::from_millis(backoff_ms);
@@ -399,13 +544,19 @@ impl ToolRegistry {
             total_attempts = attempts,
             "All retry attempts exhausted"
         );
+        self.record(|m| m.record_tool_execution(&tool_name, false, started.elapsed()));
 
-        Err(
-            last_error.unwrap_or_else(|| AgentError::ToolExecutionFailed {
-                tool_name: "unknown".to_string(),
-                reason: "All retry attempts failed".to_string(),
-            }),
-        )
+        let error = last_error.unwrap_or_else(|| AgentError::ToolExecutionFailed {
+            tool_name: "unknown".to_string(),
+            reason: "All retry attempts failed".to_string(),
+        });
+        (Err(error), attempts)
+    }
+
+    fn record(&self, f: impl FnOnce(&Metrics)) {
+        if let Some(metrics) = &self.metrics {
+            f(metrics);
+        }
     }
 }
 
@@ -418,6 +569,7 @@ impl Default for ToolRegistry {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::metrics::ToolStats;
 
     struct TestTool {
         id: ToolId,
@@ -623,6 +775,59 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_tool_definition_schema() {
+        let tool = TestTool::new();
+        let definition = tool.definition();
+
+        assert_eq!(definition.name, "test");
+        assert_eq!(definition.description, "A test tool");
+        assert_eq!(
+            definition.parameters,
+            serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "message": {"type": "string", "description": "A test message"}
+                },
+                "required": ["message"],
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_registry_definitions() {
+        let registry = ToolRegistry::new();
+        registry.register(Box::new(TestTool::new())).await.unwrap();
+
+        let definitions = registry.definitions().await;
+        assert_eq!(definitions.len(), 1);
+        assert_eq!(definitions[0].name, "test");
+    }
+
+    #[tokio::test]
+    async fn test_disabled_tool_is_hidden_and_not_executed() {
+        let registry = ToolRegistry::new();
+        let tool_id = registry.register(Box::new(TestTool::new())).await.unwrap();
+
+        registry.set_enabled(&tool_id, false).await.unwrap();
+        assert!(!registry.is_enabled(&tool_id).await);
+        assert!(registry.definitions().await.is_empty());
+
+        let mut params = HashMap::new();
+        params.insert("message".to_string(), serde_json::json!("hi"));
+        let result = registry
+            .execute_with_retry(&tool_id, params.clone(), Duration::from_secs(1), 3, 100)
+            .await;
+        assert!(matches!(result, Err(AgentError::ToolDisabled(_))));
+
+        registry.set_enabled(&tool_id, true).await.unwrap();
+        assert!(registry.execute(&tool_id, params).await.is_ok());
+        assert!(matches!(
+            registry.set_enabled(&ToolId::new(), false).await,
+            Err(AgentError::ToolNotFound(_))
+        ));
+    }
+
     #[tokio::test]
     async fn test_tool_timeout() {
         let registry = ToolRegistry::new();
@@ -749,6 +954,49 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_retry_outcomes_are_recorded_in_metrics() {
+        let metrics = Arc::new(Metrics::new());
+        let registry = ToolRegistry::with_metrics(metrics.clone());
+
+        let flaky_tool = FlakyTool::new_with_failures(1);
+        let flaky_id = flaky_tool.id;
+        registry.register(Box::new(flaky_tool)).await.unwrap();
+        let slow_tool = SlowTool::new_with_delay(Duration::from_millis(200));
+        let slow_id = slow_tool.id;
+        registry.register(Box::new(slow_tool)).await.unwrap();
+
+        registry
+            .execute_with_retry(&flaky_id, HashMap::new(), Duration::from_secs(1), 2, 10)
+            .await
+            .unwrap();
+        let result = registry
+            .execute_with_retry(&slow_id, HashMap::new(), Duration::from_millis(20), 1, 10)
+            .await;
+        assert!(result.is_err());
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(
+            snapshot.tools["flaky"],
+            ToolStats {
+                successes: 1,
+                failures: 0,
+                retries: 1,
+                timeouts: 0,
+            }
+        );
+        assert_eq!(
+            snapshot.tools["slow"],
+            ToolStats {
+                successes: 0,
+                failures: 1,
+                retries: 1,
+                timeouts: 2,
+            }
+        );
+        assert_eq!(snapshot.tool_latency["slow"].count, 1);
+    }
+
     #[tokio::test]
     async fn test_tool_retry_exponential_backoff() {
         let registry = ToolRegistry::new();
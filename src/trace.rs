@@ -0,0 +1,308 @@
+//! Decision traces
+//!
+//! A [`DecisionTrace`] records how an [`Agent`](crate::Agent) produced one
+//! response, for audits and debugging:
+//!
+//! - every candidate guideline, with the pattern and span that matched and,
+//!   for candidates that were not applied, why they lost,
+//! - every tool call, with its parameters, result, attempts and timing,
+//! - every LLM call, with the exact messages sent and the raw output.
+//!
+//! Enable traces with `AgentConfig::decision_trace`. They are returned on
+//! [`AgentResponse::trace`](crate::AgentResponse::trace) and, with
+//! `AgentConfig::store_decision_trace`, also kept in the metadata of the
+//! assistant [`Message`] under [`DECISION_TRACE_KEY`].
+
+use crate::agent::GuidelineSelection;
+use crate::context::Message;
+use crate::guideline::{Guideline, GuidelineCondition, GuidelineMatch};
+use crate::provider::TokenUsage;
+use crate::tool::ToolCall;
+use crate::types::{GuidelineId, StepId, ToolId};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ops::Range;
+use std::time::Duration;
+
+/// Message metadata key holding the decision trace of an assistant message
+pub const DECISION_TRACE_KEY: &str = "decision_trace";
+
+/// Full record of how a response was produced
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DecisionTrace {
+    /// The user message guidelines were matched against, after hooks ran
+    pub user_message: String,
+    /// Every guideline that matched the message, in match order
+    pub candidates: Vec<CandidateTrace>,
+    /// Guidelines whose actions shaped the response, primary first
+    pub applied_guidelines: Vec<GuidelineId>,
+    /// Whether the fallback strategy answered because nothing was selected
+    pub used_fallback: bool,
+    /// Journey step the message advanced to, if any
+    pub journey_step: Option<StepId>,
+    pub tool_calls: Vec<ToolCallTrace>,
+    pub llm_calls: Vec<LlmCallTrace>,
+}
+
+impl DecisionTrace {
+    /// Read the trace stored with an assistant message
+    pub fn from_message(message: &Message) -> Option<Self> {
+        message
+            .metadata
+            .as_ref()
+            .and_then(|metadata| metadata.get(DECISION_TRACE_KEY))
+            .and_then(|value| serde_json::from_value(value.clone()).ok())
+    }
+}
+
+/// A guideline that matched the user message
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CandidateTrace {
+    pub guideline_id: GuidelineId,
+    /// The condition that matched
+    pub condition: GuidelineCondition,
+    /// Byte range of the user message the condition matched, when known
+    pub span: Option<Range<usize>>,
+    /// The matched text
+    pub matched_text: Option<String>,
+    pub priority: i32,
+    pub created_at: DateTime<Utc>,
+    pub relevance_score: f32,
+    pub outcome: CandidateOutcome,
+}
+
+/// What happened to a candidate guideline during selection
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum CandidateOutcome {
+    /// Selected as the primary guideline
+    Selected,
+    /// Applied alongside the primary guideline
+    Composed,
+    /// Lost to a guideline with a higher priority
+    LowerPriority { winner: GuidelineId },
+    /// Lost to a newer guideline with the same priority
+    OlderThanWinner { winner: GuidelineId },
+    /// Relevance was below the composition threshold
+    BelowRelevance { min_relevance: f32 },
+    /// Priority was below the composition threshold
+    BelowPriority { min_priority: i32 },
+    /// The same guideline matched again elsewhere in the message
+    Duplicate,
+    /// Selected, then removed by an `after_match_selected` hook
+    RemovedByHook,
+    /// Not selected by the guideline matcher for another reason
+    NotSelected,
+}
+
+/// A tool the agent ran while producing the response
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolCallTrace {
+    pub tool_id: ToolId,
+    pub tool_name: String,
+    /// Parameters passed to the tool, after hooks ran
+    pub parameters: HashMap<String, serde_json::Value>,
+    /// Output of a successful call
+    pub output: Option<serde_json::Value>,
+    /// Error of a failed call
+    pub error: Option<String>,
+    /// Number of attempts, including retries
+    pub attempts: u32,
+    pub started_at: DateTime<Utc>,
+    pub duration: Duration,
+}
+
+/// Why the agent called the LLM
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LlmCallKind {
+    /// Generating the response
+    Response,
+    /// Asking for a rewrite after a guardrail violation
+    GuardrailRetry,
+    /// Summarizing older messages to fit the context
+    Summary,
+    /// Extracting captured context variables
+    Capture,
+}
+
+/// One call to the LLM provider
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LlmCallTrace {
+    pub kind: LlmCallKind,
+    /// The exact messages sent to the provider
+    pub messages: Vec<Message>,
+    /// The raw text the provider returned
+    pub output: String,
+    /// Tool calls the provider requested
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+    /// Provider-specific response metadata
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub metadata: HashMap<String, serde_json::Value>,
+    pub usage: TokenUsage,
+    pub duration: Duration,
+}
+
+/// Trace every match, recording why those not in `selected` lost
+///
+/// `proposed` is the matcher's selection before hooks ran, `selected` the
+/// selection after them.
+pub(crate) fn trace_candidates(
+    message: &str,
+    matches: &[GuidelineMatch],
+    proposed: &[GuidelineMatch],
+    selected: &[GuidelineMatch],
+    guidelines: &[Guideline],
+    selection: GuidelineSelection,
+) -> Vec<CandidateTrace> {
+    let find = |id: &GuidelineId| guidelines.iter().find(|g| g.id == *id);
+    let winner = selected.first().and_then(|m| find(&m.guideline_id));
+    let mut seen = Vec::new();
+
+    matches
+        .iter()
+        .filter_map(|m| {
+            let guideline = find(&m.guideline_id)?;
+            let is = |list: &[GuidelineMatch]| list.iter().any(|s| s.guideline_id == guideline.id);
+
+            let outcome = if seen.contains(&guideline.id) {
+                CandidateOutcome::Duplicate
+            } else if is(selected) {
+                if winner.is_some_and(|w| w.id == guideline.id) {
+                    CandidateOutcome::Selected
+                } else {
+                    CandidateOutcome::Composed
+                }
+            } else if is(proposed) {
+                CandidateOutcome::RemovedByHook
+            } else {
+                match (selection, winner) {
+                    (GuidelineSelection::Compose { min_relevance, .. }, _)
+                        if m.relevance_score < min_relevance =>
+                    {
+                        CandidateOutcome::BelowRelevance { min_relevance }
+                    }
+                    (GuidelineSelection::Compose { min_priority, .. }, _)
+                        if guideline.priority < min_priority =>
+                    {
+                        CandidateOutcome::BelowPriority { min_priority }
+                    }
+                    (_, Some(winner)) if guideline.priority < winner.priority => {
+                        CandidateOutcome::LowerPriority { winner: winner.id }
+                    }
+                    (_, Some(winner))
+                        if guideline.priority == winner.priority
+                            && guideline.created_at <= winner.created_at =>
+                    {
+                        CandidateOutcome::OlderThanWinner { winner: winner.id }
+                    }
+                    _ => CandidateOutcome::NotSelected,
+                }
+            };
+            seen.push(guideline.id);
+
+            Some(CandidateTrace {
+                guideline_id: guideline.id,
+                condition: guideline.condition.clone(),
+                span: m.span.clone(),
+                matched_text: m
+                    .span
+                    .clone()
+                    .and_then(|span| message.get(span))
+                    .map(str::to_string),
+                priority: guideline.priority,
+                created_at: guideline.created_at,
+                relevance_score: m.relevance_score,
+                outcome,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::guideline::GuidelineAction;
+
+    fn candidate_match(guideline: &Guideline, span: Range<usize>) -> GuidelineMatch {
+        GuidelineMatch {
+            guideline_id: guideline.id,
+            relevance_score: 1.0,
+            semantic_score: 0.0,
+            matched_condition: format!("{:?}", guideline.condition),
+            extracted_parameters: HashMap::new(),
+            explanation: None,
+            span: Some(span),
+        }
+    }
+
+    #[test]
+    fn test_candidates_record_why_they_lost() {
+        let literal = |text: &str, priority| {
+            Guideline::new(
+                GuidelineCondition::Literal(text.to_string()),
+                GuidelineAction::template(text),
+                priority,
+            )
+        };
+        let mut older = literal("refund", 10);
+        older.created_at -= chrono::Duration::seconds(1);
+        let winner = literal("order", 10);
+        let low = literal("please", 1);
+        let guidelines = vec![older.clone(), winner.clone(), low.clone()];
+
+        let message = "Please refund my order";
+        let matches = vec![
+            candidate_match(&low, 0..6),
+            candidate_match(&older, 7..13),
+            candidate_match(&winner, 17..22),
+        ];
+        let selected = vec![matches[2].clone()];
+
+        let candidates = trace_candidates(
+            message,
+            &matches,
+            &selected,
+            &selected,
+            &guidelines,
+            GuidelineSelection::Best,
+        );
+        let outcomes: Vec<_> = candidates.iter().map(|c| c.outcome.clone()).collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                CandidateOutcome::LowerPriority { winner: winner.id },
+                CandidateOutcome::OlderThanWinner { winner: winner.id },
+                CandidateOutcome::Selected,
+            ]
+        );
+        assert_eq!(candidates[0].matched_text.as_deref(), Some("Please"));
+
+        // The same match removed by a hook
+        let candidates = trace_candidates(
+            message,
+            &matches,
+            &selected,
+            &[],
+            &guidelines,
+            GuidelineSelection::Best,
+        );
+        assert_eq!(candidates[2].outcome, CandidateOutcome::RemovedByHook);
+    }
+
+    #[test]
+    fn test_trace_round_trips_through_message_metadata() {
+        let trace = DecisionTrace {
+            user_message: "hello".to_string(),
+            used_fallback: true,
+            ..Default::default()
+        };
+        let message = Message::assistant("Hi")
+            .with_metadata(DECISION_TRACE_KEY, serde_json::to_value(&trace).unwrap());
+
+        assert_eq!(DecisionTrace::from_message(&message), Some(trace));
+        assert_eq!(DecisionTrace::from_message(&Message::assistant("Hi")), None);
+    }
+}
@@ -9,7 +9,7 @@ use talk::{Agent, AgentConfig, Guideline, GuidelineAction, GuidelineCondition};
 // T024: Integration test for fallback guideline when no match
 #[tokio::test]
 async fn test_fallback_guideline_when_no_match() {
-    let mut agent = create_test_agent().await;
+    let agent = create_test_agent().await;
 
     // Add specific guideline
     let guideline = Guideline {
@@ -19,11 +19,17 @@ async fn test_fallback_guideline_when_no_match() {
             response_template: "Pricing info".to_string(),
             requires_llm: false,
             parameters: vec![],
+            must_mention: vec![],
+            output_schema: None,
+            handoff: None,
+            escalate: false,
         },
         priority: 10,
         tools: vec![],
         parameters: HashMap::new(),
+        captures: vec![],
         created_at: Utc::now(),
+        enabled: true,
     };
     agent
         .add_guideline(guideline)
@@ -56,7 +62,7 @@ async fn test_fallback_guideline_when_no_match() {
 // T025: End-to-end agent test with multiple guidelines
 #[tokio::test]
 async fn test_agent_with_multiple_guidelines() {
-    let mut agent = create_test_agent().await;
+    let agent = create_test_agent().await;
 
     // Add multiple guidelines
     let pricing_guideline = Guideline {
@@ -66,11 +72,17 @@ async fn test_agent_with_multiple_guidelines() {
             response_template: "Our pricing starts at $49/month for the basic plan.".to_string(),
             requires_llm: false,
             parameters: vec![],
+            must_mention: vec![],
+            output_schema: None,
+            handoff: None,
+            escalate: false,
         },
         priority: 10,
         tools: vec![],
         parameters: HashMap::new(),
+        captures: vec![],
         created_at: Utc::now(),
+        enabled: true,
     };
 
     let support_guideline = Guideline {
@@ -80,11 +92,17 @@ async fn test_agent_with_multiple_guidelines() {
             response_template: "How can I help you today?".to_string(),
             requires_llm: false,
             parameters: vec![],
+            must_mention: vec![],
+            output_schema: None,
+            handoff: None,
+            escalate: false,
         },
         priority: 10,
         tools: vec![],
         parameters: HashMap::new(),
+        captures: vec![],
         created_at: Utc::now(),
+        enabled: true,
     };
 
     agent
@@ -143,6 +161,299 @@ async fn test_agent_with_multiple_guidelines() {
     );
 }
 
+// Template placeholders are filled from extracted parameters and session metadata
+#[tokio::test]
+async fn test_template_interpolation_in_guideline_response() {
+    let agent = create_test_agent().await;
+
+    let guideline = Guideline::new(
+        GuidelineCondition::Regex(r"order #?(\d+)".to_string()),
+        GuidelineAction::template(
+            "Your order {order_id} is on its way, {session.customer|friend}!",
+        )
+        .with_parameters(vec!["order_id".to_string()]),
+        10,
+    );
+    agent
+        .add_guideline(guideline)
+        .await
+        .expect("Failed to add guideline");
+
+    let session_id = agent
+        .create_session()
+        .await
+        .expect("Failed to create session");
+
+    let response = agent
+        .process_message(session_id, "Where is order #12345?".to_string())
+        .await
+        .expect("Failed to process message");
+
+    assert_eq!(response.message, "Your order 12345 is on its way, friend!");
+}
+
+// With the error policy, unresolved placeholders fail the request
+#[tokio::test]
+async fn test_template_missing_variable_errors() {
+    let agent = Agent::builder()
+        .name("Test Agent")
+        .provider(Box::new(create_mock_provider()))
+        .config(AgentConfig {
+            missing_template_variables: talk::MissingVariablePolicy::Error,
+            ..Default::default()
+        })
+        .build()
+        .expect("Failed to build agent");
+
+    agent
+        .add_guideline(Guideline::new(
+            GuidelineCondition::Literal("status".to_string()),
+            GuidelineAction::template("Your order {order_id} is on its way"),
+            10,
+        ))
+        .await
+        .expect("Failed to add guideline");
+
+    let session_id = agent
+        .create_session()
+        .await
+        .expect("Failed to create session");
+
+    let result = agent
+        .process_message(session_id, "What's my status?".to_string())
+        .await;
+
+    assert!(matches!(result, Err(talk::AgentError::Template(_))));
+}
+
+// Concurrent messages for one session are serialized, so no history is lost
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_concurrent_messages_for_same_session_are_all_saved() {
+    let agent = std::sync::Arc::new(
+        Agent::builder()
+            .name("Test Agent")
+            .provider(Box::new(SlowProvider {
+                inner: create_mock_provider(),
+                delay: Duration::from_millis(20),
+            }))
+            .build()
+            .expect("Failed to build agent"),
+    );
+    let session_id = agent
+        .create_session()
+        .await
+        .expect("Failed to create session");
+
+    let tasks: Vec<_> = (0..5)
+        .map(|i| {
+            let agent = agent.clone();
+            tokio::spawn(async move {
+                agent
+                    .process_message(session_id, format!("Message {}", i))
+                    .await
+            })
+        })
+        .collect();
+    for task in tasks {
+        task.await
+            .expect("Task panicked")
+            .expect("Failed to process message");
+    }
+
+    let session = agent
+        .get_session(&session_id)
+        .await
+        .expect("Failed to get session")
+        .expect("Session should exist");
+    assert_eq!(session.context.messages.len(), 10);
+    assert_eq!(session.version, 5);
+
+    // Each user message is directly followed by its response
+    for pair in session.context.messages.chunks(2) {
+        assert_eq!(pair[0].role, talk::MessageRole::User);
+        assert_eq!(pair[1].role, talk::MessageRole::Assistant);
+    }
+}
+
+// A definition file configures guidelines and journeys by name
+#[tokio::test]
+async fn test_agent_from_definition_file() {
+    let mut definition = talk::AgentDefinition::from_file(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/examples/support_agent.yaml"
+    ))
+    .expect("Failed to load definition");
+    definition.provider = None;
+
+    let agent = talk::AgentBuilder::from_definition(&definition)
+        .expect("Failed to read definition")
+        .provider(Box::new(create_mock_provider()))
+        .build()
+        .expect("Failed to build agent");
+    assert_eq!(agent.name(), "Support Bot");
+    assert_eq!(agent.config().max_context_messages, 50);
+
+    // The order guideline references a tool that is not registered yet
+    let result = agent.add_definition(&definition).await;
+    let Err(talk::AgentError::InvalidDefinition(issues)) = result else {
+        panic!("expected an invalid definition");
+    };
+    assert_eq!(issues.len(), 1);
+    assert!(issues[0]
+        .to_string()
+        .ends_with("support_agent.yaml:guidelines[1].tools[0]: Unknown tool 'lookup_order'"));
+
+    agent
+        .add_tool(Box::new(OrderTool::new()))
+        .await
+        .expect("Failed to add tool");
+    agent
+        .add_definition(&definition)
+        .await
+        .expect("Failed to add definition");
+
+    let session_id = agent
+        .create_session()
+        .await
+        .expect("Failed to create session");
+    let response = agent
+        .process_message(session_id, "I want a refund".to_string())
+        .await
+        .expect("Failed to process message");
+    assert!(response.message.starts_with("Refunds are issued"));
+
+    let journey_id = agent
+        .find_journey("Onboarding")
+        .await
+        .expect("Journey should be registered");
+    let state = agent
+        .start_journey(&session_id, &journey_id)
+        .await
+        .expect("Failed to start journey");
+    assert!(!state.is_complete);
+}
+
+// Loading a file reports every problem with its location
+#[tokio::test]
+async fn test_from_config_file_reports_all_issues() {
+    let path = std::env::temp_dir().join(format!("talk-agent-{}.toml", uuid::Uuid::new_v4()));
+    std::fs::write(
+        &path,
+        r#"
+name = "Broken"
+
+[[guidelines]]
+matches = "("
+response = "Bad pattern"
+tools = ["unknown"]
+"#,
+    )
+    .expect("Failed to write definition");
+
+    let result = Agent::from_config_file(&path, Vec::new()).await;
+    std::fs::remove_file(&path).ok();
+
+    let Err(error) = result else {
+        panic!("expected an invalid definition");
+    };
+    let message = error.to_string();
+    let file = path.display().to_string();
+    assert!(message.contains(&format!("{}:guidelines[0].matches", file)));
+    assert!(message.contains(&format!("{}:guidelines[0].tools[0]", file)));
+}
+
+#[tokio::test]
+async fn test_scripted_provider_drives_llm_guidelines() {
+    let provider = talk::ScriptedProvider::new()
+        .with_error("rate limited")
+        .with_rule("refund", "Refunds take 5 business days.");
+    let agent = Agent::builder()
+        .name("Test Agent")
+        .provider(Box::new(provider.clone()))
+        .build()
+        .expect("Failed to build agent");
+    agent
+        .add_guideline(Guideline::new(
+            GuidelineCondition::Literal("refund".to_string()),
+            GuidelineAction::llm_with_template("Explain the refund policy"),
+            10,
+        ))
+        .await
+        .expect("Failed to add guideline");
+
+    let session_id = agent
+        .create_session()
+        .await
+        .expect("Failed to create session");
+
+    // The queued error comes first
+    let result = agent
+        .process_message(session_id, "I want a refund".to_string())
+        .await;
+    assert!(matches!(result, Err(talk::AgentError::LLMProvider(_))));
+
+    let response = agent
+        .process_message(session_id, "I want a refund".to_string())
+        .await
+        .expect("Failed to process message");
+    assert_eq!(response.message, "Refunds take 5 business days.");
+
+    let request = provider.last_request().expect("Provider was not called");
+    assert!(request
+        .iter()
+        .any(|m| m.content.contains("Explain the refund policy")));
+    assert_eq!(provider.request_count(), 2);
+}
+
+#[tokio::test]
+async fn test_recorded_conversation_replays_offline() {
+    let path = std::env::temp_dir().join(format!("talk-cassette-{}.json", talk::SessionId::new()));
+    let questions = ["Hi there", "What can you do?"];
+
+    let live = talk::ScriptedProvider::new()
+        .with_reply("Hello!")
+        .with_reply("I answer support questions.");
+    let agent = Agent::builder()
+        .name("Test Agent")
+        .provider(Box::new(talk::RecordingProvider::new(
+            Box::new(live),
+            &path,
+        )))
+        .build()
+        .expect("Failed to build agent");
+    let session_id = agent.create_session().await.unwrap();
+    let mut recorded = Vec::new();
+    for question in questions {
+        let response = agent
+            .process_message(session_id, question.to_string())
+            .await
+            .expect("Failed to process message");
+        recorded.push(response.message);
+    }
+
+    let replay = talk::ReplayProvider::from_file(&path).expect("Failed to load cassette");
+    std::fs::remove_file(&path).ok();
+    let agent = Agent::builder()
+        .name("Test Agent")
+        .provider(Box::new(replay))
+        .build()
+        .expect("Failed to build agent");
+    let session_id = agent.create_session().await.unwrap();
+    for (question, expected) in questions.iter().zip(&recorded) {
+        let response = agent
+            .process_message(session_id, question.to_string())
+            .await
+            .expect("Failed to replay message");
+        assert_eq!(&response.message, expected);
+    }
+
+    // A conversation that was never recorded fails
+    let result = agent
+        .process_message(session_id, "Something new".to_string())
+        .await;
+    assert!(matches!(result, Err(talk::AgentError::LLMProvider(_))));
+}
+
 // Helper function to create test agent
 async fn create_test_agent() -> Agent {
     // Create a mock provider for testing
@@ -156,6 +467,7 @@ async fn create_test_agent() -> Agent {
             default_tool_timeout: Duration::from_secs(30),
             enable_explainability: true,
             log_level: talk::LogLevel::Debug,
+            ..Default::default()
         })
         .build()
         .expect("Failed to build agent")
@@ -191,6 +503,38 @@ impl talk::LLMProvider for MockProvider {
     }
 }
 
+// Mock provider that takes a while to answer, to overlap concurrent requests
+struct SlowProvider {
+    inner: MockProvider,
+    delay: Duration,
+}
+
+#[async_trait::async_trait]
+impl talk::LLMProvider for SlowProvider {
+    async fn complete(&self, messages: Vec<talk::Message>) -> Result<String, talk::AgentError> {
+        tokio::time::sleep(self.delay).await;
+        self.inner.complete(messages).await
+    }
+
+    async fn stream(
+        &self,
+        messages: Vec<talk::Message>,
+    ) -> Result<
+        std::pin::Pin<Box<dyn futures::Stream<Item = Result<String, talk::AgentError>> + Send>>,
+        talk::AgentError,
+    > {
+        self.inner.stream(messages).await
+    }
+
+    fn name(&self) -> &str {
+        "SlowProvider"
+    }
+
+    fn config(&self) -> &talk::ProviderConfig {
+        self.inner.config()
+    }
+}
+
 fn create_mock_provider() -> MockProvider {
     MockProvider {
         config: talk::ProviderConfig {
@@ -203,3 +547,48 @@ fn create_mock_provider() -> MockProvider {
         },
     }
 }
+
+// Tool referenced by name from the example definition
+struct OrderTool {
+    id: talk::ToolId,
+    parameters: HashMap<String, talk::ParameterSchema>,
+}
+
+impl OrderTool {
+    fn new() -> Self {
+        Self {
+            id: talk::ToolId::new(),
+            parameters: HashMap::new(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl talk::Tool for OrderTool {
+    fn id(&self) -> &talk::ToolId {
+        &self.id
+    }
+
+    fn name(&self) -> &str {
+        "lookup_order"
+    }
+
+    fn description(&self) -> &str {
+        "Look up an order's status"
+    }
+
+    fn parameters(&self) -> &HashMap<String, talk::ParameterSchema> {
+        &self.parameters
+    }
+
+    async fn execute(
+        &self,
+        _parameters: HashMap<String, serde_json::Value>,
+    ) -> Result<talk::ToolResult, talk::AgentError> {
+        Ok(talk::ToolResult {
+            output: serde_json::json!({"status": "shipped"}),
+            error: None,
+            metadata: HashMap::new(),
+        })
+    }
+}
@@ -6,7 +6,7 @@ use chrono::Utc;
 use std::collections::HashMap;
 use talk::{
     Context, DefaultGuidelineMatcher, Guideline, GuidelineAction, GuidelineCondition, GuidelineId,
-    GuidelineMatch, GuidelineMatcher, Message, MessageRole, SessionId,
+    GuidelineMatcher,
 };
 
 // T020: Contract test for Guideline API
@@ -21,8 +21,14 @@ async fn test_guideline_api_contract() {
         .await
         .expect("Failed to add guideline");
 
-    // GuidelineId is always valid after creation
-    assert!(true, "Should return valid guideline ID");
+    // The returned ID refers to the registered guideline
+    assert!(
+        matcher
+            .get_guidelines()
+            .iter()
+            .any(|g| g.id == guideline_id),
+        "Should return valid guideline ID"
+    );
 
     // Test match_guidelines
     let context = create_test_context();
@@ -51,11 +57,17 @@ async fn test_literal_condition_matching() {
             response_template: "Our pricing info".to_string(),
             requires_llm: false,
             parameters: vec![],
+            must_mention: vec![],
+            output_schema: None,
+            handoff: None,
+            escalate: false,
         },
         priority: 10,
         tools: vec![],
         parameters: HashMap::new(),
+        captures: vec![],
         created_at: Utc::now(),
+        enabled: true,
     };
 
     matcher
@@ -97,11 +109,17 @@ async fn test_regex_condition_matching() {
             response_template: "Cancellation process".to_string(),
             requires_llm: false,
             parameters: vec![],
+            must_mention: vec![],
+            output_schema: None,
+            handoff: None,
+            escalate: false,
         },
         priority: 10,
         tools: vec![],
         parameters: HashMap::new(),
+        captures: vec![],
         created_at: Utc::now(),
+        enabled: true,
     };
 
     matcher
@@ -146,11 +164,17 @@ async fn test_guideline_priority_resolution() {
             response_template: "Low priority response".to_string(),
             requires_llm: false,
             parameters: vec![],
+            must_mention: vec![],
+            output_schema: None,
+            handoff: None,
+            escalate: false,
         },
         priority: 5,
         tools: vec![],
         parameters: HashMap::new(),
+        captures: vec![],
         created_at: Utc::now(),
+        enabled: true,
     };
 
     // Add high priority guideline
@@ -161,11 +185,17 @@ async fn test_guideline_priority_resolution() {
             response_template: "High priority response".to_string(),
             requires_llm: false,
             parameters: vec![],
+            must_mention: vec![],
+            output_schema: None,
+            handoff: None,
+            escalate: false,
         },
         priority: 20,
         tools: vec![],
         parameters: HashMap::new(),
+        captures: vec![],
         created_at: Utc::now(),
+        enabled: true,
     };
 
     matcher
@@ -206,11 +236,17 @@ fn create_test_guideline(keyword: &str, response: &str) -> Guideline {
             response_template: response.to_string(),
             requires_llm: false,
             parameters: vec![],
+            must_mention: vec![],
+            output_schema: None,
+            handoff: None,
+            escalate: false,
         },
         priority: 10,
         tools: vec![],
         parameters: HashMap::new(),
+        captures: vec![],
         created_at: Utc::now(),
+        enabled: true,
     }
 }
 
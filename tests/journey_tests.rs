@@ -9,12 +9,12 @@
 //! - T069: end_journey - Journey termination
 //! - T070: add_journey - Journey registration
 
+use std::sync::Arc;
+use std::time::Duration;
 use talk::{
-    Agent, AgentConfig, Journey, JourneyId, JourneyManager, JourneyState, JourneyStep,
-    LogLevel, OpenAIProvider, SessionId, StepId, Transition, TransitionCondition,
+    Agent, AgentConfig, InMemorySessionStore, Journey, JourneyId, JourneyStep, LogLevel,
+    OpenAIProvider, SessionStore, StepId, Transition, TransitionCondition,
 };
-use std::collections::HashMap;
-use std::time::Duration;
 
 // Helper: Create a test agent with OpenAI provider
 fn create_test_agent() -> Agent {
@@ -22,7 +22,7 @@ fn create_test_agent() -> Agent {
         .name("Test Journey Agent")
         .description("Agent for testing journey functionality")
         .provider(Box::new(
-            OpenAIProvider::new("test-api-key")
+            OpenAIProvider::new("test-api-key", "gpt-3.5-turbo")
                 .with_model("gpt-3.5-turbo")
                 .with_temperature(0.7),
         ))
@@ -31,6 +31,7 @@ fn create_test_agent() -> Agent {
             default_tool_timeout: Duration::from_secs(30),
             enable_explainability: true,
             log_level: LogLevel::Info,
+            ..Default::default()
         })
         .build()
         .expect("Failed to build test agent")
@@ -56,6 +57,7 @@ fn create_onboarding_journey() -> Journey {
                     next_step: step2_id,
                 }],
                 actions: vec![],
+                captures: vec![],
             },
             JourneyStep {
                 id: step2_id,
@@ -64,11 +66,13 @@ fn create_onboarding_journey() -> Journey {
                 expected_response: None,
                 transitions: vec![],
                 actions: vec!["complete_onboarding".to_string()],
+                captures: vec![],
             },
         ],
         initial_step: step1_id,
         current_step: None,
         created_at: chrono::Utc::now(),
+        enabled: true,
     }
 }
 
@@ -99,6 +103,7 @@ fn create_conditional_journey() -> Journey {
                     },
                 ],
                 actions: vec![],
+                captures: vec![],
             },
             JourneyStep {
                 id: step2_yes_id,
@@ -107,6 +112,7 @@ fn create_conditional_journey() -> Journey {
                 expected_response: None,
                 transitions: vec![],
                 actions: vec!["proceed".to_string()],
+                captures: vec![],
             },
             JourneyStep {
                 id: step2_no_id,
@@ -115,11 +121,13 @@ fn create_conditional_journey() -> Journey {
                 expected_response: None,
                 transitions: vec![],
                 actions: vec!["cancel".to_string()],
+                captures: vec![],
             },
         ],
         initial_step: step1_id,
         current_step: None,
         created_at: chrono::Utc::now(),
+        enabled: true,
     }
 }
 
@@ -133,7 +141,7 @@ fn create_conditional_journey() -> Journey {
 /// - Journey state is stored in session context
 #[tokio::test]
 async fn test_start_journey() {
-    let mut agent = create_test_agent();
+    let agent = create_test_agent();
     let journey = create_onboarding_journey();
     let journey_id = journey.id;
     let initial_step_id = journey.initial_step;
@@ -182,7 +190,7 @@ async fn test_start_journey() {
 /// - Final step marks journey as complete
 #[tokio::test]
 async fn test_process_step() {
-    let mut agent = create_test_agent();
+    let agent = create_test_agent();
     let journey = create_onboarding_journey();
     let journey_id = journey.id;
     let step1_id = journey.steps[0].id;
@@ -253,7 +261,7 @@ async fn test_process_step() {
 /// - State includes journey_id, current_step, completed_steps, is_complete
 #[tokio::test]
 async fn test_get_state() {
-    let mut agent = create_test_agent();
+    let agent = create_test_agent();
     let journey = create_onboarding_journey();
     let journey_id = journey.id;
 
@@ -302,7 +310,7 @@ async fn test_get_state() {
 /// - Journey can be restarted after ending
 #[tokio::test]
 async fn test_end_journey() {
-    let mut agent = create_test_agent();
+    let agent = create_test_agent();
     let journey = create_onboarding_journey();
     let journey_id = journey.id;
 
@@ -359,10 +367,9 @@ async fn test_end_journey() {
 /// - Journey validation (no circular dependencies, valid initial_step)
 #[tokio::test]
 async fn test_add_journey() {
-    let mut agent = create_test_agent();
+    let agent = create_test_agent();
     let journey = create_onboarding_journey();
     let journey_id = journey.id;
-    let journey_name = journey.name.clone();
 
     // Add journey
     let returned_id = agent
@@ -392,7 +399,7 @@ async fn test_add_journey() {
 /// - ContextVariable conditions access session context
 #[tokio::test]
 async fn test_conditional_transitions() {
-    let mut agent = create_test_agent();
+    let agent = create_test_agent();
     let journey = create_conditional_journey();
     let journey_id = journey.id;
     let step_yes_id = journey.steps[1].id;
@@ -445,7 +452,7 @@ async fn test_conditional_transitions() {
 /// - Reject journeys with unreachable steps
 #[tokio::test]
 async fn test_journey_validation() {
-    let mut agent = create_test_agent();
+    let agent = create_test_agent();
 
     // Test 1: Invalid initial_step
     let invalid_initial_step = Journey {
@@ -459,10 +466,12 @@ async fn test_journey_validation() {
             expected_response: None,
             transitions: vec![],
             actions: vec![],
+            captures: vec![],
         }],
         initial_step: StepId::new(), // Different ID, not in steps
         current_step: None,
         created_at: chrono::Utc::now(),
+        enabled: true,
     };
 
     let result = agent.add_journey(invalid_initial_step).await;
@@ -487,6 +496,7 @@ async fn test_journey_validation() {
                     next_step: step2_id,
                 }],
                 actions: vec![],
+                captures: vec![],
             },
             JourneyStep {
                 id: step2_id,
@@ -498,13 +508,124 @@ async fn test_journey_validation() {
                     next_step: step1_id, // Circular!
                 }],
                 actions: vec![],
+                captures: vec![],
             },
         ],
         initial_step: step1_id,
         current_step: None,
         created_at: chrono::Utc::now(),
+        enabled: true,
     };
 
     let result = agent.add_journey(circular_journey).await;
     assert!(result.is_err());
 }
+
+/// Additional Test: Journeys driven by process_message
+///
+/// Requirement: process_message must advance the session's active journey
+///
+/// Acceptance Criteria:
+/// - The next step's prompt is returned as the response
+/// - AgentResponse.journey_step reports the new step
+/// - Journey state is updated without calling process_journey_step
+#[tokio::test]
+async fn test_process_message_advances_journey() {
+    let agent = create_test_agent();
+    let journey = create_onboarding_journey();
+    let journey_id = journey.id;
+    let step1_id = journey.steps[0].id;
+    let step2_id = journey.steps[1].id;
+
+    agent
+        .add_journey(journey)
+        .await
+        .expect("Failed to add journey");
+
+    let session_id = agent.create_session().await.expect("Failed to create session");
+    agent
+        .start_journey(&session_id, &journey_id)
+        .await
+        .expect("Failed to start journey");
+
+    let response = agent
+        .process_message(session_id, "My name is Alice".to_string())
+        .await
+        .expect("Failed to process message");
+
+    assert_eq!(response.message, "Nice to meet you! Ready to start?");
+    assert_eq!(response.journey_step, Some(step2_id));
+    assert!(response.matched_guideline.is_none());
+
+    let state = agent
+        .get_journey_state(&session_id)
+        .await
+        .expect("Failed to get journey state")
+        .expect("Journey state not found");
+
+    assert_eq!(state.current_step, step2_id);
+    assert!(state.completed_steps.contains(&step1_id));
+    assert!(!state.is_complete);
+}
+
+#[tokio::test]
+async fn test_journey_state_persisted_in_session_store() {
+    let store: Arc<dyn SessionStore> = Arc::new(InMemorySessionStore::new());
+    let journey = create_onboarding_journey();
+    let journey_id = journey.id;
+    let step2_id = journey.steps[1].id;
+
+    let build_agent = |store: Arc<dyn SessionStore>| {
+        Agent::builder()
+            .name("Persistent Journey Agent")
+            .provider(Box::new(OpenAIProvider::new(
+                "test-api-key",
+                "gpt-3.5-turbo",
+            )))
+            .session_store(store)
+            .build()
+            .expect("Failed to build agent")
+    };
+
+    let first_agent = build_agent(store.clone());
+    first_agent
+        .add_journey(journey.clone())
+        .await
+        .expect("Failed to add journey");
+
+    let session_id = first_agent
+        .create_session()
+        .await
+        .expect("Failed to create session");
+    first_agent
+        .start_journey(&session_id, &journey_id)
+        .await
+        .expect("Failed to start journey");
+    first_agent
+        .process_message(session_id, "My name is Alice".to_string())
+        .await
+        .expect("Failed to process message");
+
+    let session = store
+        .get(&session_id)
+        .await
+        .expect("Failed to load session")
+        .expect("Session not found");
+    let stored_state = session.journey_state.expect("Journey state not stored");
+    assert_eq!(stored_state.current_step, step2_id);
+
+    // A second agent sharing the store picks up where the first left off
+    let second_agent = build_agent(store);
+    second_agent
+        .add_journey(journey)
+        .await
+        .expect("Failed to add journey");
+
+    let state = second_agent
+        .get_journey_state(&session_id)
+        .await
+        .expect("Failed to get journey state")
+        .expect("Journey state not found");
+    assert_eq!(state, stored_state);
+    assert_eq!(state.step_history.len(), 2);
+}
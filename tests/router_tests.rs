@@ -0,0 +1,233 @@
+//! Integration tests for AgentRouter
+//!
+//! Tests routing sessions by guideline match and LLM classifier, handoff
+//! between agents, and the shared session context.
+
+use std::sync::Arc;
+use talk::router::{ACTIVE_AGENT_KEY, AGENT_TURNS_KEY};
+use talk::*;
+
+/// Provider that always answers with the same text
+struct FixedProvider {
+    response: String,
+    config: ProviderConfig,
+}
+
+impl FixedProvider {
+    fn new(response: &str) -> Self {
+        Self {
+            response: response.to_string(),
+            config: ProviderConfig::new("fixed"),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl LLMProvider for FixedProvider {
+    async fn complete(&self, _messages: Vec<Message>) -> Result<String> {
+        Ok(self.response.clone())
+    }
+
+    async fn stream(
+        &self,
+        _messages: Vec<Message>,
+    ) -> Result<std::pin::Pin<Box<dyn futures::Stream<Item = Result<String>> + Send>>> {
+        unimplemented!("Stream not needed for tests")
+    }
+
+    fn name(&self) -> &str {
+        "FixedProvider"
+    }
+
+    fn config(&self) -> &ProviderConfig {
+        &self.config
+    }
+}
+
+async fn create_agent(
+    name: &str,
+    store: &Arc<dyn SessionStore>,
+    guidelines: Vec<(&str, GuidelineAction)>,
+) -> Agent {
+    let agent = Agent::builder()
+        .name(name)
+        .description(format!("Handles {} questions", name))
+        .provider(Box::new(FixedProvider::new(&format!("{} says hi", name))))
+        .session_store(store.clone())
+        .build()
+        .expect("Failed to build agent");
+
+    for (keyword, action) in guidelines {
+        agent
+            .add_guideline(Guideline::new(
+                GuidelineCondition::Literal(keyword.to_string()),
+                action,
+                10,
+            ))
+            .await
+            .expect("Failed to add guideline");
+    }
+    agent
+}
+
+async fn create_router(classifier: Option<&str>) -> AgentRouter {
+    let store: Arc<dyn SessionStore> = Arc::new(InMemorySessionStore::new());
+    let sales = create_agent(
+        "sales",
+        &store,
+        vec![
+            ("pricing", GuidelineAction::template("Plans start at $49.")),
+            (
+                "invoice",
+                GuidelineAction::template("Let me transfer you to billing.")
+                    .with_handoff("billing"),
+            ),
+        ],
+    )
+    .await;
+    let billing = create_agent(
+        "billing",
+        &store,
+        vec![("refund", GuidelineAction::template("Refunds take 5 days."))],
+    )
+    .await;
+    let technical = create_agent(
+        "technical",
+        &store,
+        vec![(
+            "error",
+            GuidelineAction::template("Try restarting the app."),
+        )],
+    )
+    .await;
+
+    let mut builder = AgentRouter::builder()
+        .agent(sales)
+        .agent(billing)
+        .agent(technical)
+        .default_agent("sales");
+    if let Some(answer) = classifier {
+        builder = builder.classifier(Box::new(FixedProvider::new(answer)));
+    }
+    builder.build().expect("Failed to build router")
+}
+
+#[tokio::test]
+async fn test_session_is_routed_by_guideline_match() {
+    let router = create_router(None).await;
+    let session_id = router.create_session().await.unwrap();
+
+    let routed = router
+        .process_message(session_id, "I need a refund".to_string())
+        .await
+        .unwrap();
+    assert_eq!(routed.agent, "billing");
+    assert_eq!(routed.response.message, "Refunds take 5 days.");
+
+    // The session stays with billing even when another agent would match
+    let routed = router
+        .process_message(session_id, "And what about pricing?".to_string())
+        .await
+        .unwrap();
+    assert_eq!(routed.agent, "billing");
+    assert_eq!(routed.response.message, "billing says hi");
+
+    let session = router.get_session(&session_id).await.unwrap().unwrap();
+    assert_eq!(session.metadata[ACTIVE_AGENT_KEY], "billing");
+    assert_eq!(
+        session.metadata[AGENT_TURNS_KEY],
+        serde_json::json!(["billing", "billing"])
+    );
+}
+
+#[tokio::test]
+async fn test_unmatched_session_uses_classifier_or_default() {
+    let router = create_router(Some("Technical.")).await;
+    let session_id = router.create_session().await.unwrap();
+    let routed = router
+        .process_message(session_id, "Hello there".to_string())
+        .await
+        .unwrap();
+    assert_eq!(routed.agent, "technical");
+
+    let router = create_router(None).await;
+    let session_id = router.create_session().await.unwrap();
+    let routed = router
+        .process_message(session_id, "Hello there".to_string())
+        .await
+        .unwrap();
+    assert_eq!(routed.agent, "sales");
+}
+
+#[tokio::test]
+async fn test_guideline_handoff_keeps_shared_context() {
+    let router = create_router(None).await;
+    let session_id = router.create_session().await.unwrap();
+
+    let routed = router
+        .process_message(session_id, "What's the pricing?".to_string())
+        .await
+        .unwrap();
+    assert_eq!(routed.agent, "sales");
+
+    let routed = router
+        .process_message(session_id, "I have a question about my invoice".to_string())
+        .await
+        .unwrap();
+    assert_eq!(routed.agent, "sales");
+    assert_eq!(routed.response.handoff.as_deref(), Some("billing"));
+    assert_eq!(
+        router.active_agent(&session_id).await.unwrap().as_deref(),
+        Some("billing")
+    );
+
+    let routed = router
+        .process_message(session_id, "Can I get a refund?".to_string())
+        .await
+        .unwrap();
+    assert_eq!(routed.agent, "billing");
+    assert_eq!(routed.response.message, "Refunds take 5 days.");
+
+    let session = router.get_session(&session_id).await.unwrap().unwrap();
+    assert_eq!(session.context.messages.len(), 6);
+    assert_eq!(
+        session.metadata[AGENT_TURNS_KEY],
+        serde_json::json!(["sales", "sales", "billing"])
+    );
+}
+
+#[tokio::test]
+async fn test_explicit_handoff() {
+    let router = create_router(None).await;
+    let session_id = router.create_session().await.unwrap();
+
+    router.handoff(&session_id, "technical").await.unwrap();
+    let routed = router
+        .process_message(session_id, "I need a refund".to_string())
+        .await
+        .unwrap();
+    assert_eq!(routed.agent, "technical");
+
+    assert!(matches!(
+        router.handoff(&session_id, "legal").await,
+        Err(AgentError::InvalidInput(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_agents_must_share_a_session_store() {
+    let first: Arc<dyn SessionStore> = Arc::new(InMemorySessionStore::new());
+    let second: Arc<dyn SessionStore> = Arc::new(InMemorySessionStore::new());
+
+    let result = AgentRouter::builder()
+        .agent(create_agent("sales", &first, vec![]).await)
+        .agent(create_agent("billing", &second, vec![]).await)
+        .build();
+    assert!(matches!(result, Err(AgentError::Configuration(_))));
+
+    let result = AgentRouter::builder()
+        .agent(create_agent("sales", &first, vec![]).await)
+        .agent(create_agent("sales", &first, vec![]).await)
+        .build();
+    assert!(matches!(result, Err(AgentError::Configuration(_))));
+}
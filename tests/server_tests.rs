@@ -0,0 +1,166 @@
+//! Integration tests for the HTTP server
+//!
+//! Each test serves an agent on a random local port and talks to it with
+//! reqwest.
+
+use serde_json::{json, Value};
+use std::sync::Arc;
+use talk::*;
+
+/// Serve an agent answering from `provider`, returning the base URL
+async fn serve(provider: ScriptedProvider) -> String {
+    let agent = Agent::builder()
+        .name("Support")
+        .provider(Box::new(provider))
+        .build()
+        .unwrap();
+    agent
+        .add_guideline(Guideline::new(
+            GuidelineCondition::Literal("refund".to_string()),
+            GuidelineAction::template("Refunds take 5 days."),
+            10,
+        ))
+        .await
+        .unwrap();
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(talk::server::serve(listener, Arc::new(agent)));
+    format!("http://{}", address)
+}
+
+async fn create_session(client: &reqwest::Client, base: &str) -> String {
+    let response = client
+        .post(format!("{}/sessions", base))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 201);
+    let body: Value = response.json().await.unwrap();
+    body["session_id"].as_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn test_session_lifecycle_and_messages() {
+    let base = serve(ScriptedProvider::new()).await;
+    let client = reqwest::Client::new();
+
+    let health: Value = client
+        .get(format!("{}/health", base))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(health, json!({ "status": "ok", "agent": "Support" }));
+
+    let session_id = create_session(&client, &base).await;
+    let response = client
+        .post(format!("{}/sessions/{}/messages", base, session_id))
+        .json(&json!({ "message": "Can I get a refund?", "idempotency_key": "req-1" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let body: AgentResponse = response.json().await.unwrap();
+    assert_eq!(body.message, "Refunds take 5 days.");
+
+    let session: Session = client
+        .get(format!("{}/sessions/{}", base, session_id))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(session.context.messages.len(), 2);
+
+    let journey: Value = client
+        .get(format!("{}/sessions/{}/journey", base, session_id))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(journey, Value::Null);
+
+    let response = client
+        .delete(format!("{}/sessions/{}", base, session_id))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 204);
+
+    // Ended sessions reject messages with 409 Conflict
+    let response = client
+        .post(format!("{}/sessions/{}/messages", base, session_id))
+        .json(&json!({ "message": "Anyone there?" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 409);
+    let body: Value = response.json().await.unwrap();
+    assert!(body["error"].as_str().unwrap().contains("completed"));
+}
+
+#[tokio::test]
+async fn test_errors_map_to_status_codes() {
+    let base = serve(ScriptedProvider::new()).await;
+    let client = reqwest::Client::new();
+
+    let unknown = SessionId::new();
+    let response = client
+        .get(format!("{}/sessions/{}", base, unknown))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+
+    // The scripted provider has no reply, so the LLM call fails
+    let session_id = create_session(&client, &base).await;
+    let response = client
+        .post(format!("{}/sessions/{}/messages", base, session_id))
+        .json(&json!({ "message": "Tell me a joke" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 502);
+}
+
+#[tokio::test]
+async fn test_stream_sends_events_as_sse() {
+    let base = serve(ScriptedProvider::new().with_default("It is sunny")).await;
+    let client = reqwest::Client::new();
+    let session_id = create_session(&client, &base).await;
+
+    let response = client
+        .post(format!("{}/sessions/{}/messages/stream", base, session_id))
+        .json(&json!({ "message": "What's the weather?" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(
+        response.headers()["content-type"].to_str().unwrap(),
+        "text/event-stream"
+    );
+
+    let body = response.text().await.unwrap();
+    let events: Vec<&str> = body
+        .lines()
+        .filter_map(|line| line.strip_prefix("event: "))
+        .collect();
+    assert_eq!(events.first(), Some(&"guideline_matched"));
+    assert_eq!(events.last(), Some(&"completed"));
+    assert!(events.contains(&"text_delta"));
+
+    let completed = body
+        .lines()
+        .rev()
+        .find_map(|line| line.strip_prefix("data: "))
+        .unwrap();
+    let response: AgentResponse = serde_json::from_str(completed).unwrap();
+    assert_eq!(response.message, "It is sunny");
+}
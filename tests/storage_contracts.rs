@@ -2,8 +2,10 @@
 //!
 //! These tests verify that all SessionStore implementations comply with the expected contract.
 
+use chrono::{Duration, Utc};
+use talk::error::StorageError;
 use talk::storage::SessionStore;
-use talk::types::AgentId;
+use talk::types::{AgentId, SessionId};
 use talk::{InMemorySessionStore, Session};
 
 /// Test the contract for SessionStore::create
@@ -114,6 +116,39 @@ async fn test_session_store_update_contract() {
     );
 }
 
+/// Test the compare-and-swap contract for SessionStore::update
+///
+/// This test verifies that:
+/// - Each successful update increments the stored version
+/// - Updating from a stale copy returns a conflict error
+#[tokio::test]
+async fn test_session_store_update_version_contract() {
+    let store = InMemorySessionStore::new();
+    let session = Session::new(AgentId::new());
+    let session_id = session.id;
+    store.create(session.clone()).await.unwrap();
+
+    store.update(&session_id, session.clone()).await.unwrap();
+    let retrieved = store.get(&session_id).await.unwrap().unwrap();
+    assert_eq!(
+        retrieved.version, 1,
+        "SessionStore::update should increment the version"
+    );
+
+    // `session` still has version 0
+    let result = store.update(&session_id, session).await;
+    assert!(
+        matches!(result, Err(talk::StorageError::Conflict { .. })),
+        "SessionStore::update should reject stale versions"
+    );
+
+    let result = store.update(&session_id, retrieved).await;
+    assert!(
+        result.is_ok(),
+        "SessionStore::update should accept the current version"
+    );
+}
+
 /// Test the contract for SessionStore::delete
 ///
 /// This test verifies that:
@@ -214,9 +249,8 @@ async fn test_session_store_exists_contract() {
         result.is_ok(),
         "SessionStore::exists should not return error"
     );
-    assert_eq!(
-        result.unwrap(),
-        false,
+    assert!(
+        !result.unwrap(),
         "SessionStore::exists should return false for non-existent session"
     );
 
@@ -229,13 +263,86 @@ async fn test_session_store_exists_contract() {
         result.is_ok(),
         "SessionStore::exists should not return error"
     );
-    assert_eq!(
+    assert!(
         result.unwrap(),
-        true,
         "SessionStore::exists should return true for existing session"
     );
 }
 
+/// Store that only implements the required methods, to exercise the defaults
+struct BasicStore(InMemorySessionStore);
+
+#[async_trait::async_trait]
+impl SessionStore for BasicStore {
+    async fn create(&self, session: Session) -> Result<SessionId, StorageError> {
+        self.0.create(session).await
+    }
+
+    async fn get(&self, id: &SessionId) -> Result<Option<Session>, StorageError> {
+        self.0.get(id).await
+    }
+
+    async fn update(&self, id: &SessionId, session: Session) -> Result<(), StorageError> {
+        self.0.update(id, session).await
+    }
+
+    async fn delete(&self, id: &SessionId) -> Result<(), StorageError> {
+        self.0.delete(id).await
+    }
+
+    async fn list(&self) -> Result<Vec<SessionId>, StorageError> {
+        self.0.list().await
+    }
+}
+
+/// Test the contract for SessionStore::cleanup_expired
+///
+/// This test verifies that:
+/// - Sessions that expired longer ago than the retention are deleted
+/// - Recently expired, unexpired and non-expiring sessions are kept
+/// - The default implementation behaves like the in-memory override
+#[tokio::test]
+async fn test_session_store_cleanup_expired_contract() {
+    let stores: Vec<Box<dyn SessionStore>> = vec![
+        Box::new(InMemorySessionStore::new()),
+        Box::new(BasicStore(InMemorySessionStore::new())),
+    ];
+
+    for store in stores {
+        let now = Utc::now();
+        let long_expired = Session::new(AgentId::new()).with_expiration(now - Duration::hours(2));
+        let recently_expired =
+            Session::new(AgentId::new()).with_expiration(now - Duration::minutes(10));
+        let active = Session::new(AgentId::new()).with_expiration(now + Duration::hours(1));
+        let long_expired_id = long_expired.id;
+
+        store.create(long_expired).await.unwrap();
+        store.create(recently_expired).await.unwrap();
+        store.create(active).await.unwrap();
+        store.create(Session::new(AgentId::new())).await.unwrap();
+
+        let deleted = store
+            .cleanup_expired(std::time::Duration::from_secs(3600))
+            .await
+            .unwrap();
+        assert_eq!(
+            deleted, 1,
+            "Only sessions past the retention should be deleted"
+        );
+        assert!(!store.exists(&long_expired_id).await.unwrap());
+        assert_eq!(store.list().await.unwrap().len(), 3);
+
+        let deleted = store
+            .cleanup_expired(std::time::Duration::ZERO)
+            .await
+            .unwrap();
+        assert_eq!(
+            deleted, 1,
+            "Expired sessions should be deleted without retention"
+        );
+    }
+}
+
 /// Test concurrent operations on SessionStore
 ///
 /// This test verifies that:
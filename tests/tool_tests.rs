@@ -595,6 +595,7 @@ async fn test_default_timeout_from_config() {
         default_tool_timeout: Duration::from_secs(2),
         enable_explainability: true,
         log_level: LogLevel::Info,
+        ..Default::default()
     };
 
     // Verify config has the timeout configured